@@ -1,6 +1,8 @@
 MEMORY
 {
   /* These values correspond to the NRF5340 */
-  FLASH : ORIGIN = 0x00000000, LENGTH = 1016K
+  /* 0x000A_0000..0x000E_0000 holds the track log and */
+  /* 0x000E_0000..0x0010_0000 holds the settings map. */
+  FLASH : ORIGIN = 0x00000000, LENGTH = 640K
   RAM : ORIGIN = 0x20000000, LENGTH = 256K
 }
@@ -0,0 +1,249 @@
+use embassy_sync::{blocking_mutex::raw::NoopRawMutex, mutex::Mutex};
+use embassy_time::Timer;
+use heapless::Vec;
+
+use crate::{
+    SharedState,
+    flash::ProjTrackLog,
+    gps::{
+        fns::LatLonAlt,
+        route::Route,
+        waypoint::{Waypoint, WaypointList},
+    },
+    settings::{
+        config::{ProjNVMCStorage, store_setting},
+        route::{remove_route, store_route},
+        settings::{SettingsState, UNITS_SETTING_ID, number_setting},
+        waypoints::{remove_waypoint, store_waypoint},
+    },
+    shell::backend::{BackendError, GpsStatus, SettingInfo, ShellBackend},
+    track::{
+        log::{SessionEntry, SessionSummary},
+        record::TrackPoint,
+    },
+    utils::units::Units,
+};
+
+/// Records copied out of the track log per lock. The shell writes each batch
+/// to the UART with the lock released, so the log task keeps recording.
+const DUMP_BATCH: usize = 32;
+
+/// The shell's view of the device: the track log, settings, waypoints and
+/// route behind their mutexes, and the state shared with the main loop.
+pub struct DeviceBackend {
+    pub track_log: &'static Mutex<NoopRawMutex, ProjTrackLog>,
+    pub shared_state: &'static Mutex<NoopRawMutex, SharedState>,
+    pub settings_state: &'static Mutex<NoopRawMutex, SettingsState>,
+    pub settings_storage: &'static Mutex<NoopRawMutex, ProjNVMCStorage>,
+    pub waypoints: &'static Mutex<NoopRawMutex, WaypointList>,
+    pub route: &'static Mutex<NoopRawMutex, Route>,
+}
+
+impl ShellBackend for DeviceBackend {
+    async fn sessions(
+        &mut self,
+        mut f: impl AsyncFnMut(SessionSummary),
+    ) -> Result<(), BackendError> {
+        let mut batch = Vec::<SessionSummary, DUMP_BATCH>::new();
+        let mut skip = 0;
+        loop {
+            self.track_log
+                .lock()
+                .await
+                .sessions_batch(skip, &mut batch)
+                .await?;
+            for summary in &batch {
+                f(*summary).await;
+            }
+            if !batch.is_full() {
+                return Ok(());
+            }
+            skip += batch.len();
+        }
+    }
+
+    async fn active_session(&mut self) -> Option<u16> {
+        self.track_log.lock().await.active_session()
+    }
+
+    async fn session_points(
+        &mut self,
+        session: u16,
+        mut f: impl AsyncFnMut(&TrackPoint),
+    ) -> Result<bool, BackendError> {
+        self.session_entries(session, async |entry: &SessionEntry| {
+            if let SessionEntry::Point(point) = entry {
+                f(point).await;
+            }
+        })
+        .await
+    }
+
+    async fn session_entries(
+        &mut self,
+        session: u16,
+        mut f: impl AsyncFnMut(&SessionEntry),
+    ) -> Result<bool, BackendError> {
+        let mut batch = Vec::<SessionEntry, DUMP_BATCH>::new();
+        let mut skip = 0;
+        loop {
+            let found = self
+                .track_log
+                .lock()
+                .await
+                .entries_batch(session, skip, &mut batch)
+                .await?;
+            if !found {
+                // Overwritten since the first batch counts as the end.
+                return Ok(skip > 0);
+            }
+            for entry in &batch {
+                f(entry).await;
+            }
+            if !batch.is_full() {
+                return Ok(true);
+            }
+            skip += batch.len();
+        }
+    }
+
+    async fn delete_session(&mut self, session: u16) -> Result<(), BackendError> {
+        let mut track_log = self.track_log.lock().await;
+        match track_log.delete_session(session).await? {
+            true => Ok(()),
+            false => Err(BackendError::NotFound),
+        }
+    }
+
+    async fn setting(&mut self, id: u8) -> Result<SettingInfo, BackendError> {
+        let settings = self.settings_state.lock().await;
+        settings.items[..settings.len]
+            .iter()
+            .find(|item| item.id() == Some(id))
+            .map(|item| SettingInfo {
+                id,
+                label: item.label(),
+                value: item.value_label(),
+            })
+            .ok_or(BackendError::NotFound)
+    }
+
+    async fn set_setting(&mut self, id: u8, value: &str) -> Result<SettingInfo, BackendError> {
+        let mut settings = self.settings_state.lock().await;
+        let len = settings.len;
+        let item = settings.items[..len]
+            .iter_mut()
+            .find(|item| item.id() == Some(id))
+            .ok_or(BackendError::NotFound)?;
+        if !item.select(value) {
+            return Err(BackendError::InvalidValue);
+        }
+        let mut storage = self.settings_storage.lock().await;
+        store_setting(&mut storage, item)
+            .await
+            .map_err(|_| BackendError::Storage)?;
+        Ok(SettingInfo {
+            id,
+            label: item.label(),
+            value: item.value_label(),
+        })
+    }
+
+    async fn waypoints(
+        &mut self,
+        mut f: impl AsyncFnMut(u8, &Waypoint),
+    ) -> Result<(), BackendError> {
+        let waypoints = self.waypoints.lock().await;
+        for (slot, waypoint) in waypoints.iter() {
+            f(slot as u8, waypoint).await;
+        }
+        Ok(())
+    }
+
+    async fn save_waypoint(&mut self, mut waypoint: Waypoint) -> Result<u8, BackendError> {
+        let mut waypoints = self.waypoints.lock().await;
+        if waypoint.name.is_empty() {
+            waypoint.name = waypoints.next_name();
+        }
+        let slot = waypoints
+            .insert(waypoint.clone())
+            .ok_or(BackendError::Full)?;
+        let mut storage = self.settings_storage.lock().await;
+        if store_waypoint(&mut storage, slot, &waypoint).await.is_err() {
+            waypoints.remove(slot);
+            return Err(BackendError::Storage);
+        }
+        Ok(slot as u8)
+    }
+
+    async fn delete_waypoint(&mut self, slot: u8) -> Result<(), BackendError> {
+        let mut waypoints = self.waypoints.lock().await;
+        if waypoints.get(slot.into()).is_none() {
+            return Err(BackendError::NotFound);
+        }
+        let mut storage = self.settings_storage.lock().await;
+        remove_waypoint(&mut storage, slot.into())
+            .await
+            .map_err(|_| BackendError::Storage)?;
+        waypoints.remove(slot.into());
+        Ok(())
+    }
+
+    async fn route_points(
+        &mut self,
+        mut f: impl AsyncFnMut(&LatLonAlt),
+    ) -> Result<(), BackendError> {
+        let route = self.route.lock().await;
+        for point in route.points() {
+            f(point).await;
+        }
+        Ok(())
+    }
+
+    async fn save_route(&mut self, route: &Route) -> Result<(), BackendError> {
+        let mut current = self.route.lock().await;
+        let mut storage = self.settings_storage.lock().await;
+        store_route(&mut storage, route)
+            .await
+            .map_err(|_| BackendError::Storage)?;
+        current.clone_from(route);
+        Ok(())
+    }
+
+    async fn delete_route(&mut self) -> Result<(), BackendError> {
+        let mut route = self.route.lock().await;
+        let mut storage = self.settings_storage.lock().await;
+        remove_route(&mut storage)
+            .await
+            .map_err(|_| BackendError::Storage)?;
+        route.clear();
+        Ok(())
+    }
+
+    async fn gps_status(&mut self) -> GpsStatus {
+        let units = {
+            let settings = self.settings_state.lock().await;
+            Units::from_setting(number_setting(&settings, UNITS_SETTING_ID).unwrap_or_default())
+        };
+        let lock = self.shared_state.lock().await;
+        GpsStatus {
+            fix: lock.last_fix,
+            position: lock.last_position,
+            speed_mps: lock.speed_mps,
+            units,
+            dop: lock.dop,
+            active_satellites: lock.active_satellites,
+            satellites_in_view: lock.satellites_in_view,
+            gps_stats: lock.gps_stats,
+            is_recording: lock.is_recording,
+            is_paused: lock.is_paused,
+        }
+    }
+
+    async fn reboot(&mut self) {
+        let _ = self.track_log.lock().await.end_session().await;
+        // Give the UART a moment to drain the final response.
+        Timer::after_millis(50).await;
+        cortex_m::peripheral::SCB::sys_reset();
+    }
+}
@@ -0,0 +1,28 @@
+use core::convert::Infallible;
+
+use embassy_nrf::saadc::Saadc;
+use hijo::utils::battery::BatterySource;
+
+/// The cell reaches AIN0 (P0.04) through two equal resistors.
+const DIVIDER: u32 = 2;
+/// Internal 0.6 V reference at 1/6 gain.
+const FULL_SCALE_MV: u32 = 3600;
+/// 12-bit samples.
+const FULL_SCALE_COUNTS: u32 = 4096;
+
+/// Battery voltage through the SAADC, one single-ended channel.
+pub struct SaadcBattery {
+    pub saadc: Saadc<'static, 1>,
+}
+
+impl BatterySource for SaadcBattery {
+    type Error = Infallible;
+
+    async fn read_mv(&mut self) -> Result<u16, Infallible> {
+        let mut buf = [0i16; 1];
+        self.saadc.sample(&mut buf).await;
+        // Noise around ground can come out slightly negative.
+        let counts = buf[0].max(0) as u32;
+        Ok((counts * FULL_SCALE_MV * DIVIDER / FULL_SCALE_COUNTS) as u16)
+    }
+}
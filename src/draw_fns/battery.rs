@@ -0,0 +1,74 @@
+use core::fmt::Write;
+
+use embedded_graphics::{
+    pixelcolor::BinaryColor,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::{Alignment, Text},
+};
+use heapless::String;
+
+use crate::{
+    draw_fns::constants::TEXT_STYLE_SM_INVERSE,
+    utils::battery::{BatteryLevel, BatteryStatus},
+};
+
+/// Icon body, right of the recording status.
+const ICON_TOP_LEFT: Point = Point::new(13, 2);
+const ICON_SIZE: Size = Size::new(12, 6);
+const BANNER_HEIGHT: u32 = 14;
+
+/// Battery outline filled in proportion to the charge. Blanked on every
+/// other call from the blink task once the battery is low.
+pub fn draw_battery<D>(display: &mut D, status: &BatteryStatus, blink: bool)
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    if status.level != BatteryLevel::Normal && !blink {
+        return;
+    }
+    let stroke = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+    let fill = PrimitiveStyle::with_fill(BinaryColor::On);
+    let _ = Rectangle::new(ICON_TOP_LEFT, ICON_SIZE)
+        .into_styled(stroke)
+        .draw(display);
+    let _ = Rectangle::new(
+        ICON_TOP_LEFT + Point::new(ICON_SIZE.width as i32, 2),
+        Size::new(1, ICON_SIZE.height - 4),
+    )
+    .into_styled(fill)
+    .draw(display);
+    let inner = ICON_SIZE.width - 4;
+    let width = (inner * u32::from(status.percent) + 50) / 100;
+    if width > 0 {
+        let _ = Rectangle::new(
+            ICON_TOP_LEFT + Point::new(2, 2),
+            Size::new(width, ICON_SIZE.height - 4),
+        )
+        .into_styled(fill)
+        .draw(display);
+    }
+}
+
+/// Banner across the top: the charge left when low, a shutdown notice when
+/// critical.
+pub fn draw_battery_warning<D>(display: &mut D, status: &BatteryStatus)
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let _ = Rectangle::new(Point::zero(), Size::new(128, BANNER_HEIGHT))
+        .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+        .draw(display);
+    let mut text: String<24> = String::new();
+    let _ = match status.level {
+        BatteryLevel::Critical => write!(text, "BATTERY EMPTY"),
+        _ => write!(text, "LOW BATTERY {}%", status.percent),
+    };
+    let _ = Text::with_alignment(
+        &text,
+        Point::new(64, 10),
+        TEXT_STYLE_SM_INVERSE,
+        Alignment::Center,
+    )
+    .draw(display);
+}
@@ -14,3 +14,6 @@ pub const TEXT_STYLE_SM: MonoTextStyle<'_, BinaryColor> =
     MonoTextStyle::new(&FONT_5X8, BinaryColor::On);
 pub const TEXT_STYLE_XS: MonoTextStyle<'_, BinaryColor> =
     MonoTextStyle::new(&FONT_4X6, BinaryColor::On);
+/// Dark text, for drawing on a lit background.
+pub const TEXT_STYLE_SM_INVERSE: MonoTextStyle<'_, BinaryColor> =
+    MonoTextStyle::new(&FONT_5X8, BinaryColor::Off);
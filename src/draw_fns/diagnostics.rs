@@ -0,0 +1,27 @@
+use core::fmt::Write;
+
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*, text::Text};
+use heapless::String;
+
+use crate::{draw_fns::constants::TEXT_STYLE_XS, gps::reader::GpsReaderStats};
+
+pub fn draw_diagnostics<D>(display: &mut D, stats: &GpsReaderStats)
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let rows: [(&str, u32); 5] = [
+        ("good", stats.good),
+        ("checksum", stats.bad_checksum),
+        ("overflow", stats.overflow),
+        ("talker", stats.unknown_talker),
+        ("parse", stats.parse_errors),
+    ];
+
+    for (idx, (label, count)) in rows.iter().enumerate() {
+        let y_pos = 26 + (idx * 8) as i32;
+        let mut value: String<12> = String::new();
+        let _ = write!(value, "{}", count);
+        let _ = Text::new(label, Point::new(4, y_pos), TEXT_STYLE_XS).draw(display);
+        let _ = Text::new(&value, Point::new(72, y_pos), TEXT_STYLE_XS).draw(display);
+    }
+}
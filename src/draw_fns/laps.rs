@@ -0,0 +1,63 @@
+use core::fmt::{Arguments, Write};
+
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*, text::Text};
+use heapless::String;
+
+use crate::{
+    draw_fns::constants::TEXT_STYLE_XS,
+    gps::laps::{Lap, LapKind, LapTracker},
+    utils::{time::Hms, units::Units},
+};
+
+/// The lap in progress, the last two completed laps, and how the last one
+/// compares with the one before it. Automatic splits are marked `*`.
+pub fn draw_laps<D>(display: &mut D, laps: &LapTracker, current: &Lap, units: Units)
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let mut y_pos = 20;
+    let mut row = |value: Arguments| {
+        let mut text: String<32> = String::new();
+        let _ = text.write_fmt(value);
+        let _ = Text::new(&text, Point::new(4, y_pos), TEXT_STYLE_XS).draw(display);
+        y_pos += 7;
+    };
+
+    for (label, lap) in [
+        ("now", Some(current)),
+        ("last", laps.last()),
+        ("prev", laps.previous()),
+    ] {
+        let Some(lap) = lap else {
+            continue;
+        };
+        let split = if lap.kind == LapKind::Split { "*" } else { "" };
+        row(format_args!(
+            "{} {}{} {}",
+            label,
+            lap.number,
+            split,
+            Hms(lap.elapsed_ms / 1000)
+        ));
+        row(format_args!(
+            "  {} {} ^{}",
+            units.distance(lap.distance_m.into()),
+            units.speed(lap.average_speed_mps()),
+            units.altitude(lap.gain_m.into())
+        ));
+    }
+
+    if let (Some(last), Some(previous)) = (laps.last(), laps.previous()) {
+        let seconds = i64::from(last.elapsed_ms / 1000) - i64::from(previous.elapsed_ms / 1000);
+        let sign = if seconds < 0 { '-' } else { '+' };
+        let speed = units.speed(last.average_speed_mps() - previous.average_speed_mps());
+        row(format_args!(
+            "vs prev {}{} {:+.*} {}",
+            sign,
+            Hms(seconds.unsigned_abs() as u32),
+            speed.precision as usize,
+            speed.value,
+            speed.unit
+        ));
+    }
+}
@@ -0,0 +1,116 @@
+use embedded_graphics::{
+    pixelcolor::BinaryColor,
+    prelude::*,
+    primitives::{Line, PrimitiveStyle, Rectangle, Triangle},
+    text::Text,
+};
+use libm::{cos, sin};
+
+use crate::{
+    draw_fns::constants::{TEXT_STYLE_MD, TEXT_STYLE_XS},
+    gps::{
+        fns::LatLonAlt,
+        map::{Breadcrumbs, MapOrientation, MapView},
+    },
+    utils::units::Units,
+};
+
+/// The map fills the screen under the title.
+const CENTER: Point = Point::new(64, 40);
+const RADIUS_PX: f64 = 22.0;
+/// Projected points are clamped to this before becoming pixels; anything
+/// that far out is off screen anyway.
+const MAX_OFFSET_PX: f64 = 1000.0;
+const MARKER_LEN: f64 = 5.0;
+const MAX_SCALE_BAR_PX: f64 = 40.0;
+const SCALE_BAR_Y: i32 = 62;
+const NORTH_MARKER: Point = Point::new(120, 22);
+
+/// Breadcrumb trail of the recording around the current position, zoomed
+/// to fit. The position is an arrow along `heading_deg`, the start a small
+/// square; a scale bar sits bottom left and a north marker top right.
+pub fn draw_map<D>(
+    display: &mut D,
+    trail: &Breadcrumbs,
+    here: Option<LatLonAlt>,
+    heading_deg: f64,
+    orientation: MapOrientation,
+    units: Units,
+) where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let Some(here) = here else {
+        let _ = Text::new("no fix", Point::new(46, 40), TEXT_STYLE_MD).draw(display);
+        return;
+    };
+    let up_deg = match orientation {
+        MapOrientation::NorthUp => 0.0,
+        MapOrientation::HeadingUp => heading_deg,
+    };
+    let view = MapView::fit(here, up_deg, trail.iter(), RADIUS_PX);
+    let to_screen = |point: LatLonAlt| {
+        let (x, y) = view.project(point);
+        CENTER
+            + Point::new(
+                x.clamp(-MAX_OFFSET_PX, MAX_OFFSET_PX) as i32,
+                y.clamp(-MAX_OFFSET_PX, MAX_OFFSET_PX) as i32,
+            )
+    };
+    let style = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+
+    let mut previous = None;
+    for point in trail.iter().map(to_screen) {
+        if let Some(previous) = previous {
+            let _ = Line::new(previous, point).into_styled(style).draw(display);
+        }
+        previous = Some(point);
+    }
+    if let Some(previous) = previous {
+        let _ = Line::new(previous, CENTER).into_styled(style).draw(display);
+    }
+    if let Some(start) = trail.start() {
+        let _ = Rectangle::with_center(to_screen(start), Size::new(5, 5))
+            .into_styled(style)
+            .draw(display);
+    }
+
+    let course = heading_deg - up_deg;
+    let _ = Triangle::new(
+        CENTER + offset(course, MARKER_LEN),
+        CENTER + offset(course + 140.0, MARKER_LEN),
+        CENTER + offset(course - 140.0, MARKER_LEN),
+    )
+    .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+    .draw(display);
+
+    let _ = Line::new(NORTH_MARKER, NORTH_MARKER + offset(-up_deg, 4.0))
+        .into_styled(style)
+        .draw(display);
+    let _ = Text::new(
+        "N",
+        NORTH_MARKER + offset(-up_deg, 9.0) - Point::new(1, -2),
+        TEXT_STYLE_XS,
+    )
+    .draw(display);
+
+    let (bar_m, label) = units.scale_bar(view.meters_per_pixel * MAX_SCALE_BAR_PX);
+    let bar_px = (bar_m / view.meters_per_pixel) as i32;
+    let left = Point::new(2, SCALE_BAR_Y);
+    let right = left + Point::new(bar_px, 0);
+    let _ = Line::new(left, right).into_styled(style).draw(display);
+    for end in [left, right] {
+        let _ = Line::new(end, end - Point::new(0, 2))
+            .into_styled(style)
+            .draw(display);
+    }
+    let _ = Text::new(label, left + Point::new(0, -4), TEXT_STYLE_XS).draw(display);
+}
+
+/// Screen offset `length` pixels away at `degrees` clockwise from up.
+fn offset(degrees: f64, length: f64) -> Point {
+    let radians = degrees.to_radians();
+    Point::new(
+        (length * sin(radians)) as i32,
+        -(length * cos(radians)) as i32,
+    )
+}
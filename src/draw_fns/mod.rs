@@ -1,3 +1,13 @@
+pub mod battery;
 pub mod constants;
+pub mod diagnostics;
+pub mod laps;
+pub mod map;
+pub mod navigation;
+pub mod profile;
+pub mod route;
 pub mod settings;
+pub mod sky;
+pub mod stats;
 pub mod utils;
+pub mod waypoints;
@@ -0,0 +1,98 @@
+use core::fmt::Write;
+
+use embedded_graphics::{
+    pixelcolor::BinaryColor,
+    prelude::*,
+    primitives::{Circle, Line, PrimitiveStyle},
+    text::Text,
+};
+use heapless::String;
+use libm::{cos, sin};
+
+use crate::{
+    draw_fns::constants::{TEXT_STYLE_MD, TEXT_STYLE_XS},
+    gps::fns::relative_bearing_deg,
+    utils::{time::Hms, units::Units},
+};
+
+const CENTER: Point = Point::new(30, 42);
+const RADIUS: f64 = 19.0;
+const HEAD_LEN: f64 = 8.0;
+const HEAD_SPREAD_DEG: f64 = 150.0;
+
+/// Where a navigation target lies from the current position.
+#[derive(Copy, Clone, Debug)]
+pub struct Target<'a> {
+    pub label: &'a str,
+    pub distance_m: f64,
+    pub bearing_deg: f64,
+}
+
+/// Arrow to `target` and the distance left. With a course over ground the
+/// arrow is relative to it (straight up = dead ahead); standing still it
+/// points relative to north instead, flagged with an `N` at the top of the
+/// dial. Moving, the time to get there at the current speed is shown too.
+pub fn draw_navigation<D>(
+    display: &mut D,
+    target: Option<Target>,
+    course_deg: Option<f64>,
+    speed_mps: f64,
+    units: Units,
+) where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let style = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+    let _ = Circle::with_center(CENTER, (RADIUS * 2.0) as u32 + 5)
+        .into_styled(style)
+        .draw(display);
+
+    let Some(target) = target else {
+        let _ = Text::new("no target", Point::new(64, 40), TEXT_STYLE_MD).draw(display);
+        return;
+    };
+
+    let angle = match course_deg {
+        Some(course) => relative_bearing_deg(target.bearing_deg, course),
+        None => {
+            let _ = Text::new(
+                "N",
+                CENTER - Point::new(1, RADIUS as i32 - 1),
+                TEXT_STYLE_XS,
+            )
+            .draw(display);
+            target.bearing_deg
+        }
+    };
+    let tip = polar(angle, RADIUS);
+    let _ = Line::new(polar(angle, -RADIUS * 0.6), tip)
+        .into_styled(style)
+        .draw(display);
+    for side in [-HEAD_SPREAD_DEG, HEAD_SPREAD_DEG] {
+        let barb = tip + (polar(angle + side, HEAD_LEN) - CENTER);
+        let _ = Line::new(tip, barb).into_styled(style).draw(display);
+    }
+
+    let _ = Text::new(target.label, Point::new(64, 22), TEXT_STYLE_XS).draw(display);
+    let mut text: String<16> = String::new();
+    let _ = write!(text, "{}", units.distance(target.distance_m));
+    let _ = Text::new(&text, Point::new(64, 38), TEXT_STYLE_MD).draw(display);
+    text.clear();
+    let _ = write!(text, "{:03.0} deg", target.bearing_deg);
+    let _ = Text::new(&text, Point::new(64, 50), TEXT_STYLE_XS).draw(display);
+    if speed_mps > 0.0 {
+        text.clear();
+        let _ = write!(text, "eta {}", Hms((target.distance_m / speed_mps) as u32));
+        let _ = Text::new(&text, Point::new(64, 58), TEXT_STYLE_XS).draw(display);
+    }
+}
+
+/// Screen point `length` pixels from the dial center at `degrees`
+/// clockwise from straight up.
+fn polar(degrees: f64, length: f64) -> Point {
+    let radians = degrees.to_radians();
+    CENTER
+        + Point::new(
+            (length * sin(radians)) as i32,
+            -(length * cos(radians)) as i32,
+        )
+}
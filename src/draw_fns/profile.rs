@@ -0,0 +1,111 @@
+use core::fmt::{Arguments, Write};
+
+use embedded_graphics::{
+    pixelcolor::BinaryColor,
+    prelude::*,
+    primitives::{Circle, Line, PrimitiveStyle, PrimitiveStyleBuilder},
+    text::{Alignment, Text},
+};
+use heapless::String;
+
+use crate::{
+    draw_fns::constants::{TEXT_STYLE_MD, TEXT_STYLE_XS},
+    gps::{elevation::ElevationTracker, profile::ElevationProfile},
+    utils::units::Units,
+};
+
+/// The chart sits between the max/grade/gain row and the min/loss row.
+const CHART_LEFT: i32 = 0;
+const CHART_WIDTH: i32 = 128;
+const CHART_TOP: i32 = 23;
+const CHART_BOTTOM: i32 = 56;
+/// Flatter than this the chart is stretched to this range around the mean,
+/// so a few meters of noise don't fill the screen.
+const MIN_RANGE_M: f32 = 10.0;
+
+/// Session altitude over distance as a filled chart, the current position
+/// circled at the right end. Highest altitude, current grade and climb sit
+/// above it, lowest altitude and descent below.
+pub fn draw_profile<D>(
+    display: &mut D,
+    profile: &ElevationProfile,
+    elevation: &ElevationTracker,
+    units: Units,
+) where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let Some((min_m, max_m)) = profile.range() else {
+        let _ = Text::new("no profile", Point::new(34, 40), TEXT_STYLE_MD).draw(display);
+        return;
+    };
+    let mut label = |value: Arguments, position: Point, alignment: Alignment| {
+        let mut text: String<16> = String::new();
+        let _ = text.write_fmt(value);
+        let _ = Text::with_alignment(&text, position, TEXT_STYLE_XS, alignment).draw(display);
+    };
+    label(
+        format_args!(
+            "max {}",
+            units.altitude(elevation.max_altitude.unwrap_or(max_m).into())
+        ),
+        Point::new(1, 21),
+        Alignment::Left,
+    );
+    label(
+        format_args!("{:+.0}%", elevation.grade_percent),
+        Point::new(64, 21),
+        Alignment::Center,
+    );
+    label(
+        format_args!("+{}", units.altitude(elevation.total_gain.into())),
+        Point::new(127, 21),
+        Alignment::Right,
+    );
+    label(
+        format_args!(
+            "min {}",
+            units.altitude(elevation.min_altitude.unwrap_or(min_m).into())
+        ),
+        Point::new(1, 63),
+        Alignment::Left,
+    );
+    label(
+        format_args!("-{}", units.altitude(elevation.total_loss.into())),
+        Point::new(127, 63),
+        Alignment::Right,
+    );
+
+    let (low_m, high_m) = if max_m - min_m < MIN_RANGE_M {
+        let mid = (max_m + min_m) / 2.0;
+        (mid - MIN_RANGE_M / 2.0, mid + MIN_RANGE_M / 2.0)
+    } else {
+        (min_m, max_m)
+    };
+    let to_y = |altitude: f32| {
+        let fraction = (altitude - low_m) / (high_m - low_m);
+        CHART_BOTTOM - (fraction * (CHART_BOTTOM - CHART_TOP) as f32) as i32
+    };
+
+    let end_m = profile.distance_m();
+    let style = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+    for column in 0..CHART_WIDTH {
+        let distance_m = end_m * column as f32 / (CHART_WIDTH - 1) as f32;
+        if let Some(altitude) = profile.altitude_at(distance_m) {
+            let x = CHART_LEFT + column;
+            let _ = Line::new(Point::new(x, CHART_BOTTOM), Point::new(x, to_y(altitude)))
+                .into_styled(style)
+                .draw(display);
+        }
+    }
+
+    if let Some((_, altitude)) = profile.iter().last() {
+        let marker = PrimitiveStyleBuilder::new()
+            .stroke_color(BinaryColor::On)
+            .stroke_width(1)
+            .fill_color(BinaryColor::Off)
+            .build();
+        let _ = Circle::with_center(Point::new(CHART_LEFT + CHART_WIDTH - 3, to_y(altitude)), 5)
+            .into_styled(marker)
+            .draw(display);
+    }
+}
@@ -0,0 +1,103 @@
+use core::fmt::{Arguments, Write};
+
+use embedded_graphics::{
+    pixelcolor::BinaryColor,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::{Alignment, Text},
+};
+use heapless::String;
+
+use crate::{
+    draw_fns::constants::{TEXT_STYLE_SM_INVERSE, TEXT_STYLE_XS},
+    gps::route::RouteProgress,
+    utils::units::Units,
+};
+
+/// Height of the title strip the off-course banner covers.
+const BANNER_HEIGHT: u32 = 14;
+
+/// Progress along the loaded route: covered and remaining distance, how far
+/// off the line and to which side, and the next turn.
+pub fn draw_route<D>(
+    display: &mut D,
+    route_points: usize,
+    progress: Option<&RouteProgress>,
+    units: Units,
+) where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let mut y_pos = 22;
+    let mut row = |value: Arguments| {
+        let mut text: String<32> = String::new();
+        let _ = text.write_fmt(value);
+        let _ = Text::new(&text, Point::new(4, y_pos), TEXT_STYLE_XS).draw(display);
+        y_pos += 8;
+    };
+
+    if route_points < 2 {
+        row(format_args!("no route"));
+        row(format_args!("load one with 'route load'"));
+        return;
+    }
+    let Some(progress) = progress else {
+        row(format_args!("{} points", route_points));
+        row(format_args!("waiting for fix"));
+        return;
+    };
+
+    row(format_args!(
+        "done {}",
+        units.distance(progress.along_track_m)
+    ));
+    row(format_args!(
+        "left {}",
+        units.distance(progress.remaining_m)
+    ));
+    // Offsets are short; show them in altitude units (ft or m).
+    let side = if progress.cross_track_m < 0.0 {
+        'L'
+    } else {
+        'R'
+    };
+    row(format_args!(
+        "off  {} {}",
+        units.altitude(progress.off_route_m),
+        side
+    ));
+    match progress.next_turn {
+        Some(turn) => {
+            let side = if turn.angle_deg < 0.0 {
+                "left"
+            } else {
+                "right"
+            };
+            row(format_args!(
+                "{} {:.0} in {}",
+                side,
+                turn.angle_deg.abs(),
+                units.distance(turn.distance_m)
+            ));
+        }
+        None => row(format_args!("no more turns")),
+    }
+}
+
+/// Replaces the title with an alert while off course, on every page.
+pub fn draw_off_course<D>(display: &mut D, off_route_m: f64, units: Units)
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let _ = Rectangle::new(Point::zero(), Size::new(128, BANNER_HEIGHT))
+        .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+        .draw(display);
+    let mut text: String<32> = String::new();
+    let _ = write!(text, "OFF COURSE {}", units.altitude(off_route_m));
+    let _ = Text::with_alignment(
+        &text,
+        Point::new(64, 10),
+        TEXT_STYLE_SM_INVERSE,
+        Alignment::Center,
+    )
+    .draw(display);
+}
@@ -7,6 +7,10 @@ use ssd1306::{
 
 use crate::{SettingsState, SettingsWrapper, draw_fns::constants::TEXT_STYLE_SM};
 
+const FIRST_ROW_Y: usize = 22;
+const ROW_HEIGHT: usize = 8;
+const VISIBLE_ROWS: usize = 6;
+
 pub async fn draw_settings(
     display: &mut Ssd1306<
         I2CInterface<Twim<'_>>,
@@ -17,53 +21,43 @@ pub async fn draw_settings(
 ) {
     let settings_state_lock = settings_state.lock().await;
     let items = &settings_state_lock.items;
-    let cursor_pos = settings_state_lock.index;
+    // Scroll so the cursor stays on screen.
+    let first_row = settings_state_lock.index.saturating_sub(VISIBLE_ROWS - 1);
+    let cursor_pos = settings_state_lock.index - first_row;
     let get_cursor_y = || -> i32 {
-        let preliminary_usize_offset = 30 + (10 * cursor_pos);
+        let preliminary_usize_offset = FIRST_ROW_Y + (ROW_HEIGHT * cursor_pos);
         preliminary_usize_offset.try_into().unwrap()
     };
 
     let cursor_point = Point::new(1, get_cursor_y());
 
-    for (idx, item) in items.iter().enumerate() {
-        let y_pos = 30 + (idx * 10) as i32;
+    for (idx, item) in items.iter().skip(first_row).take(VISIBLE_ROWS).enumerate() {
+        let y_pos = (FIRST_ROW_Y + idx * ROW_HEIGHT) as i32;
         match item {
             SettingsWrapper::Default => {}
             SettingsWrapper::Bool(setting) => {
                 Text::new(setting.label, Point::new(16, y_pos), TEXT_STYLE_SM)
                     .draw(display)
                     .unwrap();
-                Text::new(
-                    setting.options.current().1.0,
-                    Point::new(90, y_pos),
-                    TEXT_STYLE_SM,
-                )
-                .draw(display)
-                .unwrap();
+                Text::new(setting.current().0, Point::new(90, y_pos), TEXT_STYLE_SM)
+                    .draw(display)
+                    .unwrap();
             }
             SettingsWrapper::Text(setting) => {
                 Text::new(setting.label, Point::new(16, y_pos), TEXT_STYLE_SM)
                     .draw(display)
                     .unwrap();
-                Text::new(
-                    setting.options.current().1.0,
-                    Point::new(90, y_pos),
-                    TEXT_STYLE_SM,
-                )
-                .draw(display)
-                .unwrap();
+                Text::new(setting.current().0, Point::new(90, y_pos), TEXT_STYLE_SM)
+                    .draw(display)
+                    .unwrap();
             }
             SettingsWrapper::AnyNumber(setting) => {
                 Text::new(setting.label, Point::new(16, y_pos), TEXT_STYLE_SM)
                     .draw(display)
                     .unwrap();
-                Text::new(
-                    setting.options.current().1.0,
-                    Point::new(90, y_pos),
-                    TEXT_STYLE_SM,
-                )
-                .draw(display)
-                .unwrap();
+                Text::new(setting.current().0, Point::new(90, y_pos), TEXT_STYLE_SM)
+                    .draw(display)
+                    .unwrap();
             }
         };
     }
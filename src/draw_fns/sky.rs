@@ -0,0 +1,153 @@
+use core::fmt::Write;
+
+use embedded_graphics::{
+    pixelcolor::BinaryColor,
+    prelude::*,
+    primitives::{Circle, Line, PrimitiveStyle, Rectangle},
+    text::{Alignment, Text},
+};
+use heapless::String;
+
+use crate::{
+    draw_fns::constants::TEXT_STYLE_XS,
+    gps::{
+        reader::{Constellation, FixMode},
+        sky::{GnssStatus, sky_position},
+    },
+};
+
+const PLOT_CENTER: Point = Point::new(21, 40);
+const PLOT_RADIUS: u32 = 20;
+const BARS_LEFT: i32 = 46;
+const BARS_RIGHT: i32 = 127;
+const BARS_BOTTOM: i32 = 56;
+const BAR_WIDTH: u32 = 3;
+const BAR_STEP: i32 = 4;
+/// Extra space between constellations.
+const GROUP_GAP: i32 = 2;
+const MAX_BAR_HEIGHT: f32 = 28.0;
+/// Signal that fills a bar, in dB-Hz. Strong open-sky signals sit in the
+/// mid forties.
+const FULL_SNR: f32 = 50.0;
+
+/// Fix mode and satellites used out of those in view, e.g. `3D 8/12`, under
+/// the clock.
+pub fn draw_gnss_summary<D>(display: &mut D, status: &GnssStatus)
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let mut text: String<16> = String::new();
+    let _ = write!(
+        text,
+        "{} {}/{}",
+        fix_mode_text(status.fix_mode),
+        status.in_use(),
+        status.in_view()
+    );
+    let _ = Text::with_alignment(&text, Point::new(127, 20), TEXT_STYLE_XS, Alignment::Right)
+        .draw(display);
+}
+
+/// Sky plot of satellites in view on the left, signal bars grouped by
+/// constellation on the right. Satellites used in the fix are filled.
+pub fn draw_gnss<D>(display: &mut D, status: &GnssStatus)
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let stroke = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+    let fill = PrimitiveStyle::with_fill(BinaryColor::On);
+
+    let _ = Circle::with_center(PLOT_CENTER, PLOT_RADIUS * 2 + 1)
+        .into_styled(stroke)
+        .draw(display);
+    // 45 degrees elevation.
+    let _ = Circle::with_center(PLOT_CENTER, PLOT_RADIUS + 1)
+        .into_styled(stroke)
+        .draw(display);
+    let _ = Text::with_alignment(
+        "N",
+        PLOT_CENTER - Point::new(0, PLOT_RADIUS as i32 - 6),
+        TEXT_STYLE_XS,
+        Alignment::Center,
+    )
+    .draw(display);
+    for satellite in &status.satellites {
+        let Some((x, y)) = sky_position(satellite, f64::from(PLOT_RADIUS)) else {
+            continue;
+        };
+        let style = if satellite.in_use { fill } else { stroke };
+        let _ = Circle::with_center(PLOT_CENTER + Point::new(x as i32, y as i32), 3)
+            .into_styled(style)
+            .draw(display);
+    }
+
+    let mut summary: String<24> = String::new();
+    let _ = write!(
+        summary,
+        "{} {}/{} used",
+        fix_mode_text(status.fix_mode),
+        status.in_use(),
+        status.in_view()
+    );
+    let _ = Text::new(&summary, Point::new(BARS_LEFT, 21), TEXT_STYLE_XS).draw(display);
+
+    let mut x = BARS_LEFT;
+    let mut group_start = None;
+    let mut previous = None;
+    for satellite in status.by_constellation() {
+        if previous != Some(satellite.constellation) {
+            if let (Some(start), Some(constellation)) = (group_start, previous) {
+                label_group(display, constellation, start, x);
+                x += GROUP_GAP;
+            }
+            group_start = Some(x);
+            previous = Some(satellite.constellation);
+        }
+        if x + BAR_WIDTH as i32 > BARS_RIGHT {
+            break;
+        }
+        let snr = satellite.snr.unwrap_or_default().clamp(0.0, FULL_SNR);
+        let height = ((snr / FULL_SNR * MAX_BAR_HEIGHT) as u32).max(1);
+        let style = if satellite.in_use { fill } else { stroke };
+        let _ = Rectangle::new(
+            Point::new(x, BARS_BOTTOM - height as i32),
+            Size::new(BAR_WIDTH, height),
+        )
+        .into_styled(style)
+        .draw(display);
+        x += BAR_STEP;
+    }
+    if let (Some(start), Some(constellation)) = (group_start, previous) {
+        label_group(display, constellation, start, x);
+    }
+    let _ = Line::new(
+        Point::new(BARS_LEFT, BARS_BOTTOM),
+        Point::new(BARS_RIGHT, BARS_BOTTOM),
+    )
+    .into_styled(stroke)
+    .draw(display);
+}
+
+/// Constellation tag under its bars, left out when the group is too narrow
+/// to hold it.
+fn label_group<D>(display: &mut D, constellation: Constellation, start: i32, end: i32)
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    if end - start >= 8 {
+        let _ = Text::new(
+            constellation.short_name(),
+            Point::new(start, 63),
+            TEXT_STYLE_XS,
+        )
+        .draw(display);
+    }
+}
+
+fn fix_mode_text(fix_mode: Option<FixMode>) -> &'static str {
+    match fix_mode {
+        Some(FixMode::Fix3D) => "3D",
+        Some(FixMode::Fix2D) => "2D",
+        Some(FixMode::NoFix) | None => "no fix",
+    }
+}
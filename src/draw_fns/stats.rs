@@ -0,0 +1,45 @@
+use core::fmt::{Arguments, Write};
+
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*, text::Text};
+use heapless::String;
+
+use crate::{
+    draw_fns::constants::TEXT_STYLE_XS,
+    gps::session::SessionStats,
+    utils::{time::Hms, units::Units},
+};
+
+/// Session summary: clocks, speeds and totals in the selected units.
+pub fn draw_stats<D>(
+    display: &mut D,
+    stats: &SessionStats,
+    distance_m: f64,
+    gain_m: f64,
+    units: Units,
+) where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let mut y_pos = 22;
+    let mut row = |label: &str, value: Arguments| {
+        let mut text: String<16> = String::new();
+        let _ = text.write_fmt(value);
+        let _ = Text::new(label, Point::new(4, y_pos), TEXT_STYLE_XS).draw(display);
+        let _ = Text::new(&text, Point::new(64, y_pos), TEXT_STYLE_XS).draw(display);
+        y_pos += 7;
+    };
+
+    let average_mps = stats.average_speed_mps(distance_m);
+    row("elapsed", format_args!("{}", Hms(stats.elapsed_secs())));
+    row("moving", format_args!("{}", Hms(stats.moving_secs())));
+    row("distance", format_args!("{}", units.distance(distance_m)));
+    row("avg speed", format_args!("{}", units.speed(average_mps)));
+    row(
+        "max speed",
+        format_args!("{}", units.speed(stats.max_speed_mps)),
+    );
+    match units.pace(average_mps) {
+        Some(pace) => row("avg pace", format_args!("{}", pace)),
+        None => row("avg pace", format_args!("--")),
+    }
+    row("climb", format_args!("{}", units.altitude(gain_m)));
+}
@@ -1,3 +1,6 @@
+use core::fmt::Write as _;
+
+use chrono::{NaiveDateTime, Timelike};
 use embassy_nrf::twim::Twim;
 use embedded_graphics::{
     mono_font::MonoTextStyle,
@@ -17,7 +20,10 @@ use crate::{
         constants::{TEXT_STYLE_MD, TEXT_STYLE_SM, TEXT_STYLE_XS},
     },
     gps::reader::GpsReaderResults,
-    utils::float::FloatToString,
+    utils::{
+        float::FloatToString,
+        units::{Measure, Units},
+    },
 };
 
 pub fn draw_static_text<D>(display: &mut D, lg: MonoTextStyle<BinaryColor>) -> Result<(), D::Error>
@@ -60,8 +66,31 @@ pub fn draw_optional_float<D>(
     }
 }
 
+pub fn draw_measure<D>(
+    prefix: Option<&str>,
+    measure: Measure,
+    display: &mut D,
+    x: i32,
+    y: i32,
+    style: MonoTextStyle<BinaryColor>,
+) where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    draw_optional_float(
+        prefix,
+        Some(measure.unit),
+        measure.precision,
+        display,
+        Some(measure.value),
+        x,
+        y,
+        style,
+    );
+}
+
 pub fn draw_coords(
     last_lat_lon_alt: &Option<GpsReaderResults>,
+    units: Units,
     display: &mut Ssd1306<
         I2CInterface<Twim<'_>>,
         DisplaySize128x64,
@@ -89,16 +118,16 @@ pub fn draw_coords(
             38,
             TEXT_STYLE_XS,
         );
-        draw_fns::utils::draw_optional_float(
-            None,
-            None,
-            6,
-            display,
-            lat_lon_alt.alt,
-            0,
-            44,
-            TEXT_STYLE_XS,
-        );
+        if let Some(alt) = lat_lon_alt.alt {
+            draw_measure(
+                None,
+                units.altitude(alt.into()),
+                display,
+                0,
+                44,
+                TEXT_STYLE_XS,
+            );
+        }
     }
 }
 
@@ -129,18 +158,50 @@ pub fn draw_fix_status(
 
 pub fn draw_recording_status(
     is_recording: bool,
+    is_paused: bool,
     display: &mut Ssd1306<
         I2CInterface<Twim<'_>>,
         DisplaySize128x64,
         BufferedGraphicsMode<DisplaySize128x64>,
     >,
 ) {
-    let recording_state_text = if is_recording { ">>" } else { "--" };
+    let recording_state_text = match (is_recording, is_paused) {
+        (true, true) => "||",
+        (true, false) => ">>",
+        (false, _) => "--",
+    };
     Text::new(recording_state_text, Point::new(0, 8), TEXT_STYLE_SM)
         .draw(display)
         .unwrap();
 }
 
+/// Local clock in the top-right corner and, while recording, the local time
+/// the session started under the recording status.
+pub fn draw_clock(
+    local_time: Option<NaiveDateTime>,
+    session_start: Option<NaiveDateTime>,
+    display: &mut Ssd1306<
+        I2CInterface<Twim<'_>>,
+        DisplaySize128x64,
+        BufferedGraphicsMode<DisplaySize128x64>,
+    >,
+) {
+    if let Some(time) = local_time {
+        let mut text: String<8> = String::new();
+        let _ = write!(text, "{:02}:{:02}", time.hour(), time.minute());
+        Text::new(&text, Point::new(103, 8), TEXT_STYLE_SM)
+            .draw(display)
+            .unwrap();
+    }
+    if let Some(start) = session_start {
+        let mut text: String<8> = String::new();
+        let _ = write!(text, "@{:02}:{:02}", start.hour(), start.minute());
+        Text::new(&text, Point::new(0, 20), TEXT_STYLE_XS)
+            .draw(display)
+            .unwrap();
+    }
+}
+
 pub fn draw_blinky(
     display: &mut Ssd1306<
         I2CInterface<Twim<'_>>,
@@ -154,27 +215,18 @@ pub fn draw_blinky(
 }
 
 pub fn draw_total_distance(
-    distance_raw: f64,
+    distance_m: f64,
+    units: Units,
     display: &mut Ssd1306<
         I2CInterface<Twim<'_>>,
         DisplaySize128x64,
         BufferedGraphicsMode<DisplaySize128x64>,
     >,
 ) {
-    let mut drawable_distance: f64 = distance_raw;
-    let mut drawable_precision: u8 = 0;
-    let mut drawable_unit: &str = "'";
-    if distance_raw > 5280.0 {
-        drawable_distance = distance_raw / 5280.0;
-        drawable_precision = 3;
-        drawable_unit = "mi.";
-    }
-    draw_optional_float(
+    draw_measure(
         Some(">"),
-        Some(drawable_unit),
-        drawable_precision,
+        units.distance(distance_m),
         display,
-        Some(drawable_distance),
         70,
         60,
         TEXT_STYLE_SM,
@@ -182,19 +234,18 @@ pub fn draw_total_distance(
 }
 
 pub fn draw_total_elev_gain(
-    gain_raw: f64,
+    gain_m: f64,
+    units: Units,
     display: &mut Ssd1306<
         I2CInterface<Twim<'_>>,
         DisplaySize128x64,
         BufferedGraphicsMode<DisplaySize128x64>,
     >,
 ) {
-    draw_optional_float(
+    draw_measure(
         Some("^"),
-        Some("'"),
-        0,
+        units.altitude(gain_m),
         display,
-        Some(gain_raw),
         70,
         50,
         TEXT_STYLE_SM,
@@ -202,100 +253,27 @@ pub fn draw_total_elev_gain(
 }
 
 pub fn draw_current_speed(
-    speed_raw: f64,
+    speed_mps: f64,
+    units: Units,
     display: &mut Ssd1306<
         I2CInterface<Twim<'_>>,
         DisplaySize128x64,
         BufferedGraphicsMode<DisplaySize128x64>,
     >,
 ) {
-    draw_optional_float(
-        None,
-        Some("mph"),
-        2,
-        display,
-        Some(speed_raw),
-        70,
-        36,
-        TEXT_STYLE_MD,
-    );
+    draw_measure(None, units.speed(speed_mps), display, 70, 36, TEXT_STYLE_MD);
 }
 
 pub fn draw_last_segment_distance(
-    distance_raw: f64,
-    display: &mut Ssd1306<
-        I2CInterface<Twim<'_>>,
-        DisplaySize128x64,
-        BufferedGraphicsMode<DisplaySize128x64>,
-    >,
-) {
-    draw_optional_float(
-        None,
-        Some("ft"),
-        1,
-        display,
-        Some(distance_raw),
-        70,
-        40,
-        TEXT_STYLE_SM,
-    );
-}
-
-pub fn draw_hdop(
-    fix: Option<FixType>, // This is the change: it's now an Option<FixType>
-    hdop_raw: f32,
+    distance_m: f64,
+    units: Units,
     display: &mut Ssd1306<
         I2CInterface<Twim<'_>>,
         DisplaySize128x64,
         BufferedGraphicsMode<DisplaySize128x64>,
     >,
 ) {
-    let mut quality_text;
-
-    match fix {
-        Some(FixType::Invalid) => {
-            quality_text = "X";
-        }
-        Some(FixType::Gps) => {
-            quality_text = "LQ";
-            if hdop_raw < 10.0 {
-                quality_text = "O"
-            };
-            if hdop_raw < 5.0 {
-                quality_text = "O)"
-            };
-            if hdop_raw < 2.0 {
-                quality_text = "O))"
-            };
-            if hdop_raw < 1.0 {
-                quality_text = "HQ"
-            };
-        }
-        Some(FixType::DGps) => {
-            quality_text = "D";
-            if hdop_raw < 2.0 {
-                quality_text = "D)"
-            };
-            if hdop_raw < 1.0 {
-                quality_text = "D))"
-            };
-        }
-        Some(FixType::FloatRtk) => {
-            quality_text = "R";
-            if hdop_raw < 1.0 {
-                quality_text = "R)"
-            }; // Good RTK Float (HDOP still matters)
-        }
-        Some(FixType::Rtk) => quality_text = "R))", // RTK Fixed is usually the best, HDOP might still be provided but less critical
-
-        // Handle the None case for the Option<FixType>
-        None => {
-            quality_text = "N/A"; // Or "NoFix", "---", " " to indicate no fix data at all
-        }
-        // For any other unhandled FixType that might be inside Some()
-        Some(_) => quality_text = "??",
-    }
-    Text::new(quality_text, Point::new(110, 8), TEXT_STYLE_SM)
-        .draw(display)
-        .unwrap();
+    let mut measure = units.distance(distance_m);
+    measure.precision = 1;
+    draw_measure(None, measure, display, 70, 40, TEXT_STYLE_SM);
 }
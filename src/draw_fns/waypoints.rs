@@ -0,0 +1,49 @@
+use core::fmt::Write;
+
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*, text::Text};
+use heapless::String;
+
+use crate::{draw_fns::constants::TEXT_STYLE_XS, gps::waypoint::WaypointList};
+
+const FIRST_ROW_Y: i32 = 20;
+const ROW_HEIGHT: i32 = 7;
+const VISIBLE_ROWS: usize = 6;
+
+/// Saved waypoints around the cursor (`>`), with the navigation target
+/// marked `*`.
+pub fn draw_waypoints<D>(display: &mut D, waypoints: &WaypointList)
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    if waypoints.is_empty() {
+        let _ = Text::new("no waypoints", Point::new(4, FIRST_ROW_Y), TEXT_STYLE_XS).draw(display);
+        let _ = Text::new(
+            "hold to mark here",
+            Point::new(4, FIRST_ROW_Y + ROW_HEIGHT),
+            TEXT_STYLE_XS,
+        )
+        .draw(display);
+        return;
+    }
+
+    // Keep the cursor inside the visible window.
+    let cursor_row = waypoints
+        .iter()
+        .position(|(slot, _)| Some(slot) == waypoints.cursor())
+        .unwrap_or(0);
+    let first = cursor_row.saturating_sub(VISIBLE_ROWS - 1);
+
+    let mut y_pos = FIRST_ROW_Y;
+    for (row, (slot, waypoint)) in waypoints.iter().enumerate().skip(first).take(VISIBLE_ROWS) {
+        let cursor = if row == cursor_row { '>' } else { ' ' };
+        let target = if Some(slot) == waypoints.target_slot() {
+            '*'
+        } else {
+            ' '
+        };
+        let mut text: String<24> = String::new();
+        let _ = write!(text, "{}{} {}", cursor, target, waypoint.name);
+        let _ = Text::new(&text, Point::new(4, y_pos), TEXT_STYLE_XS).draw(display);
+        y_pos += ROW_HEIGHT;
+    }
+}
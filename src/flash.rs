@@ -0,0 +1,23 @@
+use embassy_embedded_hal::{adapter::BlockingAsync, flash::partition::Partition};
+use embassy_nrf::nvmc::Nvmc;
+use embassy_sync::{blocking_mutex::raw::NoopRawMutex, mutex::Mutex};
+
+use crate::track::log::TrackLog;
+
+pub type ProjFlash = Mutex<NoopRawMutex, BlockingAsync<Nvmc<'static>>>;
+pub type ProjFlashPartition = Partition<'static, NoopRawMutex, BlockingAsync<Nvmc<'static>>>;
+
+pub const TRACK_FLASH_OFFSET: u32 = 0x000A_0000;
+pub const TRACK_FLASH_SIZE: u32 = 0x0004_0000;
+pub const SETTINGS_FLASH_OFFSET: u32 = 0x000E_0000;
+pub const SETTINGS_FLASH_SIZE: u32 = 0x0002_0000;
+
+pub type ProjTrackLog = TrackLog<ProjFlashPartition>;
+
+pub fn track_partition(flash: &'static ProjFlash) -> ProjFlashPartition {
+    Partition::new(flash, TRACK_FLASH_OFFSET, TRACK_FLASH_SIZE)
+}
+
+pub fn settings_partition(flash: &'static ProjFlash) -> ProjFlashPartition {
+    Partition::new(flash, SETTINGS_FLASH_OFFSET, SETTINGS_FLASH_SIZE)
+}
@@ -0,0 +1,101 @@
+/// Below this the recorder starts counting towards a pause.
+const PAUSE_BELOW_MPS: f64 = 0.5;
+const PAUSE_AFTER_MS: u64 = 5_000;
+/// Resuming needs clearly more speed than pausing, so creeping along at the
+/// threshold doesn't flap between the two.
+const RESUME_ABOVE_MPS: f64 = 1.0;
+const RESUME_AFTER_MS: u64 = 3_000;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PauseState {
+    Moving,
+    /// Slow since `since_ms`; pauses if it stays that way.
+    Slowing {
+        since_ms: u64,
+    },
+    Paused,
+    /// Moving again since `since_ms`; resumes if it keeps going.
+    Starting {
+        since_ms: u64,
+    },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PauseEvent {
+    Paused,
+    Resumed,
+}
+
+/// Auto-pause state machine. Times are milliseconds on any monotonic clock;
+/// the session clocks live in `SessionStats`.
+#[derive(Copy, Clone, Debug)]
+pub struct AutoPause {
+    pub enabled: bool,
+    state: PauseState,
+}
+
+impl AutoPause {
+    pub fn new() -> Self {
+        AutoPause {
+            enabled: false,
+            state: PauseState::Moving,
+        }
+    }
+
+    /// Starts a new session moving.
+    pub fn reset(&mut self) {
+        *self = AutoPause {
+            enabled: self.enabled,
+            ..AutoPause::new()
+        };
+    }
+
+    pub fn state(&self) -> PauseState {
+        self.state
+    }
+
+    /// Only a confirmed pause stops recording; the resume window already
+    /// counts as moving so its fixes aren't lost.
+    pub fn is_paused(&self) -> bool {
+        self.state == PauseState::Paused
+    }
+
+    /// Feeds in the current speed at `now_ms`.
+    pub fn update(&mut self, now_ms: u64, speed_mps: f64) -> Option<PauseEvent> {
+        if !self.enabled {
+            let was_paused = matches!(self.state, PauseState::Paused | PauseState::Starting { .. });
+            self.state = PauseState::Moving;
+            return was_paused.then_some(PauseEvent::Resumed);
+        }
+
+        let (state, event) = match self.state {
+            PauseState::Moving if speed_mps < PAUSE_BELOW_MPS => {
+                (PauseState::Slowing { since_ms: now_ms }, None)
+            }
+            PauseState::Slowing { .. } if speed_mps >= PAUSE_BELOW_MPS => {
+                (PauseState::Moving, None)
+            }
+            PauseState::Slowing { since_ms } if now_ms - since_ms >= PAUSE_AFTER_MS => {
+                (PauseState::Paused, Some(PauseEvent::Paused))
+            }
+            PauseState::Paused if speed_mps >= RESUME_ABOVE_MPS => {
+                (PauseState::Starting { since_ms: now_ms }, None)
+            }
+            PauseState::Starting { .. } if speed_mps < RESUME_ABOVE_MPS => {
+                (PauseState::Paused, None)
+            }
+            PauseState::Starting { since_ms } if now_ms - since_ms >= RESUME_AFTER_MS => {
+                (PauseState::Moving, Some(PauseEvent::Resumed))
+            }
+            state => (state, None),
+        };
+        self.state = state;
+        event
+    }
+}
+
+impl Default for AutoPause {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,107 @@
+/// Climb/descent threshold used until the user setting is applied.
+pub const DEFAULT_THRESHOLD_M: f32 = 3.0;
+/// Weight of each new fix in the altitude average. GPS altitude wanders by
+/// meters from fix to fix; at 1 Hz this is a ~5 s time constant.
+const SMOOTHING: f32 = 0.2;
+/// Horizontal distance the grade is measured over.
+const GRADE_RUN_M: f64 = 20.0;
+
+/// Climb and descent totals from GPS altitude. Changes only count once the
+/// smoothed altitude has moved `threshold_m` from the last turning point,
+/// so noise on flat ground adds nothing.
+#[derive(Copy, Clone, Debug)]
+pub struct ElevationTracker {
+    pub threshold_m: f32,
+    pub total_gain: f32,
+    pub total_loss: f32,
+    pub max_altitude: Option<f32>,
+    pub min_altitude: Option<f32>,
+    pub grade_percent: f32,
+    smoothed: Option<f32>,
+    anchor: f32,
+    grade_start: f32,
+    grade_run_m: f64,
+}
+
+impl ElevationTracker {
+    pub fn new() -> Self {
+        ElevationTracker {
+            threshold_m: DEFAULT_THRESHOLD_M,
+            total_gain: 0.0,
+            total_loss: 0.0,
+            max_altitude: None,
+            min_altitude: None,
+            grade_percent: 0.0,
+            smoothed: None,
+            anchor: 0.0,
+            grade_start: 0.0,
+            grade_run_m: 0.0,
+        }
+    }
+
+    /// Clears the session totals, extremes and grade. The threshold and the
+    /// smoothed altitude carry over, so the next session starts level.
+    pub fn reset_session(&mut self) {
+        *self = ElevationTracker {
+            threshold_m: self.threshold_m,
+            smoothed: self.smoothed,
+            anchor: self.smoothed.unwrap_or_default(),
+            grade_start: self.smoothed.unwrap_or_default(),
+            ..ElevationTracker::new()
+        };
+    }
+
+    pub fn altitude(&self) -> Option<f32> {
+        self.smoothed
+    }
+
+    /// Folds in one fix. `run_m` is the horizontal distance covered since the
+    /// previous fix; totals and extremes only move while `recording`.
+    pub fn update(&mut self, altitude: f32, run_m: f64, recording: bool) {
+        let smoothed = match self.smoothed {
+            Some(prev) => prev + SMOOTHING * (altitude - prev),
+            None => {
+                self.anchor = altitude;
+                self.grade_start = altitude;
+                altitude
+            }
+        };
+        self.smoothed = Some(smoothed);
+
+        if recording {
+            self.max_altitude = Some(self.max_altitude.map_or(smoothed, |m| m.max(smoothed)));
+            self.min_altitude = Some(self.min_altitude.map_or(smoothed, |m| m.min(smoothed)));
+
+            let change = smoothed - self.anchor;
+            if change >= self.threshold_m {
+                self.total_gain += change;
+                self.anchor = smoothed;
+            } else if -change >= self.threshold_m {
+                self.total_loss -= change;
+                self.anchor = smoothed;
+            }
+        } else {
+            // Don't carry a climb made while stopped into the next session.
+            self.anchor = smoothed;
+        }
+
+        if run_m <= 0.0 {
+            self.grade_percent = 0.0;
+            self.grade_start = smoothed;
+            self.grade_run_m = 0.0;
+            return;
+        }
+        self.grade_run_m += run_m;
+        if self.grade_run_m >= GRADE_RUN_M {
+            self.grade_percent = (smoothed - self.grade_start) / self.grade_run_m as f32 * 100.0;
+            self.grade_start = smoothed;
+            self.grade_run_m = 0.0;
+        }
+    }
+}
+
+impl Default for ElevationTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
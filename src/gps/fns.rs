@@ -1,21 +1,43 @@
-use libm::{atan2, cos, sin, sqrt};
+use libm::{acos, asin, atan2, cos, sin, sqrt};
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct LatLonAlt {
     pub latitude: f64,
     pub longitude: f64,
     pub altitude: f32,
 }
 
+impl LatLonAlt {
+    /// Latitude, longitude and altitude, little-endian.
+    pub const ENCODED_LEN: usize = 8 + 8 + 4;
+
+    /// Writes the first [`ENCODED_LEN`](Self::ENCODED_LEN) bytes of `buf`.
+    pub fn encode(&self, buf: &mut [u8]) {
+        buf[0..8].copy_from_slice(&self.latitude.to_le_bytes());
+        buf[8..16].copy_from_slice(&self.longitude.to_le_bytes());
+        buf[16..20].copy_from_slice(&self.altitude.to_le_bytes());
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        Some(LatLonAlt {
+            latitude: f64::from_le_bytes(bytes.get(0..8)?.try_into().ok()?),
+            longitude: f64::from_le_bytes(bytes.get(8..16)?.try_into().ok()?),
+            altitude: f32::from_le_bytes(bytes.get(16..20)?.try_into().ok()?),
+        })
+    }
+}
+
 const EARTH_RADIUS_M: f64 = 6371000.0;
-const FT_PER_METER: f64 = 3.28084;
-const FT_IN_A_MILE: f64 = 5280.0;
 
 fn to_radians(degrees: f64) -> f64 {
     degrees * (core::f64::consts::PI / 180.0)
 }
 
-pub fn haversine_distance_ft(p1: LatLonAlt, p2: LatLonAlt) -> f64 {
+fn to_degrees(radians: f64) -> f64 {
+    radians * (180.0 / core::f64::consts::PI)
+}
+
+pub fn haversine_distance_m(p1: LatLonAlt, p2: LatLonAlt) -> f64 {
     let lat1_rad = to_radians(p1.latitude);
     let lon1_rad = to_radians(p1.longitude);
     let lat2_rad = to_radians(p2.latitude);
@@ -31,14 +53,122 @@ pub fn haversine_distance_ft(p1: LatLonAlt, p2: LatLonAlt) -> f64 {
 
     let c = 2.0 * atan2(sqrt(a), sqrt(1.0 - a));
 
-    EARTH_RADIUS_M * FT_PER_METER * c
+    EARTH_RADIUS_M * c
+}
+
+/// Initial great-circle bearing in degrees (0 = north, clockwise) for the
+/// route from `from` to `to`. The bearing drifts along long routes, so
+/// recompute it as you go.
+pub fn initial_bearing_deg(from: LatLonAlt, to: LatLonAlt) -> f64 {
+    let lat1_rad = to_radians(from.latitude);
+    let lat2_rad = to_radians(to.latitude);
+    let d_lon = to_radians(to.longitude - from.longitude);
+
+    let y = sin(d_lon) * cos(lat2_rad);
+    let x = cos(lat1_rad) * sin(lat2_rad) - sin(lat1_rad) * cos(lat2_rad) * cos(d_lon);
+
+    normalize_degrees(to_degrees(atan2(y, x)))
+}
+
+/// Distance in meters of `point` from the great circle through `start` and
+/// `end`; positive when `point` is to the right of the path.
+pub fn cross_track_distance_m(start: LatLonAlt, end: LatLonAlt, point: LatLonAlt) -> f64 {
+    let angular = haversine_distance_m(start, point) / EARTH_RADIUS_M;
+    let angle = to_radians(initial_bearing_deg(start, point) - initial_bearing_deg(start, end));
+    asin(sin(angular) * sin(angle)) * EARTH_RADIUS_M
+}
+
+/// Distance in meters from `start` to the foot of the perpendicular from
+/// `point` onto the great circle through `start` and `end`; negative when
+/// the foot lies behind `start`.
+pub fn along_track_distance_m(start: LatLonAlt, end: LatLonAlt, point: LatLonAlt) -> f64 {
+    let angular = haversine_distance_m(start, point) / EARTH_RADIUS_M;
+    let angle = to_radians(initial_bearing_deg(start, point) - initial_bearing_deg(start, end));
+    let cross = asin(sin(angular) * sin(angle));
+    let along = acos((cos(angular) / cos(cross)).clamp(-1.0, 1.0)) * EARTH_RADIUS_M;
+    if cos(angle) < 0.0 { -along } else { along }
+}
+
+/// Where a point lies relative to one segment of a path.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SegmentOffset {
+    /// Signed as in [`cross_track_distance_m`].
+    pub cross_track_m: f64,
+    /// Along the segment, clamped to its ends.
+    pub along_track_m: f64,
+    /// To the nearest point of the segment, ends included.
+    pub distance_m: f64,
 }
 
-pub fn calculate_speed(distance_ft: f64, time_secs: f64) -> f64 {
-    let fps_to_mph_conversion_factor = 3600.0 / FT_IN_A_MILE;
+/// How far `point` is from the segment `start`..`end` and how far along it
+/// its nearest point is. Past either end the distance is to that end.
+pub fn segment_offset(start: LatLonAlt, end: LatLonAlt, point: LatLonAlt) -> SegmentOffset {
+    let length = haversine_distance_m(start, end);
+    let cross_track_m = cross_track_distance_m(start, end, point);
+    let along = along_track_distance_m(start, end, point);
+    let (along_track_m, distance_m) = if length == 0.0 || along <= 0.0 {
+        (0.0, haversine_distance_m(start, point))
+    } else if along >= length {
+        (length, haversine_distance_m(end, point))
+    } else {
+        (along, cross_track_m.abs())
+    };
+    SegmentOffset {
+        cross_track_m,
+        along_track_m,
+        distance_m,
+    }
+}
+
+/// Angle of `bearing` as seen from `course`, in -180..180 degrees; positive
+/// is to the right.
+pub fn relative_bearing_deg(bearing: f64, course: f64) -> f64 {
+    let relative = normalize_degrees(bearing - course);
+    if relative >= 180.0 {
+        relative - 360.0
+    } else {
+        relative
+    }
+}
+
+fn normalize_degrees(degrees: f64) -> f64 {
+    let wrapped = degrees % 360.0;
+    if wrapped < 0.0 {
+        wrapped + 360.0
+    } else {
+        wrapped
+    }
+}
+
+/// Meters per second.
+pub fn calculate_speed(distance_m: f64, time_secs: f64) -> f64 {
     if time_secs == 0.0 {
         return 0.0;
     }
-    let speed_fps = distance_ft / time_secs;
-    speed_fps * fps_to_mph_conversion_factor
+    distance_m / time_secs
+}
+
+/// Local east/north offset in meters of `point` from `origin`. A flat-earth
+/// approximation: keep `point` within a kilometer or so of `origin`.
+pub fn to_enu(origin: LatLonAlt, point: LatLonAlt) -> (f64, f64) {
+    let east = to_radians(point.longitude - origin.longitude)
+        * EARTH_RADIUS_M
+        * cos(to_radians(origin.latitude));
+    let north = to_radians(point.latitude - origin.latitude) * EARTH_RADIUS_M;
+    (east, north)
+}
+
+/// Inverse of [`to_enu`].
+pub fn from_enu(origin: LatLonAlt, east: f64, north: f64, altitude: f32) -> LatLonAlt {
+    LatLonAlt {
+        latitude: origin.latitude + to_degrees(north / EARTH_RADIUS_M),
+        longitude: origin.longitude
+            + to_degrees(east / (EARTH_RADIUS_M * cos(to_radians(origin.latitude)))),
+        altitude,
+    }
+}
+
+/// Compass bearing in degrees (0 = north, clockwise) of an east/north vector.
+pub fn enu_heading(east: f64, north: f64) -> f64 {
+    normalize_degrees(to_degrees(atan2(east, north)))
 }
@@ -0,0 +1,214 @@
+use core::fmt::Write as _;
+
+use heapless::{String, Vec};
+
+use crate::gps::{
+    fns::LatLonAlt,
+    waypoint::{MAX_NAME_LEN, Waypoint},
+};
+
+/// Longest element the parser keeps; longer ones are skipped.
+const MAX_ELEMENT_LEN: usize = 256;
+/// Enough of the tail of an overlong element to spot its closing tag.
+const CLOSE_TAG_LEN: usize = 8;
+
+/// What a GPX upload produced, one byte at a time.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GpxEvent {
+    /// A complete `<wpt>`; the name is empty if the element had none.
+    Waypoint(Waypoint),
+    /// A `<rtept>` or `<trkpt>`, in document order.
+    Point(LatLonAlt),
+    /// An element without usable `lat`/`lon`, or too long to keep.
+    Invalid,
+    /// `</gpx>`: the upload is over.
+    End,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Element {
+    Wpt,
+    Rtept,
+    Trkpt,
+}
+
+impl Element {
+    fn from_name(name: &[u8]) -> Option<Self> {
+        match name {
+            b"wpt" => Some(Element::Wpt),
+            b"rtept" => Some(Element::Rtept),
+            b"trkpt" => Some(Element::Trkpt),
+            _ => None,
+        }
+    }
+
+    fn close_tag(self) -> &'static [u8] {
+        match self {
+            Element::Wpt => b"</wpt>",
+            Element::Rtept => b"</rtept>",
+            Element::Trkpt => b"</trkpt>",
+        }
+    }
+
+    fn parse(self, element: &[u8]) -> Option<GpxEvent> {
+        let text = core::str::from_utf8(element).ok()?;
+        let position = parse_position(text)?;
+        if self != Element::Wpt {
+            return Some(GpxEvent::Point(position));
+        }
+        let mut name: String<MAX_NAME_LEN> = String::new();
+        if let Some(raw) = child_text(text, "name") {
+            unescape_into(raw.trim(), &mut name);
+        }
+        Some(GpxEvent::Waypoint(Waypoint { name, position }))
+    }
+}
+
+/// Picks waypoints and route or track points out of a GPX document as it
+/// streams in. Anything else in the document is skipped, so metadata and
+/// extensions can come along.
+#[derive(Clone, Debug, Default)]
+pub struct GpxParser {
+    buf: Vec<u8, MAX_ELEMENT_LEN>,
+    in_tag: bool,
+    element: Option<Element>,
+    overflowed: bool,
+}
+
+impl GpxParser {
+    pub fn new() -> Self {
+        GpxParser::default()
+    }
+
+    pub fn push(&mut self, byte: u8) -> Option<GpxEvent> {
+        if let Some(element) = self.element {
+            if self.buf.push(byte).is_err() {
+                let keep = self.buf.len() - CLOSE_TAG_LEN;
+                self.buf.copy_within(keep.., 0);
+                self.buf.truncate(CLOSE_TAG_LEN);
+                let _ = self.buf.push(byte);
+                self.overflowed = true;
+            }
+            let self_closing = byte == b'>'
+                && self.buf.ends_with(b"/>")
+                && !self.buf[..self.buf.len() - 1].contains(&b'>');
+            if !self_closing && !self.buf.ends_with(element.close_tag()) {
+                return None;
+            }
+            self.element = None;
+            let event = if core::mem::take(&mut self.overflowed) {
+                GpxEvent::Invalid
+            } else {
+                element.parse(&self.buf).unwrap_or(GpxEvent::Invalid)
+            };
+            self.buf.clear();
+            return Some(event);
+        }
+
+        if byte == b'<' {
+            self.buf.clear();
+            self.in_tag = true;
+        }
+        if !self.in_tag {
+            return None;
+        }
+        if self.buf.push(byte).is_err() {
+            // An overlong tag is nothing we're looking for.
+            self.in_tag = false;
+            return None;
+        }
+        if byte != b'>' && !is_space(byte) {
+            return None;
+        }
+        self.in_tag = false;
+        match self.buf.as_slice() {
+            b"</gpx>" => {
+                self.buf.clear();
+                Some(GpxEvent::End)
+            }
+            [b'<', name @ .., end] if is_space(*end) || *end == b'>' => {
+                self.element = Element::from_name(name);
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+fn is_space(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\r' | b'\n')
+}
+
+fn parse_position(text: &str) -> Option<LatLonAlt> {
+    let start_tag = &text[..text.find('>')?];
+    let latitude: f64 = attribute(start_tag, "lat")?.trim().parse().ok()?;
+    let longitude: f64 = attribute(start_tag, "lon")?.trim().parse().ok()?;
+    if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+        return None;
+    }
+    let altitude = child_text(text, "ele")
+        .and_then(|ele| ele.trim().parse().ok())
+        .unwrap_or(0.0);
+    Some(LatLonAlt {
+        latitude,
+        longitude,
+        altitude,
+    })
+}
+
+/// Value of attribute `name` in a start tag, single or double quoted.
+fn attribute<'t>(tag: &'t str, name: &str) -> Option<&'t str> {
+    let mut rest = tag;
+    while let Some(at) = rest.find(name) {
+        let before = rest[..at].bytes().next_back();
+        let after = rest[at + name.len()..].trim_start();
+        rest = &rest[at + name.len()..];
+        if !before.is_some_and(is_space) {
+            continue;
+        }
+        let Some(value) = after.strip_prefix('=') else {
+            continue;
+        };
+        let value = value.trim_start();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let value = &value[1..];
+        return Some(&value[..value.find(quote)?]);
+    }
+    None
+}
+
+/// Text of the first `<name>...</name>` child.
+fn child_text<'t>(element: &'t str, name: &str) -> Option<&'t str> {
+    let mut open: String<16> = String::new();
+    let mut close: String<16> = String::new();
+    write!(open, "<{}>", name).ok()?;
+    write!(close, "</{}>", name).ok()?;
+    let start = element.find(open.as_str())? + open.len();
+    let end = start + element[start..].find(close.as_str())?;
+    Some(&element[start..end])
+}
+
+/// Decodes the five predefined XML entities, stopping when `out` is full.
+fn unescape_into<const N: usize>(raw: &str, out: &mut String<N>) {
+    let mut rest = raw;
+    while let Some(c) = rest.chars().next() {
+        let (decoded, len) = if c == '&' {
+            [
+                ("&amp;", '&'),
+                ("&lt;", '<'),
+                ("&gt;", '>'),
+                ("&quot;", '"'),
+                ("&apos;", '\''),
+            ]
+            .iter()
+            .find(|(entity, _)| rest.starts_with(entity))
+            .map_or((c, 1), |(entity, decoded)| (*decoded, entity.len()))
+        } else {
+            (c, c.len_utf8())
+        };
+        if out.push(decoded).is_err() {
+            return;
+        }
+        rest = &rest[len..];
+    }
+}
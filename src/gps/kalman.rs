@@ -0,0 +1,140 @@
+use libm::sqrt;
+
+use crate::gps::fns::{LatLonAlt, enu_heading, from_enu, to_enu};
+
+/// Receiver position error per unit of HDOP, in meters (1 sigma).
+const UERE_M: f64 = 4.0;
+/// Acceleration noise of the constant-velocity model, (m/s^2)^2. Low enough
+/// that fix-to-fix jitter does not read as motion, high enough to follow a
+/// cyclist or a car pulling away.
+const ACCEL_VARIANCE: f64 = 0.1;
+/// Velocity uncertainty of a fresh track, (m/s)^2.
+const INITIAL_VELOCITY_VARIANCE: f64 = 4.0;
+/// After a gap this long the old estimate is useless; start over.
+pub const MAX_PREDICT_SECS: f64 = 10.0;
+/// Move the ENU origin along once the estimate is this far from it, so the
+/// flat-earth projection never has to stretch.
+const REANCHOR_M: f64 = 500.0;
+
+/// One axis of a constant-velocity model. East and north are filtered
+/// independently: with a diagonal measurement covariance they don't couple.
+#[derive(Copy, Clone, Debug)]
+struct Axis {
+    pos: f64,
+    vel: f64,
+    cov: [[f64; 2]; 2],
+}
+
+impl Axis {
+    fn new(pos: f64, variance: f64) -> Self {
+        Axis {
+            pos,
+            vel: 0.0,
+            cov: [[variance, 0.0], [0.0, INITIAL_VELOCITY_VARIANCE]],
+        }
+    }
+
+    fn predict(&mut self, dt: f64) {
+        self.pos += self.vel * dt;
+
+        let [[p00, p01], [p10, p11]] = self.cov;
+        let dt2 = dt * dt;
+        let q = ACCEL_VARIANCE;
+        self.cov = [
+            [
+                p00 + dt * (p10 + p01) + dt2 * p11 + q * dt2 * dt2 / 4.0,
+                p01 + dt * p11 + q * dt2 * dt / 2.0,
+            ],
+            [p10 + dt * p11 + q * dt2 * dt / 2.0, p11 + q * dt2],
+        ];
+    }
+
+    fn update(&mut self, measured: f64, variance: f64) {
+        let [[p00, p01], [p10, p11]] = self.cov;
+        let innovation_variance = p00 + variance;
+        let k0 = p00 / innovation_variance;
+        let k1 = p10 / innovation_variance;
+        let innovation = measured - self.pos;
+
+        self.pos += k0 * innovation;
+        self.vel += k1 * innovation;
+        self.cov = [
+            [(1.0 - k0) * p00, (1.0 - k0) * p01],
+            [p10 - k1 * p00, p11 - k1 * p01],
+        ];
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Estimate {
+    pub position: LatLonAlt,
+    pub speed_mps: f64,
+    /// Degrees true; meaningless when `speed_mps` is near zero.
+    pub heading: f64,
+}
+
+/// Constant-velocity Kalman filter over local ENU coordinates. The origin
+/// is the first fix after a reset; altitude is passed through untouched.
+#[derive(Copy, Clone, Debug)]
+pub struct PositionFilter {
+    origin: Option<LatLonAlt>,
+    east: Axis,
+    north: Axis,
+}
+
+impl PositionFilter {
+    pub fn new() -> Self {
+        PositionFilter {
+            origin: None,
+            east: Axis::new(0.0, 0.0),
+            north: Axis::new(0.0, 0.0),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.origin = None;
+    }
+
+    /// Folds in a fix taken `dt_secs` after the previous one.
+    pub fn update(&mut self, fix: LatLonAlt, hdop: f32, dt_secs: f64) -> Estimate {
+        let sigma = hdop as f64 * UERE_M;
+        let variance = sigma * sigma;
+
+        let origin = match self.origin {
+            Some(origin) if dt_secs <= MAX_PREDICT_SECS => {
+                let (east, north) = to_enu(origin, fix);
+                self.east.predict(dt_secs);
+                self.north.predict(dt_secs);
+                self.east.update(east, variance);
+                self.north.update(north, variance);
+                if self.east.pos.abs() > REANCHOR_M || self.north.pos.abs() > REANCHOR_M {
+                    let anchor = from_enu(origin, self.east.pos, self.north.pos, fix.altitude);
+                    self.origin = Some(anchor);
+                    self.east.pos = 0.0;
+                    self.north.pos = 0.0;
+                    anchor
+                } else {
+                    origin
+                }
+            }
+            _ => {
+                self.origin = Some(fix);
+                self.east = Axis::new(0.0, variance);
+                self.north = Axis::new(0.0, variance);
+                fix
+            }
+        };
+
+        Estimate {
+            position: from_enu(origin, self.east.pos, self.north.pos, fix.altitude),
+            speed_mps: sqrt(self.east.vel * self.east.vel + self.north.vel * self.north.vel),
+            heading: enu_heading(self.east.vel, self.north.vel),
+        }
+    }
+}
+
+impl Default for PositionFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
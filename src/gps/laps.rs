@@ -0,0 +1,130 @@
+use heapless::Deque;
+
+use crate::gps::session::SessionStats;
+
+/// Laps kept in RAM for the laps page; the track log has all of them.
+pub const RECENT_LAPS: usize = 4;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LapKind {
+    /// Taken from the button.
+    Manual,
+    /// Taken automatically every split distance.
+    Split,
+}
+
+/// Totals for one lap of a session.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Lap {
+    /// 1-based within the session.
+    pub number: u16,
+    pub kind: LapKind,
+    pub distance_m: f32,
+    pub elapsed_ms: u32,
+    pub moving_ms: u32,
+    pub gain_m: f32,
+}
+
+impl Lap {
+    /// Average over the lap's moving time.
+    pub fn average_speed_mps(&self) -> f64 {
+        if self.moving_ms == 0 {
+            return 0.0;
+        }
+        self.distance_m as f64 * 1000.0 / self.moving_ms as f64
+    }
+}
+
+/// Session totals at the start of the current lap.
+#[derive(Copy, Clone, Debug, Default)]
+struct Mark {
+    distance_m: f64,
+    elapsed_ms: u64,
+    moving_ms: u64,
+    gain_m: f32,
+}
+
+/// Cuts a session into laps by subtracting the session totals at the start
+/// of each lap from the totals at its end.
+#[derive(Clone, Debug, Default)]
+pub struct LapTracker {
+    /// Automatic split distance; zero turns splits off.
+    pub split_m: f64,
+    start: Mark,
+    count: u16,
+    recent: Deque<Lap, RECENT_LAPS>,
+}
+
+impl LapTracker {
+    pub fn new() -> Self {
+        LapTracker::default()
+    }
+
+    /// Clears the laps for a new session, keeping the split distance.
+    pub fn reset(&mut self) {
+        *self = LapTracker {
+            split_m: self.split_m,
+            ..LapTracker::new()
+        };
+    }
+
+    /// Number of laps completed so far.
+    pub fn count(&self) -> u16 {
+        self.count
+    }
+
+    /// Ends the current lap on request.
+    pub fn lap(&mut self, stats: &SessionStats, distance_m: f64, gain_m: f32) -> Lap {
+        self.close(LapKind::Manual, stats, distance_m, gain_m)
+    }
+
+    /// Ends the current lap if it has covered the split distance.
+    pub fn update(&mut self, stats: &SessionStats, distance_m: f64, gain_m: f32) -> Option<Lap> {
+        if self.split_m > 0.0 && distance_m - self.start.distance_m >= self.split_m {
+            Some(self.close(LapKind::Split, stats, distance_m, gain_m))
+        } else {
+            None
+        }
+    }
+
+    /// The lap in progress, numbered as it will be once it ends.
+    pub fn current(&self, stats: &SessionStats, distance_m: f64, gain_m: f32) -> Lap {
+        Lap {
+            number: self.count + 1,
+            kind: LapKind::Manual,
+            distance_m: (distance_m - self.start.distance_m) as f32,
+            elapsed_ms: stats.elapsed_ms.saturating_sub(self.start.elapsed_ms) as u32,
+            moving_ms: stats.moving_ms.saturating_sub(self.start.moving_ms) as u32,
+            gain_m: gain_m - self.start.gain_m,
+        }
+    }
+
+    /// The most recently completed lap.
+    pub fn last(&self) -> Option<&Lap> {
+        self.recent.back()
+    }
+
+    /// The lap before [`last`](Self::last), for comparison.
+    pub fn previous(&self) -> Option<&Lap> {
+        self.recent.iter().rev().nth(1)
+    }
+
+    fn close(&mut self, kind: LapKind, stats: &SessionStats, distance_m: f64, gain_m: f32) -> Lap {
+        let lap = Lap {
+            kind,
+            ..self.current(stats, distance_m, gain_m)
+        };
+        self.count = lap.number;
+        self.start = Mark {
+            distance_m,
+            elapsed_ms: stats.elapsed_ms,
+            moving_ms: stats.moving_ms,
+            gain_m,
+        };
+        if self.recent.is_full() {
+            self.recent.pop_front();
+        }
+        let _ = self.recent.push_back(lap);
+        lap
+    }
+}
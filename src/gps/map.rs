@@ -0,0 +1,164 @@
+use heapless::Vec;
+use libm::{cos, sin, sqrt};
+
+use crate::gps::fns::{LatLonAlt, haversine_distance_m, to_enu};
+
+pub const MAX_BREADCRUMBS: usize = 512;
+/// Spacing of a fresh trail. Each time the trail fills up every other
+/// point is dropped and the spacing doubles, so a long session still fits.
+const INITIAL_SPACING_M: f64 = 5.0;
+/// Degrees are stored as integers in these units, 1.1 cm at the equator.
+const UNITS_PER_DEGREE: f64 = 1e7;
+/// Zoom steps in meters per pixel, closest first.
+const ZOOM_LEVELS: [f64; 14] = [
+    0.5, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0, 10000.0,
+];
+
+/// Which way is up on the map.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum MapOrientation {
+    #[default]
+    NorthUp,
+    HeadingUp,
+}
+
+impl MapOrientation {
+    /// Maps the value stored by the Map setting.
+    pub fn from_setting(value: isize) -> Self {
+        match value {
+            1 => MapOrientation::HeadingUp,
+            _ => MapOrientation::NorthUp,
+        }
+    }
+}
+
+/// The recorded trail, kept in RAM for the map page. The track log in flash
+/// has every point; this keeps enough to draw.
+#[derive(Clone, Debug)]
+pub struct Breadcrumbs {
+    points: Vec<(i32, i32), MAX_BREADCRUMBS>,
+    spacing_m: f64,
+    last: Option<LatLonAlt>,
+}
+
+impl Default for Breadcrumbs {
+    fn default() -> Self {
+        Breadcrumbs::new()
+    }
+}
+
+impl Breadcrumbs {
+    pub const fn new() -> Self {
+        Breadcrumbs {
+            points: Vec::new(),
+            spacing_m: INITIAL_SPACING_M,
+            last: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Breadcrumbs::new();
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Current minimum distance between kept points.
+    pub fn spacing_m(&self) -> f64 {
+        self.spacing_m
+    }
+
+    /// Adds `point` if it is far enough from the last one kept. Returns
+    /// whether it was kept.
+    pub fn push(&mut self, point: LatLonAlt) -> bool {
+        if let Some(last) = self.last
+            && haversine_distance_m(last, point) < self.spacing_m
+        {
+            return false;
+        }
+        if self.points.is_full() {
+            let mut index = 0;
+            self.points.retain(|_| {
+                index += 1;
+                index % 2 == 1
+            });
+            self.spacing_m *= 2.0;
+        }
+        let _ = self.points.push((
+            (point.latitude * UNITS_PER_DEGREE) as i32,
+            (point.longitude * UNITS_PER_DEGREE) as i32,
+        ));
+        self.last = Some(point);
+        true
+    }
+
+    /// Kept points, oldest first. Altitudes are not kept.
+    pub fn iter(&self) -> impl Iterator<Item = LatLonAlt> + '_ {
+        self.points.iter().map(|&(latitude, longitude)| LatLonAlt {
+            latitude: f64::from(latitude) / UNITS_PER_DEGREE,
+            longitude: f64::from(longitude) / UNITS_PER_DEGREE,
+            altitude: 0.0,
+        })
+    }
+
+    /// Where the trail began.
+    pub fn start(&self) -> Option<LatLonAlt> {
+        self.iter().next()
+    }
+}
+
+/// Projects positions to screen offsets around a center position, scaled
+/// and turned for the map.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MapView {
+    pub center: LatLonAlt,
+    /// Compass direction that points up the screen.
+    pub up_deg: f64,
+    pub meters_per_pixel: f64,
+}
+
+impl MapView {
+    /// The closest zoom step that keeps every point of `points` within
+    /// `radius_px` of the center.
+    pub fn fit(
+        center: LatLonAlt,
+        up_deg: f64,
+        points: impl Iterator<Item = LatLonAlt>,
+        radius_px: f64,
+    ) -> Self {
+        // Planar around the center; far points are only squeezed slightly.
+        let extent_m = points
+            .map(|point| {
+                let (east, north) = to_enu(center, point);
+                sqrt(east * east + north * north)
+            })
+            .fold(0.0, f64::max);
+        let meters_per_pixel = ZOOM_LEVELS
+            .iter()
+            .copied()
+            .find(|level| extent_m <= level * radius_px)
+            .unwrap_or(ZOOM_LEVELS[ZOOM_LEVELS.len() - 1]);
+        MapView {
+            center,
+            up_deg,
+            meters_per_pixel,
+        }
+    }
+
+    /// Pixels right of and below the center where `point` goes.
+    pub fn project(&self, point: LatLonAlt) -> (f64, f64) {
+        let (east, north) = to_enu(self.center, point);
+        let (sin_up, cos_up) = {
+            let radians = self.up_deg.to_radians();
+            (sin(radians), cos(radians))
+        };
+        let right = east * cos_up - north * sin_up;
+        let up = east * sin_up + north * cos_up;
+        (right / self.meters_per_pixel, -up / self.meters_per_pixel)
+    }
+}
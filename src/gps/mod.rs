@@ -1,3 +1,17 @@
+pub mod auto_pause;
+pub mod elevation;
 pub mod fns;
+pub mod gpx;
+pub mod kalman;
+pub mod laps;
+pub mod map;
+pub mod power;
+pub mod profile;
 pub mod reader;
+pub mod receiver;
+pub mod replay;
+pub mod route;
+pub mod session;
+pub mod sky;
 pub mod stack;
+pub mod waypoint;
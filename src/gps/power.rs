@@ -0,0 +1,159 @@
+//! Receiver duty-cycling. Between fixes the receiver sleeps: in standby for
+//! short periods, where the engine keeps its almanac and restarts hot, and in
+//! backup for long ones, where only the RTC runs and the receiver wakes on
+//! its own.
+
+use crate::gps::receiver::Command;
+
+/// Periods at least this long sleep in backup rather than standby.
+const BACKUP_MIN_PERIOD_SECS: u32 = 30;
+/// Head start the engine gets to have a fix ready when the period is up.
+const STANDBY_WAKE_LEAD_MS: u64 = 1_000;
+/// A hot start out of backup takes longer than one out of standby.
+const BACKUP_WAKE_LEAD_MS: u64 = 5_000;
+/// Awake this long without a fix, the receiver sleeps out a period anyway
+/// rather than searching a blocked sky until the battery is flat.
+pub const MAX_AWAKE_MS: u64 = 120_000;
+
+/// How often the receiver takes a fix.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PowerProfile {
+    #[default]
+    Continuous,
+    Every5s,
+    Every30s,
+    EveryMinute,
+}
+
+impl PowerProfile {
+    /// Maps the value stored by the Power setting, in seconds between fixes.
+    /// The track log stores the same value.
+    pub fn from_setting(value: isize) -> Self {
+        match value {
+            5 => PowerProfile::Every5s,
+            30 => PowerProfile::Every30s,
+            60 => PowerProfile::EveryMinute,
+            _ => PowerProfile::Continuous,
+        }
+    }
+
+    /// Seconds between fixes, 0 for continuous.
+    pub fn period_secs(self) -> u16 {
+        match self {
+            PowerProfile::Continuous => 0,
+            PowerProfile::Every5s => 5,
+            PowerProfile::Every30s => 30,
+            PowerProfile::EveryMinute => 60,
+        }
+    }
+
+    pub fn is_continuous(self) -> bool {
+        self == PowerProfile::Continuous
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DutyState {
+    /// Searching or tracking since `since_ms`.
+    Awake { since_ms: u64 },
+    /// Engine stopped; `DutyCycle::poll` wakes it at `wake_ms`.
+    Standby { wake_ms: u64 },
+    /// Powered down; the receiver wakes itself at `wake_ms`.
+    Backup { wake_ms: u64 },
+}
+
+/// Decides when to put the receiver to sleep and when to wake it. Times are
+/// milliseconds on any monotonic clock; the commands returned are for
+/// `ReceiverConfig` to send.
+#[derive(Copy, Clone, Debug)]
+pub struct DutyCycle {
+    pub profile: PowerProfile,
+    state: DutyState,
+}
+
+impl Default for DutyCycle {
+    fn default() -> Self {
+        DutyCycle::new()
+    }
+}
+
+impl DutyCycle {
+    pub const fn new() -> Self {
+        DutyCycle {
+            profile: PowerProfile::Continuous,
+            state: DutyState::Awake { since_ms: 0 },
+        }
+    }
+
+    pub fn state(&self) -> DutyState {
+        self.state
+    }
+
+    pub fn is_asleep(&self) -> bool {
+        !matches!(self.state, DutyState::Awake { .. })
+    }
+
+    /// Switches to `profile`. An awake receiver gets a fresh search budget;
+    /// leaving a sleeping profile for continuous wakes a receiver in standby
+    /// at the next `poll`.
+    pub fn set_profile(&mut self, profile: PowerProfile, now_ms: u64) {
+        if profile == self.profile {
+            return;
+        }
+        self.profile = profile;
+        match &mut self.state {
+            DutyState::Awake { since_ms } => *since_ms = now_ms,
+            DutyState::Standby { wake_ms } if profile.is_continuous() => *wake_ms = now_ms,
+            _ => {}
+        }
+    }
+
+    /// Call with every accepted fix. Returns the command that puts the
+    /// receiver to sleep until the next fix is due.
+    pub fn fix(&mut self, now_ms: u64) -> Option<Command> {
+        match self.state {
+            DutyState::Awake { .. } => self.sleep(now_ms),
+            _ => None,
+        }
+    }
+
+    /// Call regularly. Returns the command that wakes the receiver once its
+    /// sleep is over, or puts it to sleep after searching too long.
+    pub fn poll(&mut self, now_ms: u64) -> Option<Command> {
+        match self.state {
+            DutyState::Standby { wake_ms } if now_ms >= wake_ms => {
+                self.state = DutyState::Awake { since_ms: now_ms };
+                Some(Command::Wake)
+            }
+            DutyState::Backup { wake_ms } if now_ms >= wake_ms => {
+                self.state = DutyState::Awake { since_ms: now_ms };
+                None
+            }
+            DutyState::Awake { since_ms } if now_ms.saturating_sub(since_ms) >= MAX_AWAKE_MS => {
+                self.sleep(now_ms)
+            }
+            _ => None,
+        }
+    }
+
+    fn sleep(&mut self, now_ms: u64) -> Option<Command> {
+        let period_secs = u32::from(self.profile.period_secs());
+        if period_secs == 0 {
+            return None;
+        }
+        let period_ms = u64::from(period_secs) * 1000;
+        if period_secs >= BACKUP_MIN_PERIOD_SECS {
+            let sleep_ms = period_ms - BACKUP_WAKE_LEAD_MS;
+            self.state = DutyState::Backup {
+                wake_ms: now_ms + sleep_ms,
+            };
+            Some(Command::Backup((sleep_ms / 1000) as u32))
+        } else {
+            self.state = DutyState::Standby {
+                wake_ms: now_ms + period_ms - STANDBY_WAKE_LEAD_MS,
+            };
+            Some(Command::Standby)
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use heapless::Vec;
+
+pub const MAX_PROFILE_SAMPLES: usize = 256;
+/// Spacing of a fresh profile. Each time the buffer fills up every other
+/// sample is dropped and the spacing doubles, so a long session still fits.
+const INITIAL_SPACING_M: f32 = 10.0;
+
+/// Altitude over distance for the session, downsampled to fit in RAM.
+/// Distances are meters into the session, as counted by `GeoStack`.
+#[derive(Clone, Debug)]
+pub struct ElevationProfile {
+    samples: Vec<(f32, f32), MAX_PROFILE_SAMPLES>,
+    spacing_m: f32,
+    /// The newest point, kept or not, so the chart reaches the current
+    /// position.
+    latest: Option<(f32, f32)>,
+    min_altitude: Option<f32>,
+    max_altitude: Option<f32>,
+}
+
+impl Default for ElevationProfile {
+    fn default() -> Self {
+        ElevationProfile::new()
+    }
+}
+
+impl ElevationProfile {
+    pub const fn new() -> Self {
+        ElevationProfile {
+            samples: Vec::new(),
+            spacing_m: INITIAL_SPACING_M,
+            latest: None,
+            min_altitude: None,
+            max_altitude: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = ElevationProfile::new();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.latest.is_none()
+    }
+
+    /// Current minimum distance between kept samples.
+    pub fn spacing_m(&self) -> f32 {
+        self.spacing_m
+    }
+
+    /// Lowest and highest altitude pushed, including samples since dropped.
+    pub fn range(&self) -> Option<(f32, f32)> {
+        self.min_altitude.zip(self.max_altitude)
+    }
+
+    /// Distance of the newest point.
+    pub fn distance_m(&self) -> f32 {
+        self.latest.map_or(0.0, |(distance, _)| distance)
+    }
+
+    /// Records `altitude` at `distance_m` into the session. Returns whether
+    /// the sample was kept in the buffer.
+    pub fn push(&mut self, distance_m: f32, altitude: f32) -> bool {
+        self.latest = Some((distance_m, altitude));
+        self.min_altitude = Some(self.min_altitude.map_or(altitude, |m| m.min(altitude)));
+        self.max_altitude = Some(self.max_altitude.map_or(altitude, |m| m.max(altitude)));
+
+        if let Some(&(last, _)) = self.samples.last()
+            && distance_m - last < self.spacing_m
+        {
+            return false;
+        }
+        if self.samples.is_full() {
+            let mut index = 0;
+            self.samples.retain(|_| {
+                index += 1;
+                index % 2 == 1
+            });
+            self.spacing_m *= 2.0;
+        }
+        let _ = self.samples.push((distance_m, altitude));
+        true
+    }
+
+    /// `(distance, altitude)` points, oldest first, ending at the newest.
+    pub fn iter(&self) -> impl Iterator<Item = (f32, f32)> + '_ {
+        let last_kept = self.samples.last().map(|&(distance, _)| distance);
+        let tail = self
+            .latest
+            .filter(|&(distance, _)| last_kept.is_none_or(|last| distance > last));
+        self.samples.iter().copied().chain(tail)
+    }
+
+    /// Altitude at `distance_m`, interpolated between the points around it.
+    /// `None` outside the recorded stretch.
+    pub fn altitude_at(&self, distance_m: f32) -> Option<f32> {
+        let mut previous: Option<(f32, f32)> = None;
+        for (distance, altitude) in self.iter() {
+            if distance >= distance_m {
+                return match previous {
+                    Some((from_distance, from_altitude)) if distance > from_distance => {
+                        let t = (distance_m - from_distance) / (distance - from_distance);
+                        Some(from_altitude + t * (altitude - from_altitude))
+                    }
+                    Some(_) => Some(altitude),
+                    None => (distance == distance_m).then_some(altitude),
+                };
+            }
+            previous = Some((distance, altitude));
+        }
+        None
+    }
+}
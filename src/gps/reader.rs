@@ -1,45 +1,231 @@
-use chrono::NaiveTime;
-use embassy_nrf::buffered_uarte::BufferedUarte;
+use chrono::{NaiveDate, NaiveTime};
 use embassy_sync::{blocking_mutex::raw::NoopRawMutex, channel::Sender};
+use embedded_io_async::Read;
 use heapless::Vec;
-use nmea::{Nmea, sentences::FixType};
+use nmea::{
+    Nmea, ParseResult, SentenceType,
+    sentences::{FixType, GnssType},
+};
 
-pub struct GpsReader<'a> {
-    uart: BufferedUarte<'a>,
-    rx_buffer: [u8; 1],
-    sentence_buffer: Vec<u8, 82>,
+use crate::gps::receiver::{ACK_QUEUE_LEN, Ack};
+
+pub const MAX_SATELLITES: usize = 32;
+
+/// NMEA 0183 caps a sentence at 82 characters including `$` and CRLF.
+pub const MAX_SENTENCE_LEN: usize = 82;
+
+const RX_CHUNK_LEN: usize = 64;
+
+const KNOWN_TALKERS: [&[u8; 2]; 9] = [
+    b"GP", b"GL", b"GA", b"GB", b"BD", b"GQ", b"QZ", b"GN", b"GI",
+];
+
+pub type SatelliteList = Vec<SatelliteInfo, MAX_SATELLITES>;
+
+pub type Sentence = Vec<u8, MAX_SENTENCE_LEN>;
+
+/// Reads NMEA from any byte stream: a UART on the device, a recorded log
+/// on the host, or a test double.
+pub struct GpsReader<R: Read> {
+    input: R,
+    rx_buffer: [u8; RX_CHUNK_LEN],
+    rx_pos: usize,
+    rx_len: usize,
+    lines: LineAssembler,
     parser: Nmea,
-    sender: Sender<'static, NoopRawMutex, ParseOut, 1>,
+    stats: GpsReaderStats,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum LineState {
+    /// Waiting for `$`; anything else is line noise.
+    Idle,
+    Sentence,
+    /// The sentence outgrew the buffer; drop bytes until the line ends.
+    Overflowed,
+}
+
+enum LineEvent {
+    Line(Sentence),
+    Overflow,
+}
+
+struct LineAssembler {
+    state: LineState,
+    buffer: Sentence,
+}
+
+impl LineAssembler {
+    fn new() -> Self {
+        LineAssembler {
+            state: LineState::Idle,
+            buffer: Sentence::new(),
+        }
+    }
+
+    fn feed(&mut self, byte: u8) -> Option<LineEvent> {
+        match (self.state, byte) {
+            // A `$` always starts over, even mid-sentence.
+            (_, b'$') => {
+                self.buffer.clear();
+                let _ = self.buffer.push(byte);
+                self.state = LineState::Sentence;
+                None
+            }
+            (LineState::Sentence, b'\n') => {
+                self.state = LineState::Idle;
+                Some(LineEvent::Line(core::mem::take(&mut self.buffer)))
+            }
+            (LineState::Overflowed, b'\n') => {
+                self.state = LineState::Idle;
+                self.buffer.clear();
+                Some(LineEvent::Overflow)
+            }
+            (LineState::Sentence, b'\r') => None,
+            (LineState::Sentence, _) => {
+                if self.buffer.push(byte).is_err() {
+                    self.state = LineState::Overflowed;
+                }
+                None
+            }
+            (LineState::Idle | LineState::Overflowed, _) => None,
+        }
+    }
+}
+
+/// Running sentence counters since boot.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct GpsReaderStats {
+    pub good: u32,
+    pub bad_checksum: u32,
+    pub overflow: u32,
+    pub unknown_talker: u32,
+    pub parse_errors: u32,
 }
 
 pub struct ParseOut {
     pub fix: Option<FixType>,
     // Optional: parsed lat/lon/alt
     pub reader_results: Option<GpsReaderResults>,
+    // GSA: dilution of precision and satellites used in the fix
+    pub dop: Option<Dop>,
+    pub active_satellites: Option<u8>,
+    pub fix_mode: Option<FixMode>,
+    // GSV: every satellite in view, sent once per complete GSV group
+    pub satellites: Option<SatelliteList>,
+    // $PAIR001: the receiver's answer to a configuration command
+    pub ack: Option<Ack>,
+    pub stats: GpsReaderStats,
+}
+
+impl ParseOut {
+    fn empty(stats: GpsReaderStats) -> Self {
+        ParseOut {
+            fix: None,
+            reader_results: None,
+            dop: None,
+            active_satellites: None,
+            fix_mode: None,
+            satellites: None,
+            ack: None,
+            stats,
+        }
+    }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub struct GpsReaderResults {
     pub lat: Option<f64>,
     pub lon: Option<f64>,
     pub alt: Option<f32>,
     pub hdop: Option<f32>,
     pub timestamp: Option<NaiveTime>,
+    pub date: Option<NaiveDate>,
+    pub speed_knots: Option<f32>,
+    pub course: Option<f32>,
 }
-impl<'a> GpsReader<'a> {
-    pub fn new(
-        uart: BufferedUarte<'a>,
-        sender: Sender<'static, NoopRawMutex, ParseOut, 1>,
-    ) -> Self {
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Dop {
+    pub pdop: Option<f32>,
+    pub hdop: Option<f32>,
+    pub vdop: Option<f32>,
+}
+
+/// GSA navigation mode: whether the receiver has a position, and whether
+/// it includes altitude.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FixMode {
+    NoFix,
+    Fix2D,
+    Fix3D,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Constellation {
+    Gps,
+    Glonass,
+    Galileo,
+    Beidou,
+    Qzss,
+    Other,
+}
+
+impl From<GnssType> for Constellation {
+    fn from(gnss_type: GnssType) -> Self {
+        match gnss_type {
+            GnssType::Gps => Constellation::Gps,
+            GnssType::Glonass => Constellation::Glonass,
+            GnssType::Galileo => Constellation::Galileo,
+            GnssType::Beidou => Constellation::Beidou,
+            GnssType::Qzss => Constellation::Qzss,
+            _ => Constellation::Other,
+        }
+    }
+}
+
+impl Constellation {
+    /// Two-letter tag for labels.
+    pub fn short_name(self) -> &'static str {
+        match self {
+            Constellation::Gps => "GP",
+            Constellation::Glonass => "GL",
+            Constellation::Galileo => "GA",
+            Constellation::Beidou => "BD",
+            Constellation::Qzss => "QZ",
+            Constellation::Other => "--",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SatelliteInfo {
+    pub constellation: Constellation,
+    pub prn: u32,
+    pub elevation: Option<f32>,
+    pub azimuth: Option<f32>,
+    pub snr: Option<f32>,
+    pub in_use: bool,
+}
+
+impl<R: Read> GpsReader<R> {
+    pub fn new(input: R) -> Self {
         GpsReader {
-            uart,
-            rx_buffer: [0; 1],
-            sentence_buffer: Vec::<u8, 82>::new(),
+            input,
+            rx_buffer: [0; RX_CHUNK_LEN],
+            rx_pos: 0,
+            rx_len: 0,
+            lines: LineAssembler::new(),
             parser: Nmea::default(),
-            sender,
+            stats: GpsReaderStats::default(),
         }
     }
 
+    pub fn stats(&self) -> GpsReaderStats {
+        self.stats
+    }
+
     fn get_pos(&mut self) -> GpsReaderResults {
         GpsReaderResults {
             lat: self.parser.latitude(),
@@ -47,64 +233,199 @@ impl<'a> GpsReader<'a> {
             alt: self.parser.altitude(),
             hdop: self.parser.hdop(),
             timestamp: self.parser.fix_timestamp(),
+            date: self.parser.fix_date,
+            speed_knots: self.parser.speed_over_ground,
+            course: self.parser.true_course,
         }
     }
 
-    fn parse_line(&mut self, line: Vec<u8, 82>) -> Option<ParseOut> {
-        let msg = heapless::String::<82>::from(line.iter().map(|x| *x as char).collect());
-        // info!("MSG: {:?}", defmt::Debug2Format(&msg));
-        if line.starts_with(&[b'$']) && line.contains(&b'*') {
-            match self.parser.parse(&msg) {
-                Ok(_) => {
-                    if let Some(fix) = self.parser.fix_type() {
-                        let lla = self.get_pos();
-                        Some(ParseOut {
-                            fix: Some(fix),
-                            reader_results: Some(lla),
-                        })
-                    } else {
-                        Some(ParseOut {
-                            fix: None,
-                            reader_results: None,
-                        })
-                    }
+    fn get_satellites(&self) -> SatelliteList {
+        let in_use = self.parser.fix_satellites_prns.as_ref();
+        let mut satellites = SatelliteList::new();
+        for sat in self.parser.satellites().iter() {
+            let _ = satellites.push(SatelliteInfo {
+                constellation: sat.gnss_type().into(),
+                prn: sat.prn(),
+                elevation: sat.elevation(),
+                azimuth: sat.azimuth(),
+                snr: sat.snr(),
+                in_use: in_use.is_some_and(|prns| prns.contains(&sat.prn())),
+            });
+        }
+        satellites
+    }
+
+    /// ZDA carries the full UTC date; the `Nmea` state machine only learns
+    /// the date from RMC, so fold it in by hand.
+    fn apply_zda(&mut self, msg: &str) {
+        if let Ok(ParseResult::ZDA(zda)) = nmea::parse_str(msg) {
+            if let (Some(year), Some(month), Some(day)) = (zda.year, zda.month, zda.day) {
+                self.parser.fix_date =
+                    NaiveDate::from_ymd_opt(year.into(), month.into(), day.into());
+            }
+            if zda.utc_time.is_some() {
+                self.parser.fix_time = zda.utc_time;
+            }
+        }
+    }
+
+    fn parse_line(&mut self, line: Sentence) -> Option<ParseOut> {
+        if !line.starts_with(b"$") {
+            return None;
+        }
+        if !checksum_ok(&line) {
+            self.stats.bad_checksum += 1;
+            warn!("gps: bad checksum {:?}", self.stats);
+            return None;
+        }
+        // Proprietary `$P...` sentences are receiver replies, not fixes.
+        // Only command acknowledgements are passed on.
+        if line.get(1) == Some(&b'P') {
+            let ack = Ack::parse(&line)?;
+            let mut out = ParseOut::empty(self.stats);
+            out.ack = Some(ack);
+            return Some(out);
+        }
+        if !line
+            .get(1..3)
+            .is_some_and(|talker| KNOWN_TALKERS.iter().any(|t| *t == talker))
+        {
+            self.stats.unknown_talker += 1;
+            warn!("gps: unknown talker {:?}", self.stats);
+            return None;
+        }
+
+        // A valid checksum doesn't make the bytes text.
+        let Ok(msg) = core::str::from_utf8(&line) else {
+            self.stats.parse_errors += 1;
+            warn!("gps: sentence not UTF-8 {:?}", self.stats);
+            return None;
+        };
+        if msg.get(3..6) == Some("ZDA") {
+            self.apply_zda(msg);
+            self.stats.good += 1;
+            return Some(ParseOut::empty(self.stats));
+        }
+
+        let parsed = self.parser.parse(msg);
+        match parsed {
+            Ok(_) | Err(nmea::Error::Unsupported(_)) => self.stats.good += 1,
+            Err(_) => {
+                self.stats.parse_errors += 1;
+                warn!("gps: parse error {:?}", self.stats);
+                return None;
+            }
+        }
+
+        let mut out = ParseOut::empty(self.stats);
+        match parsed {
+            Ok(SentenceType::GGA) => {
+                if let Some(fix) = self.parser.fix_type() {
+                    out.fix = Some(fix);
+                    out.reader_results = Some(self.get_pos());
                 }
-                Err(_) => Some(ParseOut {
-                    fix: None,
-                    reader_results: None,
-                }),
             }
-        } else {
-            Some(ParseOut {
-                fix: None,
-                reader_results: None,
-            })
+            Ok(SentenceType::GSA) => {
+                out.dop = Some(Dop {
+                    pdop: self.parser.pdop,
+                    hdop: self.parser.hdop,
+                    vdop: self.parser.vdop,
+                });
+                out.active_satellites = self
+                    .parser
+                    .fix_satellites_prns
+                    .as_ref()
+                    .map(|prns| prns.len() as u8);
+                out.fix_mode = gsa_fix_mode(msg);
+            }
+            Ok(SentenceType::GSV) if is_last_gsv_message(msg) => {
+                out.satellites = Some(self.get_satellites());
+            }
+            _ => {}
         }
+        Some(out)
     }
 
-    pub async fn run(&mut self) {
+    /// Returns the next parsed sentence, or `None` once the input is
+    /// exhausted. Junk and malformed lines are counted and skipped.
+    pub async fn next(&mut self) -> Option<ParseOut> {
         loop {
-            if self.uart.read(&mut self.rx_buffer).await.is_ok() {
-                let byte = self.rx_buffer[0];
-                match byte {
-                    b'$' => {
-                        self.sentence_buffer.clear();
-                        let _ = self.sentence_buffer.push(byte);
-                    }
-                    b'\n' => {
-                        let line = core::mem::take(&mut self.sentence_buffer);
+            while self.rx_pos < self.rx_len {
+                let byte = self.rx_buffer[self.rx_pos];
+                self.rx_pos += 1;
+                match self.lines.feed(byte) {
+                    Some(LineEvent::Line(line)) => {
                         if let Some(out) = self.parse_line(line) {
-                            self.sender.send(out).await;
+                            return Some(out);
                         }
                     }
-                    b'\r' => {}
-                    _ => {
-                        if self.sentence_buffer.starts_with(b"$") {
-                            let _ = self.sentence_buffer.push(byte);
-                        }
+                    Some(LineEvent::Overflow) => {
+                        self.stats.overflow += 1;
+                        warn!("gps: sentence too long {:?}", self.stats);
                     }
+                    None => {}
                 }
             }
+
+            match self.input.read(&mut self.rx_buffer).await {
+                Ok(0) => return None,
+                Ok(len) => {
+                    self.rx_pos = 0;
+                    self.rx_len = len;
+                }
+                // UART framing/parity errors are transient; keep reading.
+                Err(_) => {}
+            }
         }
     }
+
+    /// Parses until the input ends. Acknowledgements go to `acks`, dropped
+    /// when nobody is waiting for them; everything else to `sender`.
+    pub async fn run(
+        &mut self,
+        sender: Sender<'static, NoopRawMutex, ParseOut, 1>,
+        acks: Sender<'static, NoopRawMutex, Ack, ACK_QUEUE_LEN>,
+    ) {
+        while let Some(out) = self.next().await {
+            match out.ack {
+                Some(ack) => {
+                    let _ = acks.try_send(ack);
+                }
+                None => sender.send(out).await,
+            }
+        }
+    }
+}
+
+/// Verifies the `*HH` suffix against the XOR of every byte between `$` and `*`.
+fn checksum_ok(line: &[u8]) -> bool {
+    let Some(star) = line.iter().rposition(|&b| b == b'*') else {
+        return false;
+    };
+    let expected = line
+        .get(star + 1..star + 3)
+        .and_then(|hex| core::str::from_utf8(hex).ok())
+        .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+    let actual = line[1..star].iter().fold(0u8, |acc, b| acc ^ b);
+    expected == Some(actual)
+}
+
+/// `$xxGSA,<selection>,<mode>,...` - the `Nmea` state machine drops the
+/// mode, so read it straight from the sentence.
+fn gsa_fix_mode(msg: &str) -> Option<FixMode> {
+    match msg.split(',').nth(2)? {
+        "1" => Some(FixMode::NoFix),
+        "2" => Some(FixMode::Fix2D),
+        "3" => Some(FixMode::Fix3D),
+        _ => None,
+    }
+}
+
+/// `$xxGSV,<total>,<number>,...` - true for the last message of a group.
+fn is_last_gsv_message(msg: &str) -> bool {
+    let mut fields = msg.split(',').skip(1);
+    match (fields.next(), fields.next()) {
+        (Some(total), Some(number)) => total == number,
+        _ => false,
+    }
 }
@@ -0,0 +1,382 @@
+//! Configuration of the Reyax RYS352A (Airoha AG3352). Commands are
+//! proprietary `$PAIR` sentences with the usual NMEA checksum; the receiver
+//! answers each one with `$PAIR001,<command>,<result>`.
+
+use core::{fmt::Write as _, future::Future, pin::pin};
+
+use embassy_futures::select::{Either, select};
+use embassy_sync::{blocking_mutex::raw::NoopRawMutex, channel::Receiver};
+use embedded_io_async::Write;
+use heapless::{String, Vec};
+
+use crate::gps::reader::MAX_SENTENCE_LEN;
+
+/// Acknowledgements buffered between the reader and the configurator.
+pub const ACK_QUEUE_LEN: usize = 4;
+/// Sends of one command before giving up on a missing or busy answer.
+pub const MAX_ATTEMPTS: u8 = 3;
+
+const PAIR_ACK: &[u8] = b"$PAIR001,";
+
+/// Position fixes per second.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum UpdateRate {
+    #[default]
+    Hz1,
+    Hz5,
+    Hz10,
+}
+
+impl UpdateRate {
+    /// Maps the value stored by the GPS Rate setting, in Hz.
+    pub fn from_setting(value: isize) -> Self {
+        match value {
+            5 => UpdateRate::Hz5,
+            10 => UpdateRate::Hz10,
+            _ => UpdateRate::Hz1,
+        }
+    }
+
+    pub fn hz(self) -> u8 {
+        match self {
+            UpdateRate::Hz1 => 1,
+            UpdateRate::Hz5 => 5,
+            UpdateRate::Hz10 => 10,
+        }
+    }
+
+    pub fn interval_ms(self) -> u16 {
+        1000 / u16::from(self.hz())
+    }
+}
+
+/// Output sentences, numbered as `PAIR062` expects.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NmeaSentence {
+    Gga = 0,
+    Gll = 1,
+    Gsa = 2,
+    Gsv = 3,
+    Rmc = 4,
+    Vtg = 5,
+    Zda = 6,
+}
+
+/// Satellite systems the receiver searches.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ConstellationMask {
+    pub gps: bool,
+    pub glonass: bool,
+    pub galileo: bool,
+    pub beidou: bool,
+    pub qzss: bool,
+}
+
+impl ConstellationMask {
+    pub const ALL: ConstellationMask = ConstellationMask {
+        gps: true,
+        glonass: true,
+        galileo: true,
+        beidou: true,
+        qzss: true,
+    };
+
+    /// Maps the value stored by the GNSS setting: one bit per system in
+    /// the order of the fields, GPS lowest. GPS is always searched.
+    pub fn from_setting(value: isize) -> Self {
+        let bit = |n: u32| value & (1 << n) != 0;
+        ConstellationMask {
+            gps: true,
+            glonass: bit(1),
+            galileo: bit(2),
+            beidou: bit(3),
+            qzss: bit(4),
+        }
+    }
+
+    /// The GNSS setting value for this mask.
+    pub fn to_setting(self) -> isize {
+        [self.gps, self.glonass, self.galileo, self.beidou, self.qzss]
+            .iter()
+            .enumerate()
+            .filter(|(_, on)| **on)
+            .map(|(n, _)| 1 << n)
+            .sum()
+    }
+}
+
+impl Default for ConstellationMask {
+    fn default() -> Self {
+        ConstellationMask::ALL
+    }
+}
+
+/// One receiver command.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Command {
+    /// `PAIR050`: time between fixes.
+    FixInterval(UpdateRate),
+    /// `PAIR062`: output `sentence` every `every` fixes, 0 to turn it off.
+    SentenceRate(NmeaSentence, u8),
+    /// `PAIR066`: systems to search. Takes effect after the receiver
+    /// restarts, so follow it with `SaveConfig` and `HotStart`.
+    Constellations(ConstellationMask),
+    /// `PAIR070`: below this speed, in dm/s, the position is held still.
+    /// 0 turns static navigation off.
+    StaticThreshold(u8),
+    /// `PAIR003`: stop the GNSS engine, keeping the receiver awake.
+    Standby,
+    /// `PAIR002`: restart the GNSS engine after `Standby`.
+    Wake,
+    /// `PAIR004`: restart the receiver, keeping what it knows of the sky.
+    HotStart,
+    /// `PAIR650`: power down to the RTC and backup RAM for `seconds`, 0 for
+    /// until woken by the wakeup pin.
+    Backup(u32),
+    /// `PAIR513`: keep the current configuration across power cycles.
+    SaveConfig,
+    /// `PAIR864`: baud rate of the receiver's UART.
+    BaudRate(u32),
+}
+
+impl Command {
+    /// Number of the `PAIR` command, echoed back in its acknowledgement.
+    pub fn id(&self) -> u16 {
+        match self {
+            Command::FixInterval(_) => 50,
+            Command::SentenceRate(..) => 62,
+            Command::Constellations(_) => 66,
+            Command::StaticThreshold(_) => 70,
+            Command::Standby => 3,
+            Command::Wake => 2,
+            Command::HotStart => 4,
+            Command::Backup(_) => 650,
+            Command::SaveConfig => 513,
+            Command::BaudRate(_) => 864,
+        }
+    }
+
+    /// The full sentence, from `$` to CRLF.
+    pub fn sentence(&self) -> String<MAX_SENTENCE_LEN> {
+        let mut body: String<MAX_SENTENCE_LEN> = String::new();
+        let _ = write!(body, "PAIR{:03}", self.id());
+        let _ = match *self {
+            Command::FixInterval(rate) => write!(body, ",{}", rate.interval_ms()),
+            Command::SentenceRate(sentence, every) => {
+                write!(body, ",{},{}", sentence as u8, every)
+            }
+            Command::Constellations(mask) => write!(
+                body,
+                ",{},{},{},{},{},0",
+                u8::from(mask.gps),
+                u8::from(mask.glonass),
+                u8::from(mask.galileo),
+                u8::from(mask.beidou),
+                u8::from(mask.qzss)
+            ),
+            Command::StaticThreshold(dm_per_s) => write!(body, ",{}", dm_per_s),
+            Command::Backup(seconds) => write!(body, ",{}", seconds),
+            Command::BaudRate(baud) => write!(body, ",0,0,{}", baud),
+            Command::Standby | Command::Wake | Command::HotStart | Command::SaveConfig => Ok(()),
+        };
+
+        let checksum = body.bytes().fold(0u8, |acc, b| acc ^ b);
+        let mut sentence = String::new();
+        let _ = write!(sentence, "${}*{:02X}\r\n", body, checksum);
+        sentence
+    }
+}
+
+/// Result code of an acknowledgement.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AckResult {
+    Success,
+    /// Accepted and still running; the final answer follows.
+    Processing,
+    Failed,
+    Unsupported,
+    BadParameter,
+    Busy,
+}
+
+impl AckResult {
+    fn from_code(code: u8) -> Option<Self> {
+        Some(match code {
+            0 => AckResult::Success,
+            1 => AckResult::Processing,
+            2 => AckResult::Failed,
+            3 => AckResult::Unsupported,
+            4 => AckResult::BadParameter,
+            5 => AckResult::Busy,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ack {
+    pub command: u16,
+    pub result: AckResult,
+}
+
+impl Ack {
+    /// Reads `$PAIR001,<command>,<result>*HH`. The checksum is the
+    /// caller's business.
+    pub fn parse(line: &[u8]) -> Option<Self> {
+        let fields = line.strip_prefix(PAIR_ACK)?;
+        let end = fields.iter().position(|&b| b == b'*')?;
+        let fields = core::str::from_utf8(&fields[..end]).ok()?;
+        let (command, result) = fields.split_once(',')?;
+        Some(Ack {
+            command: command.parse().ok()?,
+            result: AckResult::from_code(result.parse().ok()?)?,
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConfigError<E> {
+    Io(E),
+    /// No final answer after `MAX_ATTEMPTS` sends.
+    Timeout,
+    Rejected(AckResult),
+}
+
+/// Sends commands over the receiver's TX line and waits for the answers
+/// `GpsReader` picks out of its RX line.
+pub struct ReceiverConfig<'a, W: Write> {
+    tx: W,
+    acks: Receiver<'a, NoopRawMutex, Ack, ACK_QUEUE_LEN>,
+}
+
+impl<'a, W: Write> ReceiverConfig<'a, W> {
+    pub fn new(tx: W, acks: Receiver<'a, NoopRawMutex, Ack, ACK_QUEUE_LEN>) -> Self {
+        ReceiverConfig { tx, acks }
+    }
+
+    /// Sends `command` and waits for it to be acknowledged. `timeout` makes
+    /// the wait for each attempt; a send that times out or finds the
+    /// receiver busy is repeated, up to `MAX_ATTEMPTS` in all.
+    pub async fn send<F: Future>(
+        &mut self,
+        command: Command,
+        mut timeout: impl FnMut() -> F,
+    ) -> Result<(), ConfigError<W::Error>> {
+        let sentence = command.sentence();
+        for _ in 0..MAX_ATTEMPTS {
+            // Answers to an earlier, abandoned attempt.
+            while self.acks.try_receive().is_ok() {}
+            self.tx
+                .write_all(sentence.as_bytes())
+                .await
+                .map_err(ConfigError::Io)?;
+            self.tx.flush().await.map_err(ConfigError::Io)?;
+
+            let mut deadline = pin!(timeout());
+            loop {
+                match select(self.acks.receive(), deadline.as_mut()).await {
+                    Either::First(ack) if ack.command != command.id() => {}
+                    Either::First(Ack {
+                        result: AckResult::Success,
+                        ..
+                    }) => return Ok(()),
+                    Either::First(Ack {
+                        result: AckResult::Processing,
+                        ..
+                    }) => {}
+                    Either::First(Ack {
+                        result: AckResult::Busy,
+                        ..
+                    }) => break,
+                    Either::First(ack) => return Err(ConfigError::Rejected(ack.result)),
+                    Either::Second(_) => break,
+                }
+            }
+        }
+        Err(ConfigError::Timeout)
+    }
+
+    /// Sends every command of `commands` in order, stopping at the first
+    /// failure.
+    pub async fn send_all<F: Future>(
+        &mut self,
+        commands: &[Command],
+        mut timeout: impl FnMut() -> F,
+    ) -> Result<(), ConfigError<W::Error>> {
+        for &command in commands {
+            self.send(command, &mut timeout).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Sentence rates for `rate`: everything `GpsReader` parses on every fix,
+/// except satellites in view and the date, which only need to come about
+/// once a second. GLL repeats GGA and is turned off.
+pub fn sentence_rates(rate: UpdateRate) -> [Command; 7] {
+    let once_a_second = rate.hz();
+    [
+        Command::SentenceRate(NmeaSentence::Gga, 1),
+        Command::SentenceRate(NmeaSentence::Gll, 0),
+        Command::SentenceRate(NmeaSentence::Gsa, 1),
+        Command::SentenceRate(NmeaSentence::Gsv, once_a_second),
+        Command::SentenceRate(NmeaSentence::Rmc, 1),
+        Command::SentenceRate(NmeaSentence::Vtg, 1),
+        Command::SentenceRate(NmeaSentence::Zda, once_a_second),
+    ]
+}
+
+/// Receiver configuration chosen in the settings.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ReceiverSettings {
+    pub rate: UpdateRate,
+    pub constellations: ConstellationMask,
+    /// Static navigation threshold in dm/s, 0 for off.
+    pub static_threshold: u8,
+}
+
+impl ReceiverSettings {
+    /// Commands that take the receiver from `applied` to `self`; all of
+    /// them when nothing is known to be applied yet. A change of
+    /// constellations ends with a save and a restart, so everything sent
+    /// before it survives the restart.
+    pub fn commands(&self, applied: Option<&ReceiverSettings>) -> Vec<Command, 12> {
+        self.changes(
+            applied.is_none_or(|applied| applied.rate != self.rate),
+            applied.is_none_or(|applied| applied.static_threshold != self.static_threshold),
+            applied.is_none_or(|applied| applied.constellations != self.constellations),
+        )
+    }
+
+    /// Commands for a receiver that just powered up with `saved` in its
+    /// own flash: everything, but the constellations, the save and the
+    /// restart only if they differ, so an unchanged setup doesn't wear the
+    /// receiver's flash or lose the fix on every boot.
+    pub fn boot_commands(&self, saved: ConstellationMask) -> Vec<Command, 12> {
+        self.changes(true, true, saved != self.constellations)
+    }
+
+    fn changes(
+        &self,
+        rate: bool,
+        static_threshold: bool,
+        constellations: bool,
+    ) -> Vec<Command, 12> {
+        let mut commands = Vec::new();
+        if rate {
+            let _ = commands.push(Command::FixInterval(self.rate));
+            let _ = commands.extend_from_slice(&sentence_rates(self.rate));
+        }
+        if static_threshold {
+            let _ = commands.push(Command::StaticThreshold(self.static_threshold));
+        }
+        if constellations {
+            let _ = commands.push(Command::Constellations(self.constellations));
+            let _ = commands.push(Command::SaveConfig);
+            let _ = commands.push(Command::HotStart);
+        }
+        commands
+    }
+}
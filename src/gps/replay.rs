@@ -0,0 +1,54 @@
+use embedded_io_async::Read;
+
+use crate::gps::{
+    reader::{GpsReader, GpsReaderStats},
+    session::SessionStats,
+    stack::GeoStack,
+};
+
+/// What a recorded log produced once it went through the same
+/// reader -> `GeoStack` path the device uses while recording.
+#[derive(Copy, Clone, Debug)]
+pub struct ReplaySummary {
+    pub sentences: GpsReaderStats,
+    pub fixes: u32,
+    pub accepted: u32,
+    pub total_distance_m: f64,
+    pub total_elevation_gain: f32,
+    pub total_elevation_loss: f32,
+    pub final_speed_mps: f64,
+    pub max_speed_mps: f64,
+    pub stats: SessionStats,
+}
+
+pub async fn replay<R: Read>(input: R) -> ReplaySummary {
+    let mut reader = GpsReader::new(input);
+    let mut geo_stack = GeoStack::new();
+    let mut fixes = 0;
+    let mut accepted = 0;
+    let mut stats = SessionStats::new();
+
+    while let Some(out) = reader.next().await {
+        if let Some(coords) = out.reader_results {
+            fixes += 1;
+            if geo_stack.add_coords(coords, true) {
+                accepted += 1;
+                if let Some(time) = coords.timestamp {
+                    stats.update(time, geo_stack.current_speed_mps, true);
+                }
+            }
+        }
+    }
+
+    ReplaySummary {
+        sentences: reader.stats(),
+        fixes,
+        accepted,
+        total_distance_m: geo_stack.total_distance,
+        total_elevation_gain: geo_stack.elevation.total_gain,
+        total_elevation_loss: geo_stack.elevation.total_loss,
+        final_speed_mps: geo_stack.current_speed_mps,
+        max_speed_mps: stats.max_speed_mps,
+        stats,
+    }
+}
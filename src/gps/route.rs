@@ -0,0 +1,278 @@
+use heapless::Vec;
+
+use crate::gps::fns::{
+    LatLonAlt, SegmentOffset, haversine_distance_m, initial_bearing_deg, relative_bearing_deg,
+    segment_offset,
+};
+
+pub const MAX_ROUTE_POINTS: usize = 256;
+/// Points closer than this to the previous one are dropped on load; dense
+/// track exports would fill the route otherwise.
+pub const MIN_POINT_SPACING_M: f64 = 5.0;
+/// Points per flash item.
+pub const CHUNK_POINTS: usize = 16;
+pub const CHUNK_ENCODED_LEN: usize = CHUNK_POINTS * LatLonAlt::ENCODED_LEN;
+/// Bends sharper than this count as turns.
+pub const TURN_ANGLE_DEG: f64 = 30.0;
+/// A bend is measured between the points this far either side of it, so
+/// curves drawn with many short segments still read as one turn.
+const TURN_BASE_M: f64 = 25.0;
+/// Segments either side of the last match searched first, so a route that
+/// doubles back on itself isn't followed on the wrong leg.
+const SEARCH_WINDOW: usize = 8;
+/// Further than this from every segment in the window, search the whole
+/// route.
+const REACQUIRE_M: f64 = 50.0;
+/// Meters of distance a match in the window is charged per meter it lies
+/// behind the last one. Where the route overlaps itself both legs are
+/// equally near, and this keeps the rider on the one they're riding.
+const BACKTRACK_WEIGHT: f64 = 0.5;
+/// Meters charged per meter along the route, so that with nothing else to
+/// go on the start of a loop wins over its end.
+const FORWARD_WEIGHT: f64 = 0.001;
+/// Back on course once within this fraction of the alert distance, so the
+/// alert doesn't flicker at the edge.
+const ALERT_HYSTERESIS: f64 = 0.8;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RouteError {
+    Full,
+    Corrupt,
+}
+
+/// An ordered list of points to follow, with the distance along the route
+/// to each.
+#[derive(Clone, Debug, Default)]
+pub struct Route {
+    points: Vec<LatLonAlt, MAX_ROUTE_POINTS>,
+    distances_m: Vec<f32, MAX_ROUTE_POINTS>,
+}
+
+impl Route {
+    pub const fn new() -> Self {
+        Route {
+            points: Vec::new(),
+            distances_m: Vec::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+        self.distances_m.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn points(&self) -> &[LatLonAlt] {
+        &self.points
+    }
+
+    /// Distance along the route from the first point to point `index`.
+    pub fn distance_to_m(&self, index: usize) -> f64 {
+        self.distances_m
+            .get(index)
+            .copied()
+            .unwrap_or_default()
+            .into()
+    }
+
+    pub fn length_m(&self) -> f64 {
+        self.distances_m.last().copied().unwrap_or_default().into()
+    }
+
+    /// Appends `point` unless it is within [`MIN_POINT_SPACING_M`] of the
+    /// last one. Returns whether it was kept.
+    pub fn push(&mut self, point: LatLonAlt) -> Result<bool, RouteError> {
+        let step_m = match self.points.last() {
+            Some(&last) => haversine_distance_m(last, point),
+            None => 0.0,
+        };
+        if !self.points.is_empty() && step_m < MIN_POINT_SPACING_M {
+            return Ok(false);
+        }
+        self.points.push(point).map_err(|_| RouteError::Full)?;
+        let _ = self.distances_m.push((self.length_m() + step_m) as f32);
+        Ok(true)
+    }
+
+    /// Number of flash items the route takes.
+    pub fn chunk_count(&self) -> usize {
+        self.points.len().div_ceil(CHUNK_POINTS)
+    }
+
+    pub fn encode_chunk<'b>(&self, index: usize, buf: &'b mut [u8; CHUNK_ENCODED_LEN]) -> &'b [u8] {
+        let points = self
+            .points
+            .chunks(CHUNK_POINTS)
+            .nth(index)
+            .unwrap_or_default();
+        for (point, out) in points.iter().zip(buf.chunks_mut(LatLonAlt::ENCODED_LEN)) {
+            point.encode(out);
+        }
+        &buf[..points.len() * LatLonAlt::ENCODED_LEN]
+    }
+
+    /// Appends the points of a chunk written by
+    /// [`encode_chunk`](Self::encode_chunk).
+    pub fn decode_chunk(&mut self, bytes: &[u8]) -> Result<(), RouteError> {
+        if !bytes.len().is_multiple_of(LatLonAlt::ENCODED_LEN) {
+            return Err(RouteError::Corrupt);
+        }
+        for encoded in bytes.chunks(LatLonAlt::ENCODED_LEN) {
+            let point = LatLonAlt::decode(encoded).ok_or(RouteError::Corrupt)?;
+            self.push(point)?;
+        }
+        Ok(())
+    }
+
+    /// Index of the first point at least `distance_m` along the route, or
+    /// the last point.
+    fn index_at(&self, distance_m: f64) -> usize {
+        let index = self
+            .distances_m
+            .partition_point(|&d| f64::from(d) < distance_m);
+        index.min(self.points.len().saturating_sub(1))
+    }
+
+    /// Signed change of heading at point `index`, positive to the right.
+    fn bend_deg(&self, index: usize) -> f64 {
+        let at = self.distance_to_m(index);
+        let before = self
+            .distances_m
+            .partition_point(|&d| f64::from(d) <= at - TURN_BASE_M)
+            .saturating_sub(1);
+        let after = self.index_at(at + TURN_BASE_M);
+        if before == index || after == index {
+            return 0.0;
+        }
+        let here = self.points[index];
+        relative_bearing_deg(
+            initial_bearing_deg(here, self.points[after]),
+            initial_bearing_deg(self.points[before], here),
+        )
+    }
+}
+
+/// The next bend in the route.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Turn {
+    pub distance_m: f64,
+    /// Change of heading, positive to the right.
+    pub angle_deg: f64,
+}
+
+/// Where the rider is relative to the route.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RouteProgress {
+    /// Index of the first point of the nearest segment.
+    pub segment: usize,
+    /// Off the line of the nearest segment, positive to the right.
+    pub cross_track_m: f64,
+    /// To the nearest point of the route.
+    pub off_route_m: f64,
+    pub along_track_m: f64,
+    pub remaining_m: f64,
+    pub next_turn: Option<Turn>,
+    pub off_course: bool,
+}
+
+/// Tracks progress along a route from fix to fix.
+#[derive(Clone, Debug, Default)]
+pub struct RouteFollower {
+    /// Off-course alert distance; zero turns the alert off.
+    pub alert_m: f64,
+    /// Segment and along-track distance of the last match.
+    last: Option<(usize, f64)>,
+    off_course: bool,
+}
+
+impl RouteFollower {
+    pub fn new() -> Self {
+        RouteFollower::default()
+    }
+
+    /// Forgets the position on the route, keeping the alert distance.
+    pub fn reset(&mut self) {
+        *self = RouteFollower {
+            alert_m: self.alert_m,
+            ..RouteFollower::new()
+        };
+    }
+
+    /// Locates `here` on `route`. `None` until the route has a segment.
+    pub fn update(&mut self, route: &Route, here: LatLonAlt) -> Option<RouteProgress> {
+        let segments = route.len().checked_sub(1).filter(|&n| n > 0)?;
+        let nearest = |first: usize, last: usize, behind_m: f64| {
+            (first..last)
+                .map(|i| {
+                    let offset = segment_offset(route.points[i], route.points[i + 1], here);
+                    let along_m = route.distance_to_m(i) + offset.along_track_m;
+                    let score = offset.distance_m
+                        + (behind_m - along_m).max(0.0) * BACKTRACK_WEIGHT
+                        + along_m * FORWARD_WEIGHT;
+                    (i, offset, score)
+                })
+                .min_by(|(_, _, a), (_, _, b)| a.total_cmp(b))
+                .map(|(i, offset, _)| (i, offset))
+        };
+
+        let windowed = self.last.and_then(|(segment, along_m)| {
+            nearest(
+                segment.saturating_sub(SEARCH_WINDOW),
+                (segment + SEARCH_WINDOW + 1).min(segments),
+                along_m,
+            )
+        });
+        let (segment, offset): (usize, SegmentOffset) = match windowed {
+            Some(found) if found.1.distance_m <= REACQUIRE_M => found,
+            _ => nearest(0, segments, 0.0)?,
+        };
+        let along_track_m = route.distance_to_m(segment) + offset.along_track_m;
+        self.last = Some((segment, along_track_m));
+
+        let alert_m = if self.off_course {
+            self.alert_m * ALERT_HYSTERESIS
+        } else {
+            self.alert_m
+        };
+        self.off_course = self.alert_m > 0.0 && offset.distance_m > alert_m;
+
+        // Points around a corner all bend; the turn is where it's sharpest.
+        let mut bends = (segment + 1..segments)
+            .map(|i| (i, route.bend_deg(i)))
+            .skip_while(|(_, bend)| bend.abs() < TURN_ANGLE_DEG);
+        let next_turn = bends.next().map(|first| {
+            let (i, angle_deg) = bends
+                .take_while(|(_, bend)| {
+                    bend.abs() >= TURN_ANGLE_DEG && bend.signum() == first.1.signum()
+                })
+                .fold(first, |sharpest, bend| {
+                    if bend.1.abs() > sharpest.1.abs() {
+                        bend
+                    } else {
+                        sharpest
+                    }
+                });
+            Turn {
+                distance_m: route.distance_to_m(i) - along_track_m,
+                angle_deg,
+            }
+        });
+
+        Some(RouteProgress {
+            segment,
+            cross_track_m: offset.cross_track_m,
+            off_route_m: offset.distance_m,
+            along_track_m,
+            remaining_m: route.length_m() - along_track_m,
+            next_turn,
+            off_course: self.off_course,
+        })
+    }
+}
@@ -0,0 +1,59 @@
+use chrono::{Duration, NaiveTime};
+
+/// Running totals for the recording in progress, clocked by fix timestamps
+/// so they don't depend on how often the caller gets scheduled.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SessionStats {
+    pub elapsed_ms: u64,
+    pub moving_ms: u64,
+    pub max_speed_mps: f64,
+    last_time: Option<NaiveTime>,
+}
+
+impl SessionStats {
+    pub fn new() -> Self {
+        SessionStats::default()
+    }
+
+    pub fn reset(&mut self) {
+        *self = SessionStats::new();
+    }
+
+    /// Feeds an accepted fix. The time since the previous fix always counts
+    /// as elapsed, and as moving when `counting` (not auto-paused) and the
+    /// fix reports motion.
+    pub fn update(&mut self, time: NaiveTime, speed_mps: f64, counting: bool) {
+        if let Some(last) = self.last_time {
+            let mut delta = time - last;
+            // The receiver only reports time of day.
+            if delta < Duration::zero() {
+                delta += Duration::days(1);
+            }
+            let delta_ms = u64::try_from(delta.num_milliseconds()).unwrap_or(0);
+            self.elapsed_ms += delta_ms;
+            if counting && speed_mps > 0.0 {
+                self.moving_ms += delta_ms;
+            }
+        }
+        if counting {
+            self.max_speed_mps = self.max_speed_mps.max(speed_mps);
+        }
+        self.last_time = Some(time);
+    }
+
+    pub fn elapsed_secs(&self) -> u32 {
+        (self.elapsed_ms / 1000) as u32
+    }
+
+    pub fn moving_secs(&self) -> u32 {
+        (self.moving_ms / 1000) as u32
+    }
+
+    /// Average over moving time only, so stops don't drag it down.
+    pub fn average_speed_mps(&self, distance_m: f64) -> f64 {
+        if self.moving_ms == 0 {
+            return 0.0;
+        }
+        distance_m * 1000.0 / self.moving_ms as f64
+    }
+}
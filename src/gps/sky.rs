@@ -0,0 +1,84 @@
+use heapless::Vec;
+use libm::{cos, sin};
+
+use crate::gps::reader::{
+    Constellation, FixMode, MAX_SATELLITES, ParseOut, SatelliteInfo, SatelliteList,
+};
+
+/// Order the signal bars are grouped in.
+pub const CONSTELLATIONS: [Constellation; 6] = [
+    Constellation::Gps,
+    Constellation::Glonass,
+    Constellation::Galileo,
+    Constellation::Beidou,
+    Constellation::Qzss,
+    Constellation::Other,
+];
+
+/// Receiver status for the GNSS page, folded together from GSA and GSV.
+#[derive(Clone, Debug, Default)]
+pub struct GnssStatus {
+    pub fix_mode: Option<FixMode>,
+    /// Satellites used in the fix, as counted by GSA.
+    pub used: Option<u8>,
+    /// Every satellite in view from the last complete GSV group.
+    pub satellites: SatelliteList,
+}
+
+impl GnssStatus {
+    pub const fn new() -> Self {
+        GnssStatus {
+            fix_mode: None,
+            used: None,
+            satellites: SatelliteList::new(),
+        }
+    }
+
+    /// Takes whatever GSA or GSV data `out` carries.
+    pub fn update(&mut self, out: &ParseOut) {
+        if out.fix_mode.is_some() {
+            self.fix_mode = out.fix_mode;
+            self.used = out.active_satellites;
+        }
+        if let Some(satellites) = &out.satellites {
+            self.satellites = satellites.clone();
+        }
+    }
+
+    pub fn in_view(&self) -> usize {
+        self.satellites.len()
+    }
+
+    /// Satellites used in the fix, from GSA or, before one arrives, from
+    /// the GSV list.
+    pub fn in_use(&self) -> usize {
+        self.used.map_or_else(
+            || self.satellites.iter().filter(|sat| sat.in_use).count(),
+            usize::from,
+        )
+    }
+
+    /// Satellites with a signal, grouped by constellation in
+    /// [`CONSTELLATIONS`] order and by PRN within a group.
+    pub fn by_constellation(&self) -> impl Iterator<Item = &SatelliteInfo> + '_ {
+        CONSTELLATIONS.iter().flat_map(move |&constellation| {
+            let mut group: Vec<&SatelliteInfo, MAX_SATELLITES> = self
+                .satellites
+                .iter()
+                .filter(|sat| sat.constellation == constellation && sat.snr.is_some())
+                .collect();
+            group.sort_unstable_by_key(|sat| sat.prn);
+            group.into_iter()
+        })
+    }
+}
+
+/// Offset from the plot center of a satellite on a polar sky plot of
+/// `radius`: zenith in the middle, horizon on the rim, north up. `None`
+/// without a position.
+pub fn sky_position(satellite: &SatelliteInfo, radius: f64) -> Option<(f64, f64)> {
+    let elevation = f64::from(satellite.elevation?).clamp(0.0, 90.0);
+    let azimuth = f64::from(satellite.azimuth?).to_radians();
+    let distance = radius * (90.0 - elevation) / 90.0;
+    Some((distance * sin(azimuth), -distance * cos(azimuth)))
+}
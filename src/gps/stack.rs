@@ -2,37 +2,72 @@ use chrono::Duration;
 use heapless::Deque;
 
 use crate::gps::{
-    fns::{LatLonAlt, calculate_speed, haversine_distance_ft},
+    elevation::ElevationTracker,
+    fns::{LatLonAlt, haversine_distance_m, initial_bearing_deg},
+    kalman::{MAX_PREDICT_SECS, PositionFilter},
+    profile::ElevationProfile,
     reader::GpsReaderResults,
 };
 
 const MAX_ITEMS: usize = 16;
+/// Filtered speeds below this are GPS jitter, not motion.
+const STATIONARY_SPEED_MPS: f64 = 0.8;
+/// Unfiltered legs between sparse fixes shorter than this are position
+/// noise. Longer ones count whatever speed they imply: a slow climb covers
+/// little ground between fixes a minute apart.
+const SPARSE_MIN_LEG_M: f64 = 15.0;
 
 pub struct GeoStack {
     pub stack: Deque<GpsReaderResults, MAX_ITEMS>,
+    // Meters
     pub last_segment_distance: f64,
     pub total_distance: f64,
-    pub total_elevation_gain: f32,
-    pub current_speed_mph: f64,
+    pub elevation: ElevationTracker,
+    /// Altitude over distance while recording, for the profile page.
+    pub profile: ElevationProfile,
+    pub current_speed_mps: f64,
     pub current_hdop: f32,
+    pub current_heading: f64,
+    pub filtered_position: Option<LatLonAlt>,
+    pub filter: PositionFilter,
+    /// Fixes closer together than this are dropped. Follows the receiver's
+    /// fix interval, so only repeated epochs are.
     pub min_time_interval_ms: i64,
     pub min_distance_threshold: f64,
 }
 
+impl Default for GeoStack {
+    fn default() -> Self {
+        GeoStack::new()
+    }
+}
+
 impl GeoStack {
     pub fn new() -> Self {
         GeoStack {
             stack: Deque::new(),
             last_segment_distance: 0.0,
             total_distance: 0.0,
-            total_elevation_gain: 0.0,
-            current_speed_mph: 0.0,
+            elevation: ElevationTracker::new(),
+            profile: ElevationProfile::new(),
+            current_speed_mps: 0.0,
             current_hdop: 0.0,
+            current_heading: 0.0,
+            filtered_position: None,
+            filter: PositionFilter::new(),
             min_time_interval_ms: 1000,
             min_distance_threshold: 0.0,
         }
     }
 
+    /// Clears the running totals for a new recording.
+    pub fn reset_totals(&mut self) {
+        self.last_segment_distance = 0.0;
+        self.total_distance = 0.0;
+        self.elevation.reset_session();
+        self.profile.reset();
+    }
+
     pub fn ring_buffer_push(&mut self, item: GpsReaderResults) {
         if !self.stack.is_full() {
             let _ = self.stack.push_back(item);
@@ -42,66 +77,90 @@ impl GeoStack {
         }
     }
 
-    pub fn add_coords(&mut self, coords: GpsReaderResults, mut _last_lla: Option<GpsReaderResults>, is_recording: bool) {
+    fn record_profile(&mut self) {
+        if let Some(altitude) = self.elevation.altitude() {
+            self.profile.push(self.total_distance as f32, altitude);
+        }
+    }
+
+    pub fn add_coords(&mut self, coords: GpsReaderResults, is_recording: bool) -> bool {
         if let GpsReaderResults {
             lat: Some(new_lat),
             lon: Some(new_lon),
             alt: Some(new_alt),
             hdop: Some(new_hdop),
-            timestamp: Some(new_timestamp)
+            timestamp: Some(new_timestamp),
+            ..
         } = coords {
             self.current_hdop = new_hdop;
+            let fix = LatLonAlt {
+                latitude: new_lat,
+                longitude: new_lon,
+                altitude: new_alt,
+            };
             if let Some(last_coord) = self.stack.back() {
                 if let GpsReaderResults {
-                    lat: Some(prev_lat),
-                    lon: Some(prev_lon),
-                    alt: Some(prev_alt),
+                    lat: Some(_),
+                    lon: Some(_),
+                    alt: Some(_),
                     hdop: Some(_prev_hdop),
-                    timestamp: Some(prev_timestamp)
+                    timestamp: Some(prev_timestamp),
+                    ..
                 } = *last_coord {
-                    let time_delta = new_timestamp - prev_timestamp;
+                    let mut time_delta = new_timestamp - prev_timestamp;
+                    // The clock wrapped at UTC midnight.
+                    if time_delta < Duration::zero() {
+                        time_delta += Duration::days(1);
+                    }
                     if time_delta < Duration::milliseconds(self.min_time_interval_ms) {
-                        return;
+                        return false;
                     }
 
-                    if new_hdop < 5.0 {
-                        _last_lla = Some(coords);
-                        let p1 = LatLonAlt {
-                            latitude: prev_lat,
-                            longitude: prev_lon,
-                            altitude: prev_alt,
-                        };
-                        let p2 = LatLonAlt {
-                            latitude: new_lat,
-                            longitude: new_lon,
-                            altitude: new_alt,
-                        };
-
-                        let distance_segment_ft = haversine_distance_ft(p1, p2);
-
-                        let alt_diff = p2.altitude - p1.altitude;
+                    self.ring_buffer_push(coords);
 
-                        self.ring_buffer_push(coords);
+                    // HDOP weights the fix instead of gating it outright.
+                    let dt_secs = time_delta.as_seconds_f64();
+                    let mut estimate = self.filter.update(fix, new_hdop, dt_secs);
+                    let mut moving = estimate.speed_mps > STATIONARY_SPEED_MPS;
+                    if dt_secs > MAX_PREDICT_SECS && let Some(prev_position) = self.filtered_position {
+                        // Too far apart for the filter, as when the receiver
+                        // sleeps between fixes: take the straight leg.
+                        let leg_m = haversine_distance_m(prev_position, estimate.position);
+                        estimate.speed_mps = leg_m / dt_secs;
+                        estimate.heading = initial_bearing_deg(prev_position, estimate.position);
+                        moving = leg_m > SPARSE_MIN_LEG_M;
+                    }
+                    let distance_segment_m = match self.filtered_position {
+                        Some(prev_position) if moving => haversine_distance_m(prev_position, estimate.position),
+                        _ => 0.0,
+                    };
+                    self.filtered_position = Some(estimate.position);
+                    self.elevation.update(new_alt, distance_segment_m, is_recording);
 
-                         if is_recording {
-                            self.last_segment_distance = distance_segment_ft;
-                            self.total_distance += distance_segment_ft;
-                            if alt_diff > 0.0 {
-                                self.total_elevation_gain += alt_diff
-                            }
-                        }
+                     if is_recording {
+                        self.last_segment_distance = distance_segment_m;
+                        self.total_distance += distance_segment_m;
+                        self.record_profile();
+                    }
 
-                        if distance_segment_ft > self.min_distance_threshold {
-                            self.current_speed_mph =
-                                calculate_speed(distance_segment_ft, time_delta.as_seconds_f64());
-                        } else {
-                            self.current_speed_mph = 0.0;
-                        }
+                    if moving && distance_segment_m > self.min_distance_threshold {
+                        self.current_speed_mps = estimate.speed_mps;
+                        self.current_heading = estimate.heading;
+                    } else {
+                        self.current_speed_mps = 0.0;
                     }
+                    return true;
                 }
             } else {
                 self.ring_buffer_push(coords);
+                self.elevation.update(new_alt, 0.0, is_recording);
+                if is_recording {
+                    self.record_profile();
+                }
+                self.filtered_position = Some(self.filter.update(fix, new_hdop, 0.0).position);
+                return true;
             }
         }
+        false
     }
 }
@@ -0,0 +1,175 @@
+use core::fmt::Write as _;
+
+use heapless::String;
+
+use crate::gps::fns::LatLonAlt;
+
+pub const MAX_WAYPOINTS: usize = 16;
+pub const MAX_NAME_LEN: usize = 12;
+/// Position, name length and name.
+pub const ENCODED_LEN: usize = LatLonAlt::ENCODED_LEN + 1 + MAX_NAME_LEN;
+const NAME_AT: usize = LatLonAlt::ENCODED_LEN;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Waypoint {
+    pub name: String<MAX_NAME_LEN>,
+    pub position: LatLonAlt,
+}
+
+impl Waypoint {
+    /// Names longer than `MAX_NAME_LEN` bytes are cut at a character
+    /// boundary.
+    pub fn new(name: &str, position: LatLonAlt) -> Self {
+        let mut short = String::new();
+        for c in name.chars() {
+            if short.push(c).is_err() {
+                break;
+            }
+        }
+        Waypoint {
+            name: short,
+            position,
+        }
+    }
+
+    pub fn encode<'b>(&self, buf: &'b mut [u8; ENCODED_LEN]) -> &'b [u8] {
+        self.position.encode(buf);
+        let name = self.name.as_bytes();
+        buf[NAME_AT] = name.len() as u8;
+        buf[NAME_AT + 1..NAME_AT + 1 + name.len()].copy_from_slice(name);
+        &buf[..NAME_AT + 1 + name.len()]
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let len = usize::from(*bytes.get(NAME_AT)?);
+        let name = core::str::from_utf8(bytes.get(NAME_AT + 1..NAME_AT + 1 + len)?).ok()?;
+        Some(Waypoint::new(name, LatLonAlt::decode(bytes)?))
+    }
+}
+
+/// The saved waypoints by storage slot, plus the browse cursor and the
+/// navigation target.
+#[derive(Clone, Debug)]
+pub struct WaypointList {
+    slots: [Option<Waypoint>; MAX_WAYPOINTS],
+    cursor: Option<usize>,
+    target: Option<usize>,
+}
+
+impl Default for WaypointList {
+    fn default() -> Self {
+        WaypointList::new()
+    }
+}
+
+impl WaypointList {
+    pub const fn new() -> Self {
+        WaypointList {
+            slots: [const { None }; MAX_WAYPOINTS],
+            cursor: None,
+            target: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.iter().flatten().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, slot: usize) -> Option<&Waypoint> {
+        self.slots.get(slot)?.as_ref()
+    }
+
+    /// Occupied slots in order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Waypoint)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, waypoint)| Some((slot, waypoint.as_ref()?)))
+    }
+
+    /// Puts `waypoint` in `slot`, replacing whatever was there.
+    pub fn set(&mut self, slot: usize, waypoint: Waypoint) {
+        if let Some(entry) = self.slots.get_mut(slot) {
+            *entry = Some(waypoint);
+            self.cursor = self.cursor.or(Some(slot));
+        }
+    }
+
+    /// Saves into the first free slot and returns it, or `None` when full.
+    pub fn insert(&mut self, waypoint: Waypoint) -> Option<usize> {
+        let slot = self.slots.iter().position(Option::is_none)?;
+        self.set(slot, waypoint);
+        Some(slot)
+    }
+
+    pub fn remove(&mut self, slot: usize) -> Option<Waypoint> {
+        let waypoint = self.slots.get_mut(slot)?.take()?;
+        if self.target == Some(slot) {
+            self.target = None;
+        }
+        if self.cursor == Some(slot) {
+            self.cursor = None;
+            self.cursor = self.step(slot, 1);
+        }
+        Some(waypoint)
+    }
+
+    /// Slot under the browse cursor.
+    pub fn cursor(&self) -> Option<usize> {
+        self.cursor
+    }
+
+    pub fn next(&mut self) {
+        if let Some(cursor) = self.cursor {
+            self.cursor = self.step(cursor, 1);
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if let Some(cursor) = self.cursor {
+            self.cursor = self.step(cursor, MAX_WAYPOINTS - 1);
+        }
+    }
+
+    /// Makes the waypoint under the cursor the navigation target, or clears
+    /// the target if it already is.
+    pub fn toggle_target(&mut self) {
+        self.target = if self.target == self.cursor {
+            None
+        } else {
+            self.cursor
+        };
+    }
+
+    pub fn target_slot(&self) -> Option<usize> {
+        self.target
+    }
+
+    pub fn target(&self) -> Option<&Waypoint> {
+        self.get(self.target?)
+    }
+
+    /// `WP01`, `WP02`, ... picking the lowest number not in use.
+    pub fn next_name(&self) -> String<MAX_NAME_LEN> {
+        let mut name = String::new();
+        for number in 1..=MAX_WAYPOINTS + 1 {
+            name.clear();
+            let _ = write!(name, "WP{:02}", number);
+            if !self.iter().any(|(_, waypoint)| waypoint.name == name) {
+                break;
+            }
+        }
+        name
+    }
+
+    /// First occupied slot `stride` steps away from `from`, wrapping.
+    fn step(&self, from: usize, stride: usize) -> Option<usize> {
+        (1..=MAX_WAYPOINTS)
+            .map(|n| (from + n * stride) % MAX_WAYPOINTS)
+            .find(|&slot| self.slots[slot].is_some())
+    }
+}
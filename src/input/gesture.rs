@@ -0,0 +1,253 @@
+//! Turns raw button levels into gestures. Pure state machine: the caller
+//! feeds in levels and timestamps, so the timing can be tested on the host.
+
+use heapless::Deque;
+
+pub const BUTTON_COUNT: usize = 4;
+/// A level has to hold this long before it counts.
+pub const DEBOUNCE_MS: u64 = 20;
+/// Held this long a press turns into a long press.
+pub const LONG_PRESS_MS: u64 = 800;
+/// After a long press, a held button repeats this often.
+pub const REPEAT_INTERVAL_MS: u64 = 150;
+/// Longest release between the two presses of a double press.
+pub const DOUBLE_PRESS_GAP_MS: u64 = 300;
+/// Two buttons going down this close together are one chord.
+pub const CHORD_WINDOW_MS: u64 = 80;
+const EVENT_QUEUE_LEN: usize = 8;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Button {
+    Action = 0,
+    Page = 1,
+    Up = 2,
+    Down = 3,
+}
+
+impl Button {
+    pub const ALL: [Button; BUTTON_COUNT] =
+        [Button::Action, Button::Page, Button::Up, Button::Down];
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ButtonEvent {
+    /// Pressed and released before `LONG_PRESS_MS`. For a button with double
+    /// press enabled, only once no second press followed.
+    Short(Button),
+    Long(Button),
+    /// Held on after `Long`, every `REPEAT_INTERVAL_MS`.
+    Repeat(Button),
+    /// Second press within `DOUBLE_PRESS_GAP_MS` of releasing the first.
+    Double(Button),
+    /// Two buttons pressed together, lower one first. Neither button
+    /// reports anything else until it is released.
+    Chord(Button, Button),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Phase {
+    Idle,
+    Down {
+        since_ms: u64,
+    },
+    Held {
+        next_repeat_ms: u64,
+    },
+    /// Released after a short press; a second press makes it a double.
+    Released {
+        at_ms: u64,
+    },
+    /// Used up by a double press or a chord; waits for the release.
+    Spent,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct ButtonState {
+    /// Level as last read, and since when.
+    raw: bool,
+    raw_since_ms: u64,
+    /// Debounced level.
+    pressed: bool,
+    phase: Phase,
+    double_press: bool,
+}
+
+impl ButtonState {
+    const fn new() -> Self {
+        ButtonState {
+            raw: false,
+            raw_since_ms: 0,
+            pressed: false,
+            phase: Phase::Idle,
+            double_press: false,
+        }
+    }
+}
+
+/// Recognizes short, long, double and repeated presses of each button and
+/// chords of two. Call `input` with every level read, `update` whenever
+/// time has passed, and take the results from `next_event`.
+#[derive(Clone, Debug)]
+pub struct GestureRecognizer {
+    buttons: [ButtonState; BUTTON_COUNT],
+    events: Deque<ButtonEvent, EVENT_QUEUE_LEN>,
+}
+
+impl Default for GestureRecognizer {
+    fn default() -> Self {
+        GestureRecognizer::new()
+    }
+}
+
+impl GestureRecognizer {
+    pub const fn new() -> Self {
+        GestureRecognizer {
+            buttons: [ButtonState::new(); BUTTON_COUNT],
+            events: Deque::new(),
+        }
+    }
+
+    /// Lets `button` report double presses. Its short presses are held back
+    /// by `DOUBLE_PRESS_GAP_MS` to wait for a second one.
+    pub fn with_double_press(mut self, button: Button) -> Self {
+        self.buttons[button as usize].double_press = true;
+        self
+    }
+
+    /// Records the level of `button` read at `now_ms`.
+    pub fn input(&mut self, button: Button, pressed: bool, now_ms: u64) {
+        let state = &mut self.buttons[button as usize];
+        if state.raw != pressed {
+            state.raw = pressed;
+            state.raw_since_ms = now_ms;
+        }
+    }
+
+    /// Settles bounced levels and runs the timers up to `now_ms`.
+    pub fn update(&mut self, now_ms: u64) {
+        for button in Button::ALL {
+            let state = self.buttons[button as usize];
+            if state.raw != state.pressed && now_ms >= state.raw_since_ms + DEBOUNCE_MS {
+                // Timers that ran out before the edge go first.
+                self.tick(button, state.raw_since_ms);
+                self.buttons[button as usize].pressed = state.raw;
+                if state.raw {
+                    self.press(button, state.raw_since_ms);
+                } else {
+                    self.release(button, state.raw_since_ms);
+                }
+            }
+        }
+        for button in Button::ALL {
+            self.tick(button, now_ms);
+        }
+    }
+
+    /// Earliest time `update` has something to do, if anything is pending.
+    pub fn next_deadline(&self) -> Option<u64> {
+        self.buttons
+            .iter()
+            .filter_map(|state| {
+                if state.raw != state.pressed {
+                    return Some(state.raw_since_ms + DEBOUNCE_MS);
+                }
+                match state.phase {
+                    Phase::Down { since_ms } => Some(since_ms + LONG_PRESS_MS),
+                    Phase::Held { next_repeat_ms } => Some(next_repeat_ms),
+                    Phase::Released { at_ms } => Some(at_ms + DOUBLE_PRESS_GAP_MS),
+                    Phase::Idle | Phase::Spent => None,
+                }
+            })
+            .min()
+    }
+
+    pub fn next_event(&mut self) -> Option<ButtonEvent> {
+        self.events.pop_front()
+    }
+
+    fn emit(&mut self, event: ButtonEvent) {
+        if self.events.push_back(event).is_err() {
+            warn!("button events dropped");
+        }
+    }
+
+    fn press(&mut self, button: Button, at_ms: u64) {
+        // Another button's pending short press is over; report it before
+        // anything this press leads to.
+        for other in Button::ALL {
+            if other != button
+                && matches!(self.buttons[other as usize].phase, Phase::Released { .. })
+            {
+                self.buttons[other as usize].phase = Phase::Idle;
+                self.emit(ButtonEvent::Short(other));
+            }
+        }
+
+        let partner = Button::ALL.into_iter().find(|&other| {
+            other != button
+                && matches!(
+                    self.buttons[other as usize].phase,
+                    Phase::Down { since_ms } if at_ms.abs_diff(since_ms) <= CHORD_WINDOW_MS
+                )
+        });
+        if let Some(other) = partner {
+            self.buttons[other as usize].phase = Phase::Spent;
+            self.buttons[button as usize].phase = Phase::Spent;
+            self.emit(ButtonEvent::Chord(other.min(button), other.max(button)));
+            return;
+        }
+
+        let state = &mut self.buttons[button as usize];
+        match state.phase {
+            Phase::Released { at_ms: released } => {
+                if at_ms.saturating_sub(released) <= DOUBLE_PRESS_GAP_MS {
+                    state.phase = Phase::Spent;
+                    self.emit(ButtonEvent::Double(button));
+                } else {
+                    state.phase = Phase::Down { since_ms: at_ms };
+                    self.emit(ButtonEvent::Short(button));
+                }
+            }
+            _ => state.phase = Phase::Down { since_ms: at_ms },
+        }
+    }
+
+    fn release(&mut self, button: Button, at_ms: u64) {
+        let state = &mut self.buttons[button as usize];
+        match state.phase {
+            Phase::Down { .. } if state.double_press => {
+                state.phase = Phase::Released { at_ms };
+            }
+            Phase::Down { .. } => {
+                state.phase = Phase::Idle;
+                self.emit(ButtonEvent::Short(button));
+            }
+            _ => state.phase = Phase::Idle,
+        }
+    }
+
+    fn tick(&mut self, button: Button, now_ms: u64) {
+        let state = &mut self.buttons[button as usize];
+        match state.phase {
+            Phase::Down { since_ms } if now_ms >= since_ms + LONG_PRESS_MS => {
+                state.phase = Phase::Held {
+                    next_repeat_ms: since_ms + LONG_PRESS_MS + REPEAT_INTERVAL_MS,
+                };
+                self.emit(ButtonEvent::Long(button));
+            }
+            Phase::Held { next_repeat_ms } if now_ms >= next_repeat_ms => {
+                state.phase = Phase::Held {
+                    next_repeat_ms: next_repeat_ms + REPEAT_INTERVAL_MS,
+                };
+                self.emit(ButtonEvent::Repeat(button));
+            }
+            Phase::Released { at_ms } if now_ms >= at_ms + DOUBLE_PRESS_GAP_MS => {
+                state.phase = Phase::Idle;
+                self.emit(ButtonEvent::Short(button));
+            }
+            _ => {}
+        }
+    }
+}
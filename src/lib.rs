@@ -0,0 +1,18 @@
+//! Target-independent half of the tracker: NMEA parsing, track maths, the
+//! session log with its exporters, the command shell and small containers. Everything here builds for the host as well, so field
+//! logs can be replayed with `cargo test`.
+#![no_std]
+
+/// `defmt::warn!` when logging is compiled in, nothing on the host.
+macro_rules! warn {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "defmt")]
+        defmt::warn!($($arg)*);
+    }};
+}
+
+pub mod gps;
+pub mod input;
+pub mod shell;
+pub mod track;
+pub mod utils;
@@ -1,14 +1,22 @@
 #![no_std]
 #![no_main]
 
-mod gps;
-mod utils;
+mod backend;
+mod battery;
+mod flash;
 
+use chrono::NaiveDateTime;
 use defmt::info;
 use embassy_embedded_hal::adapter::BlockingAsync;
-use embassy_futures::select::{Either, select};
-use embassy_sync::{blocking_mutex::raw::NoopRawMutex, channel::Channel, mutex::Mutex};
-use embassy_time::{Duration, Timer};
+use embassy_futures::select::{Either, select, select_array};
+use embassy_sync::{
+    blocking_mutex::raw::NoopRawMutex,
+    channel::{Channel, Receiver, Sender},
+    mutex::Mutex,
+    signal::Signal,
+};
+use embassy_time::{Duration, Instant, Timer};
+use hijo::{gps, input, shell, track, utils};
 use sequential_storage::{
     cache::Cache,
     map::{MapConfig, MapStorage},
@@ -19,36 +27,91 @@ use defmt_rtt as _;
 use panic_probe as _;
 
 use crate::{
-    Page::{RECORD, SETTINGS},
+    Page::{
+        DIAGNOSTICS, GNSS, LAPS, MAP, NAVIGATION, PROFILE, RECORD, ROUTE, SETTINGS, STATS,
+        WAYPOINTS,
+    },
+    backend::DeviceBackend,
+    battery::SaadcBattery,
     draw_fns::{
+        battery::{draw_battery, draw_battery_warning},
         constants::TEXT_STYLE_LG,
+        diagnostics::draw_diagnostics,
+        laps::draw_laps,
+        map::draw_map,
+        navigation::{Target, draw_navigation},
+        profile::draw_profile,
+        route::{draw_off_course, draw_route},
         settings::draw_settings,
+        sky::{draw_gnss, draw_gnss_summary},
+        stats::draw_stats,
         utils::{
-            draw_blinky, draw_coords, draw_current_speed, draw_hdop, draw_recording_status,
+            draw_blinky, draw_clock, draw_coords, draw_current_speed, draw_recording_status,
             draw_static_text, draw_total_distance, draw_total_elev_gain,
         },
+        waypoints::draw_waypoints,
+    },
+    flash::{
+        ProjFlash, ProjTrackLog, SETTINGS_FLASH_SIZE, TRACK_FLASH_SIZE, settings_partition,
+        track_partition,
     },
     gps::{
-        reader::{GpsReader, ParseOut},
+        auto_pause::{AutoPause, PauseEvent, PauseState},
+        fns::{LatLonAlt, haversine_distance_m, initial_bearing_deg},
+        laps::LapTracker,
+        map::{Breadcrumbs, MapOrientation},
+        power::{DutyCycle, PowerProfile},
+        reader::{Dop, GpsReader, GpsReaderResults, GpsReaderStats, ParseOut},
+        receiver::{
+            ACK_QUEUE_LEN, Ack, Command, ConstellationMask, ReceiverConfig, ReceiverSettings,
+            UpdateRate,
+        },
+        route::{Route, RouteFollower, RouteProgress},
+        session::SessionStats,
+        sky::GnssStatus,
         stack::GeoStack,
+        waypoint::{Waypoint, WaypointList},
     },
+    input::gesture::{BUTTON_COUNT, Button, ButtonEvent, GestureRecognizer},
     settings::{
         config::{
-            ProjNVMCStorage, configure_auto_pause_setting, configure_time_zone_setting,
-            configure_units_setting,
+            ProjNVMCStorage, configure_auto_pause_setting, configure_climb_threshold_setting,
+            configure_constellation_setting, configure_dst_setting, configure_gps_rate_setting,
+            configure_map_orientation_setting, configure_off_course_setting,
+            configure_power_setting, configure_split_setting, configure_static_nav_setting,
+            configure_time_zone_setting, configure_units_setting, load_receiver_constellations,
+            store_receiver_constellations, store_setting,
         },
-        settings::{SettingsState, SettingsWrapper},
+        route::load_route,
+        settings::{
+            AUTO_PAUSE_SETTING_ID, CLIMB_THRESHOLD_SETTING_ID, CONSTELLATION_SETTING_ID,
+            DST_SETTING_ID, GPS_RATE_SETTING_ID, MAP_ORIENTATION_SETTING_ID, OFF_COURSE_SETTING_ID,
+            POWER_SETTING_ID, SPLIT_SETTING_ID, STATIC_NAV_SETTING_ID, SettingsState,
+            SettingsWrapper, TIME_ZONE_SETTING_ID, UNITS_SETTING_ID, bool_setting, number_setting,
+        },
+        waypoints::{load_waypoints, store_waypoint},
+    },
+    shell::runner::Shell,
+    track::{
+        log::{TrackEvent, TrackLog},
+        record::{MS_PER_DAY, TrackPoint},
+    },
+    utils::{
+        battery::{BatteryLevel, BatteryMonitor, BatteryStatus},
+        time::{DstRule, TimeZone, utc_datetime},
+        units::Units,
+        vector::CircularTracker,
     },
-    utils::vector::CircularTracker,
 };
 
 use embassy_executor::Spawner;
 use embassy_nrf::{
     bind_interrupts,
-    buffered_uarte::{self, BufferedUarte},
+    buffered_uarte::{self, BufferedUarte, BufferedUarteRx, BufferedUarteTx},
     gpio::{Input, Pull},
     nvmc::Nvmc,
     peripherals::{self},
+    saadc::{self, ChannelConfig, Saadc},
     twim::{self, Twim},
     uarte::{self, Baudrate, Parity},
 };
@@ -62,134 +125,359 @@ mod settings;
 bind_interrupts!(struct Irqs {
     SERIAL0 => twim::InterruptHandler<peripherals::SERIAL0>;
     SERIAL1 => buffered_uarte::InterruptHandler<peripherals::SERIAL1>;
+    SERIAL2 => buffered_uarte::InterruptHandler<peripherals::SERIAL2>;
+    SAADC => saadc::InterruptHandler;
 });
 
 static CHANNEL: StaticCell<Channel<NoopRawMutex, ParseOut, 1>> = StaticCell::new();
+static ACK_CHANNEL: StaticCell<Channel<NoopRawMutex, Ack, ACK_QUEUE_LEN>> = StaticCell::new();
+static POWER_CHANNEL: StaticCell<Channel<NoopRawMutex, Command, 2>> = StaticCell::new();
+static POWER_DONE: StaticCell<Signal<NoopRawMutex, u16>> = StaticCell::new();
 static SHARED_STATE: StaticCell<Mutex<NoopRawMutex, SharedState>> = StaticCell::new();
 static SETTINGS_STATE: StaticCell<Mutex<NoopRawMutex, SettingsState>> = StaticCell::new();
 static BLINK_STATE: StaticCell<Mutex<NoopRawMutex, bool>> = StaticCell::new();
+static FLASH: StaticCell<ProjFlash> = StaticCell::new();
+static BUTTON_CHANNEL: StaticCell<Channel<NoopRawMutex, ButtonEvent, BUTTON_QUEUE_LEN>> =
+    StaticCell::new();
+static TRACK_CHANNEL: StaticCell<Channel<NoopRawMutex, TrackEvent, 8>> = StaticCell::new();
+static TRACK_FLUSHED: StaticCell<Signal<NoopRawMutex, ()>> = StaticCell::new();
+static TRACK_LOG: StaticCell<Mutex<NoopRawMutex, ProjTrackLog>> = StaticCell::new();
+static SETTINGS_STORAGE: StaticCell<Mutex<NoopRawMutex, ProjNVMCStorage>> = StaticCell::new();
+static WAYPOINTS: StaticCell<Mutex<NoopRawMutex, WaypointList>> = StaticCell::new();
+static ROUTE: StaticCell<Mutex<NoopRawMutex, Route>> = StaticCell::new();
 
-static GPS_READER: StaticCell<GpsReader<'static>> = StaticCell::new();
+static GPS_READER: StaticCell<GpsReader<BufferedUarteRx<'static>>> = StaticCell::new();
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Page {
     RECORD,
+    STATS,
+    LAPS,
+    NAVIGATION,
+    WAYPOINTS,
+    ROUTE,
+    MAP,
+    PROFILE,
+    GNSS,
     SETTINGS,
+    DIAGNOSTICS,
+}
+
+impl Page {
+    const ALL: [Page; 11] = [
+        RECORD,
+        STATS,
+        LAPS,
+        NAVIGATION,
+        WAYPOINTS,
+        ROUTE,
+        MAP,
+        PROFILE,
+        GNSS,
+        SETTINGS,
+        DIAGNOSTICS,
+    ];
+
+    fn next(self) -> Page {
+        Page::ALL[(self as usize + 1) % Page::ALL.len()]
+    }
+
+    fn previous(self) -> Page {
+        Page::ALL[(self as usize + Page::ALL.len() - 1) % Page::ALL.len()]
+    }
 }
 
 pub struct SharedState {
     pub is_recording: bool,
+    pub is_paused: bool,
+    /// Set by a long press of the action button, taken by the main loop.
+    pub lap_requested: bool,
+    /// Set by a long press on the waypoints page, taken by the main loop.
+    pub mark_requested: bool,
     pub page: Page,
+    pub last_fix: Option<FixType>,
+    pub last_position: Option<GpsReaderResults>,
+    pub speed_mps: f64,
+    pub dop: Option<Dop>,
+    pub active_satellites: Option<u8>,
+    pub satellites_in_view: Option<u8>,
+    pub gps_stats: GpsReaderStats,
+    pub battery: Option<BatteryStatus>,
 }
 
 #[embassy_executor::task]
-async fn gps_reader_task(gps_reader: &'static mut GpsReader<'static>) {
-    gps_reader.run().await;
+async fn gps_reader_task(
+    gps_reader: &'static mut GpsReader<BufferedUarteRx<'static>>,
+    sender: Sender<'static, NoopRawMutex, ParseOut, 1>,
+    acks: Sender<'static, NoopRawMutex, Ack, ACK_QUEUE_LEN>,
+) {
+    gps_reader.run(sender, acks).await;
 }
 
-pub async fn wait_for_press(input: &mut Input<'_>) {
-    input.wait_for_falling_edge().await;
-    Timer::after(Duration::from_millis(20)).await;
-}
+/// Time between battery readings. The charge moves slowly; a long interval
+/// keeps the SAADC off nearly all the time.
+const BATTERY_INTERVAL: Duration = Duration::from_secs(30);
 
 #[embassy_executor::task]
-async fn action_button_task(
-    mut button: Input<'static>,
+async fn battery_task(
+    mut battery: SaadcBattery,
     shared_state: &'static Mutex<NoopRawMutex, SharedState>,
-    settings_state: &'static Mutex<NoopRawMutex, SettingsState>,
-    mut flash_storage: ProjNVMCStorage,
 ) {
+    let mut monitor = BatteryMonitor::new();
     loop {
-        wait_for_press(&mut button).await;
+        let Ok(change) = monitor.sample(&mut battery).await;
+        if let Some(level) = change {
+            info!("battery {:?}: {:?}", level, monitor.status());
+        }
+        shared_state.lock().await.battery = monitor.status();
+        Timer::after(BATTERY_INTERVAL).await;
+    }
+}
 
-        let mut lock = shared_state.lock().await;
-        if lock.page == SETTINGS {
-            let mut settings_lock = settings_state.lock().await;
-            let index = settings_lock.index;
-            let mut buf = [0u8; 32];
-            match &mut settings_lock.items[index] {
-                SettingsWrapper::Default => {}
-                SettingsWrapper::Bool(setting) => {
-                    setting.options.next();
-                    let v = &u8::try_from(setting.options.index).unwrap();
-                    let res = flash_storage.store_item(&mut buf, setting.id, v).await;
-
-                    info!("{:?}", res);
-                }
-                SettingsWrapper::Text(setting) => {
-                    setting.options.next();
-                    let res = flash_storage
-                        .store_item(
-                            &mut buf,
-                            setting.id,
-                            &u8::try_from(setting.options.index).unwrap(),
-                        )
-                        .await;
-                    info!("{:?}", res);
-                }
-                SettingsWrapper::AnyNumber(setting) => {
-                    setting.options.next();
-                    let res = flash_storage
-                        .store_item(
-                            &mut buf,
-                            setting.id,
-                            &u8::try_from(setting.options.index).unwrap(),
-                        )
-                        .await;
-                    info!("{:?}", res);
-                }
-            };
-        } else {
-            lock.is_recording = !lock.is_recording;
-        };
+/// Enters System OFF. Pressing the action button or a reset boots the
+/// device from scratch.
+fn shut_down() -> ! {
+    use embassy_nrf::pac;
+
+    // P0.23, the action button, pulled up.
+    pac::P0
+        .pin_cnf(23)
+        .modify(|w| w.set_sense(pac::gpio::vals::Sense::Low));
+    pac::REGULATORS.systemoff().write(|w| w.set_systemoff(true));
+    loop {
+        cortex_m::asm::wfe();
     }
 }
 
+/// How long battery warnings stay on screen.
+const BATTERY_WARNING: Duration = Duration::from_secs(5);
+
+/// Longest wait for the track log and the receiver before powering off on
+/// a critical battery. The receiver may first have to finish applying a
+/// settings change.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the receiver gets to answer a command.
+const ACK_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Keeps the receiver configured as the settings say, at boot and after
+/// every change, and passes on the sleep and wake commands of the power
+/// profile. `power_done` gets the id of each of those once it has been
+/// answered or has timed out. The constellations saved in the receiver are
+/// remembered in `settings_storage`, so a boot only saves and restarts the
+/// receiver when they changed.
 #[embassy_executor::task]
-async fn page_button_task(
-    mut button: Input<'static>,
-    shared: &'static Mutex<NoopRawMutex, SharedState>,
+async fn receiver_config_task(
+    mut config: ReceiverConfig<'static, BufferedUarteTx<'static>>,
+    settings_state: &'static Mutex<NoopRawMutex, SettingsState>,
+    settings_storage: &'static Mutex<NoopRawMutex, ProjNVMCStorage>,
+    power: Receiver<'static, NoopRawMutex, Command, 2>,
+    power_done: &'static Signal<NoopRawMutex, u16>,
 ) {
+    let mut applied: Option<ReceiverSettings> = None;
+    // Nothing stored yet: the receiver runs its factory default.
+    let mut saved = load_receiver_constellations(&mut *settings_storage.lock().await)
+        .await
+        .map_or(ConstellationMask::ALL, ConstellationMask::from_setting);
+    // A reset while the power profile had the engine stopped leaves it in
+    // standby.
+    let res = config
+        .send(Command::Wake, || Timer::after(ACK_TIMEOUT))
+        .await;
+    info!("receiver wake: {:?}", res);
     loop {
-        wait_for_press(&mut button).await;
-        let mut lock = shared.lock().await;
-        let next_page = if lock.page == SETTINGS {
-            RECORD
-        } else {
-            SETTINGS
+        let wanted = {
+            let settings = settings_state.lock().await;
+            ReceiverSettings {
+                rate: UpdateRate::from_setting(
+                    number_setting(&settings, GPS_RATE_SETTING_ID).unwrap_or_default(),
+                ),
+                constellations: ConstellationMask::from_setting(
+                    number_setting(&settings, CONSTELLATION_SETTING_ID).unwrap_or_default(),
+                ),
+                static_threshold: number_setting(&settings, STATIC_NAV_SETTING_ID)
+                    .unwrap_or_default() as u8,
+            }
         };
-        lock.page = next_page;
+        if applied != Some(wanted) {
+            let commands = match applied {
+                Some(applied) => wanted.commands(Some(&applied)),
+                None => wanted.boot_commands(saved),
+            };
+            let res = config
+                .send_all(&commands, || Timer::after(ACK_TIMEOUT))
+                .await;
+            info!("receiver config: {:?}", res);
+            if res.is_ok() && wanted.constellations != saved {
+                saved = wanted.constellations;
+                let res = store_receiver_constellations(
+                    &mut *settings_storage.lock().await,
+                    saved.to_setting(),
+                )
+                .await;
+                info!("receiver constellations stored: {:?}", res.is_ok());
+            }
+            // A receiver that refuses a setting won't take it on a retry
+            // either; wait for the next change.
+            applied = Some(wanted);
+        }
+        if let Either::First(command) = select(power.receive(), Timer::after_secs(1)).await {
+            let res = config.send(command, || Timer::after(ACK_TIMEOUT)).await;
+            info!("receiver power {:?}: {:?}", command.id(), res);
+            power_done.signal(command.id());
+        }
     }
 }
 
 #[embassy_executor::task]
-async fn cursor_up_task(
-    mut button: Input<'static>,
-    shared_state: &'static Mutex<NoopRawMutex, SharedState>,
-    settings_state: &'static Mutex<NoopRawMutex, SettingsState>,
+async fn track_log_task(
+    track_log: &'static Mutex<NoopRawMutex, ProjTrackLog>,
+    receiver: Receiver<'static, NoopRawMutex, TrackEvent, 8>,
+    flushed: &'static Signal<NoopRawMutex, ()>,
 ) {
     loop {
-        wait_for_press(&mut button).await;
-        let lock = shared_state.lock().await;
-        if lock.page == SETTINGS {
-            let mut lock = settings_state.lock().await;
-            lock.previous();
+        let event = receiver.receive().await;
+        if let Err(e) = track_log.lock().await.handle(event).await {
+            info!("track log: {:?}", e);
+        }
+        if let TrackEvent::Flush = event {
+            flushed.signal(());
         }
     }
 }
 
 #[embassy_executor::task]
-async fn cursor_down_task(
-    mut button: Input<'static>,
+async fn shell_task(
+    mut shell: Shell<BufferedUarteRx<'static>, BufferedUarteTx<'static>, DeviceBackend>,
+) {
+    shell.run().await;
+}
+
+const BUTTON_QUEUE_LEN: usize = 8;
+
+/// Returns once the button's level differs from `pressed`, at once if it
+/// already does.
+async fn wait_for_change(input: &mut Input<'_>, pressed: bool) {
+    if pressed {
+        input.wait_for_high().await;
+    } else {
+        input.wait_for_low().await;
+    }
+}
+
+/// Watches the buttons, given in `Button::ALL` order, and publishes what
+/// they do. Double presses are only told apart on the action button, so the
+/// others answer a short press without waiting.
+#[embassy_executor::task]
+async fn button_input_task(
+    mut buttons: [Input<'static>; BUTTON_COUNT],
+    sender: Sender<'static, NoopRawMutex, ButtonEvent, BUTTON_QUEUE_LEN>,
+) {
+    let mut recognizer = GestureRecognizer::new().with_double_press(Button::Action);
+    loop {
+        let now = Instant::now().as_millis();
+        let levels = buttons.each_ref().map(|input| input.is_low());
+        for (button, pressed) in Button::ALL.into_iter().zip(levels) {
+            recognizer.input(button, pressed, now);
+        }
+        recognizer.update(now);
+        while let Some(event) = recognizer.next_event() {
+            sender.send(event).await;
+        }
+
+        let [action, page, up, down] = &mut buttons;
+        let change = select_array([
+            wait_for_change(action, levels[0]),
+            wait_for_change(page, levels[1]),
+            wait_for_change(up, levels[2]),
+            wait_for_change(down, levels[3]),
+        ]);
+        match recognizer.next_deadline() {
+            Some(deadline) => {
+                select(change, Timer::at(Instant::from_millis(deadline))).await;
+            }
+            None => {
+                change.await;
+            }
+        }
+    }
+}
+
+/// Moves the selected setting one option on, or back, and saves it.
+async fn step_setting(
+    settings_state: &'static Mutex<NoopRawMutex, SettingsState>,
+    flash_storage: &'static Mutex<NoopRawMutex, ProjNVMCStorage>,
+    forward: bool,
+) {
+    let mut settings_lock = settings_state.lock().await;
+    let index = settings_lock.index;
+    let setting = &mut settings_lock.items[index];
+    if forward {
+        setting.next_option();
+    } else {
+        setting.previous_option();
+    }
+    let res = store_setting(&mut *flash_storage.lock().await, setting).await;
+    info!("{:?}", res);
+}
+
+/// Acts on button events for the page on screen.
+#[embassy_executor::task]
+async fn ui_task(
+    events: Receiver<'static, NoopRawMutex, ButtonEvent, BUTTON_QUEUE_LEN>,
     shared_state: &'static Mutex<NoopRawMutex, SharedState>,
     settings_state: &'static Mutex<NoopRawMutex, SettingsState>,
+    flash_storage: &'static Mutex<NoopRawMutex, ProjNVMCStorage>,
+    waypoints: &'static Mutex<NoopRawMutex, WaypointList>,
 ) {
     loop {
-        wait_for_press(&mut button).await;
-        let lock = shared_state.lock().await;
-        if lock.page == SETTINGS {
-            let mut lock = settings_state.lock().await;
-            lock.next();
+        let event = events.receive().await;
+        let mut lock = shared_state.lock().await;
+        match event {
+            ButtonEvent::Short(Button::Action) | ButtonEvent::Long(Button::Action) => {
+                let long_press = matches!(event, ButtonEvent::Long(_));
+                if lock.page == SETTINGS {
+                    step_setting(settings_state, flash_storage, true).await;
+                } else if lock.page == WAYPOINTS {
+                    if long_press {
+                        lock.mark_requested = true;
+                    } else {
+                        waypoints.lock().await.toggle_target();
+                    }
+                } else if long_press && matches!(lock.page, RECORD | LAPS) {
+                    lock.lap_requested = lock.is_recording;
+                } else {
+                    lock.is_recording = !lock.is_recording;
+                }
+            }
+            // Settings only cycle forwards otherwise.
+            ButtonEvent::Double(Button::Action) if lock.page == SETTINGS => {
+                step_setting(settings_state, flash_storage, false).await;
+            }
+            ButtonEvent::Short(Button::Page) => lock.page = lock.page.next(),
+            ButtonEvent::Long(Button::Page) | ButtonEvent::Repeat(Button::Page) => {
+                lock.page = lock.page.previous();
+            }
+            ButtonEvent::Short(Button::Up)
+            | ButtonEvent::Long(Button::Up)
+            | ButtonEvent::Repeat(Button::Up) => {
+                if lock.page == SETTINGS {
+                    settings_state.lock().await.previous_item();
+                } else if lock.page == WAYPOINTS {
+                    waypoints.lock().await.previous();
+                }
+            }
+            ButtonEvent::Short(Button::Down)
+            | ButtonEvent::Long(Button::Down)
+            | ButtonEvent::Repeat(Button::Down) => {
+                if lock.page == SETTINGS {
+                    settings_state.lock().await.next_item();
+                } else if lock.page == WAYPOINTS {
+                    waypoints.lock().await.next();
+                }
+            }
+            ButtonEvent::Chord(Button::Up, Button::Down) => lock.page = RECORD,
+            _ => {}
         }
     }
 }
@@ -210,6 +498,8 @@ async fn show_jo_updater_task(show_jo_mutex: &'static Mutex<NoopRawMutex, bool>)
 
 static RX_BUFFER: StaticCell<[u8; 256]> = StaticCell::new();
 static TX_BUFFER: StaticCell<[u8; 256]> = StaticCell::new();
+static SHELL_RX_BUFFER: StaticCell<[u8; 256]> = StaticCell::new();
+static SHELL_TX_BUFFER: StaticCell<[u8; 256]> = StaticCell::new();
 
 #[embassy_executor::main]
 async fn main(spawner: Spawner) {
@@ -218,24 +508,68 @@ async fn main(spawner: Spawner) {
     let p = embassy_nrf::init(Default::default());
 
     let nvmc_blocking = Nvmc::new(p.NVMC);
-    let flash = BlockingAsync::new(nvmc_blocking);
-    let flash_addressing = 0x000E_0000..0x0010_0000;
-    let map_config = MapConfig::new(flash_addressing);
-    let mut storage = MapStorage::<u8, _, _>::new(flash, map_config, Cache::new_uncached());
+    let flash = FLASH.init(Mutex::new(BlockingAsync::new(nvmc_blocking)));
+    let map_config = MapConfig::new(0..SETTINGS_FLASH_SIZE);
+    let mut storage =
+        MapStorage::<u8, _, _>::new(settings_partition(flash), map_config, Cache::new_uncached());
+    let track_log = TrackLog::new(track_partition(flash), 0..TRACK_FLASH_SIZE)
+        .await
+        .unwrap();
+    info!("track log: last session {}", track_log.last_session());
 
     Timer::after_millis(250).await;
     let auto_pause_setting = configure_auto_pause_setting(&mut storage).await;
     let time_zone_setting = configure_time_zone_setting(&mut storage).await;
+    let dst_setting = configure_dst_setting(&mut storage).await;
     let unit_setting = configure_units_setting(&mut storage).await;
+    let climb_threshold_setting = configure_climb_threshold_setting(&mut storage).await;
+    let split_setting = configure_split_setting(&mut storage).await;
+    let off_course_setting = configure_off_course_setting(&mut storage).await;
+    let map_orientation_setting = configure_map_orientation_setting(&mut storage).await;
+    let gps_rate_setting = configure_gps_rate_setting(&mut storage).await;
+    let constellation_setting = configure_constellation_setting(&mut storage).await;
+    let static_nav_setting = configure_static_nav_setting(&mut storage).await;
+    let power_setting = configure_power_setting(&mut storage).await;
 
     let shared_state = SHARED_STATE.init(Mutex::new(SharedState {
         is_recording: false,
+        is_paused: false,
+        lap_requested: false,
+        mark_requested: false,
         page: RECORD,
+        last_fix: None,
+        last_position: None,
+        speed_mps: 0.0,
+        dop: None,
+        active_satellites: None,
+        satellites_in_view: None,
+        gps_stats: GpsReaderStats::default(),
+        battery: None,
     }));
 
-    let settings_vec = &[auto_pause_setting, time_zone_setting, unit_setting];
+    let settings_vec = &[
+        auto_pause_setting,
+        time_zone_setting,
+        dst_setting,
+        unit_setting,
+        climb_threshold_setting,
+        split_setting,
+        off_course_setting,
+        map_orientation_setting,
+        gps_rate_setting,
+        constellation_setting,
+        static_nav_setting,
+        power_setting,
+    ];
 
     let settings_state = SETTINGS_STATE.init(Mutex::new(CircularTracker::new(settings_vec, None)));
+    let mut waypoint_list = WaypointList::new();
+    load_waypoints(&mut storage, &mut waypoint_list).await;
+    let waypoints = WAYPOINTS.init(Mutex::new(waypoint_list));
+    let route = ROUTE.init(Mutex::new(Route::new()));
+    load_route(&mut storage, &mut *route.lock().await).await;
+    let settings_storage = SETTINGS_STORAGE.init(Mutex::new(storage));
+    let track_log = TRACK_LOG.init(Mutex::new(track_log));
 
     // set up uarte
     let mut uart_config = uarte::Config::default();
@@ -245,7 +579,7 @@ async fn main(spawner: Spawner) {
     let rx_buffer = RX_BUFFER.init([0u8; 256]);
     let tx_buffer = TX_BUFFER.init([0u8; 256]);
 
-    let uart = BufferedUarte::new(
+    let gps_uart = BufferedUarte::new(
         p.SERIAL1,
         p.TIMER0,
         p.PPI_CH1,
@@ -259,6 +593,40 @@ async fn main(spawner: Spawner) {
         &mut tx_buffer[..],
     );
 
+    // set up the command shell on P0.20 (TX) / P0.22 (RX)
+    let mut shell_uart_config = uarte::Config::default();
+    shell_uart_config.parity = Parity::Excluded;
+    shell_uart_config.baudrate = Baudrate::Baud115200;
+
+    let shell_rx_buffer = SHELL_RX_BUFFER.init([0u8; 256]);
+    let shell_tx_buffer = SHELL_TX_BUFFER.init([0u8; 256]);
+    let shell_uart = BufferedUarte::new(
+        p.SERIAL2,
+        p.TIMER1,
+        p.PPI_CH3,
+        p.PPI_CH4,
+        p.PPI_GROUP1,
+        p.P0_22,
+        p.P0_20,
+        Irqs,
+        shell_uart_config,
+        &mut shell_rx_buffer[..],
+        &mut shell_tx_buffer[..],
+    );
+    let (shell_rx, shell_tx) = shell_uart.split();
+    let shell = Shell::new(
+        shell_rx,
+        shell_tx,
+        DeviceBackend {
+            track_log,
+            shared_state,
+            settings_state,
+            settings_storage,
+            waypoints,
+            route,
+        },
+    );
+
     let serial_port = p.SERIAL0;
     let sda_pin = p.P1_12;
     let scl_pin = p.P1_14;
@@ -286,7 +654,21 @@ async fn main(spawner: Spawner) {
 
     let gps_channel = CHANNEL.init(Channel::new());
     let gps_receiver = gps_channel.receiver();
-    let gps_reader = GPS_READER.init(GpsReader::new(uart, gps_channel.sender()));
+    let (gps_rx, gps_tx) = gps_uart.split();
+    let gps_reader = GPS_READER.init(GpsReader::new(gps_rx));
+    let ack_channel = ACK_CHANNEL.init(Channel::new());
+    let receiver_config = ReceiverConfig::new(gps_tx, ack_channel.receiver());
+    let power_channel = POWER_CHANNEL.init(Channel::new());
+    let power_sender = power_channel.sender();
+    let power_done = POWER_DONE.init(Signal::new());
+
+    let saadc = Saadc::new(
+        p.SAADC,
+        Irqs,
+        saadc::Config::default(),
+        [ChannelConfig::single_ended(p.P0_04)],
+    );
+    let battery = SaadcBattery { saadc };
 
     let record_button = Input::new(p.P0_23, Pull::Up);
     let page_button = Input::new(p.P0_08, Pull::Up);
@@ -294,31 +676,206 @@ async fn main(spawner: Spawner) {
     let cursor_down_button = Input::new(p.P0_09, Pull::Up);
 
     let mut last_fix: Option<FixType> = None;
+    let mut gps_stats = GpsReaderStats::default();
+    let mut gnss = GnssStatus::new();
 
-    let mut last_lat_lon_alt: Option<gps::reader::GpsReaderResults> = None;
+    let mut last_lat_lon_alt: Option<GpsReaderResults> = None;
 
     let mut geo_stack = GeoStack::new();
 
-    spawner.spawn(page_button_task(page_button, shared_state).unwrap());
-    spawner
-        .spawn(action_button_task(record_button, shared_state, settings_state, storage).unwrap());
-    spawner.spawn(cursor_up_task(cursor_up_button, shared_state, settings_state).unwrap());
-    spawner.spawn(cursor_down_task(cursor_down_button, shared_state, settings_state).unwrap());
-    spawner.spawn(gps_reader_task(gps_reader).unwrap());
+    let track_channel = TRACK_CHANNEL.init(Channel::new());
+    let track_sender = track_channel.sender();
+    let track_flushed = TRACK_FLUSHED.init(Signal::new());
+    let mut was_recording = false;
+    let mut auto_pause = AutoPause::new();
+    let mut session_start: Option<NaiveDateTime> = None;
+    let mut session_stats = SessionStats::new();
+    let mut laps = LapTracker::new();
+    // First fix of the latest session, kept after it stops to find the way back.
+    let mut home: Option<LatLonAlt> = None;
+    let mut route_follower = RouteFollower::new();
+    let mut route_progress: Option<RouteProgress> = None;
+    let mut breadcrumbs = Breadcrumbs::new();
+    let mut duty_cycle = DutyCycle::new();
+    // Power profile last written to the open session.
+    let mut recorded_power: Option<PowerProfile> = None;
+    let mut battery_level = BatteryLevel::Normal;
+    // The low battery banner shows for a while after the warning.
+    let mut battery_warning_until: Option<Instant> = None;
+
+    let button_channel = BUTTON_CHANNEL.init(Channel::new());
+    spawner.spawn(
+        button_input_task(
+            [
+                record_button,
+                page_button,
+                cursor_up_button,
+                cursor_down_button,
+            ],
+            button_channel.sender(),
+        )
+        .unwrap(),
+    );
+    spawner.spawn(
+        ui_task(
+            button_channel.receiver(),
+            shared_state,
+            settings_state,
+            settings_storage,
+            waypoints,
+        )
+        .unwrap(),
+    );
+    spawner.spawn(gps_reader_task(gps_reader, gps_channel.sender(), ack_channel.sender()).unwrap());
+    spawner.spawn(
+        receiver_config_task(
+            receiver_config,
+            settings_state,
+            settings_storage,
+            power_channel.receiver(),
+            power_done,
+        )
+        .unwrap(),
+    );
     spawner.spawn(show_jo_updater_task(blink_mutex_ref).unwrap());
+    spawner.spawn(track_log_task(track_log, track_channel.receiver(), track_flushed).unwrap());
+    spawner.spawn(shell_task(shell).unwrap());
+    spawner.spawn(battery_task(battery, shared_state).unwrap());
 
     loop {
-        let (is_recording, page) = {
-            let lock = shared_state.lock().await;
-            (lock.is_recording, lock.page)
+        let (is_recording, page, lap_requested, mark_requested, battery) = {
+            let mut lock = shared_state.lock().await;
+            let lap_requested = core::mem::take(&mut lock.lap_requested);
+            let mark_requested = core::mem::take(&mut lock.mark_requested);
+            (
+                lock.is_recording,
+                lock.page,
+                lap_requested,
+                mark_requested,
+                lock.battery,
+            )
+        };
+
+        if let Some(status) = battery
+            && status.level != battery_level
+        {
+            battery_level = status.level;
+            match status.level {
+                BatteryLevel::Low => battery_warning_until = Some(Instant::now() + BATTERY_WARNING),
+                BatteryLevel::Critical => {
+                    info!(
+                        "battery critical at {} mV, shutting down",
+                        status.millivolts
+                    );
+                    if was_recording {
+                        track_sender.send(TrackEvent::Stop).await;
+                    }
+                    // Let the log task write the last events before the
+                    // power goes.
+                    track_flushed.reset();
+                    track_sender.send(TrackEvent::Flush).await;
+                    let _ = select(track_flushed.wait(), Timer::after(SHUTDOWN_TIMEOUT)).await;
+
+                    // Standby rather than backup: only the wakeup pin ends
+                    // backup, and the wake sent at boot has to reach the
+                    // receiver.
+                    power_done.reset();
+                    power_sender.send(Command::Standby).await;
+                    // Duty-cycle commands queued earlier are answered first.
+                    let parked =
+                        async { while power_done.wait().await != Command::Standby.id() {} };
+                    let _ = select(parked, Timer::after(SHUTDOWN_TIMEOUT)).await;
+
+                    display.clear(BinaryColor::Off).unwrap();
+                    draw_battery_warning(&mut display, &status);
+                    display.flush().unwrap();
+                    Timer::after(BATTERY_WARNING).await;
+                    let _ = display.set_display_on(false);
+                    shut_down();
+                }
+                BatteryLevel::Normal => battery_warning_until = None,
+            }
+        }
+
+        let (units, time_zone, map_orientation, power_profile) = {
+            let settings = settings_state.lock().await;
+            let offset_minutes =
+                number_setting(&settings, TIME_ZONE_SETTING_ID).unwrap_or_default();
+            let dst = number_setting(&settings, DST_SETTING_ID).unwrap_or_default();
+            (
+                Units::from_setting(
+                    number_setting(&settings, UNITS_SETTING_ID).unwrap_or_default(),
+                ),
+                TimeZone::new(offset_minutes as i32, DstRule::from_setting(dst)),
+                MapOrientation::from_setting(
+                    number_setting(&settings, MAP_ORIENTATION_SETTING_ID).unwrap_or_default(),
+                ),
+                PowerProfile::from_setting(
+                    number_setting(&settings, POWER_SETTING_ID).unwrap_or_default(),
+                ),
+            )
         };
 
+        if is_recording != was_recording {
+            let event = if is_recording {
+                auto_pause.reset();
+                session_stats.reset();
+                laps.reset();
+                home = None;
+                route_follower.reset();
+                breadcrumbs.reset();
+                geo_stack.reset_totals();
+                session_start = last_lat_lon_alt.as_ref().and_then(utc_datetime);
+                TrackEvent::Start
+            } else {
+                session_start = None;
+                TrackEvent::Stop
+            };
+            track_sender.send(event).await;
+            recorded_power = None;
+            was_recording = is_recording;
+        }
+
+        if is_recording && recorded_power != Some(power_profile) {
+            track_sender.send(TrackEvent::Power(power_profile)).await;
+            recorded_power = Some(power_profile);
+        }
+
+        let now_ms = Instant::now().as_millis();
+        duty_cycle.set_profile(power_profile, now_ms);
+        if let Some(command) = duty_cycle.poll(now_ms)
+            && power_sender.try_send(command).is_err()
+        {
+            info!("receiver busy, power command dropped");
+        }
+
+        if lap_requested && is_recording {
+            let lap = laps.lap(
+                &session_stats,
+                geo_stack.total_distance,
+                geo_stack.elevation.total_gain,
+            );
+            track_sender.send(TrackEvent::Lap(lap)).await;
+        }
+
+        if mark_requested && let Some(here) = geo_stack.filtered_position {
+            let mut list = waypoints.lock().await;
+            let waypoint = Waypoint::new(&list.next_name(), here);
+            if let Some(slot) = list.insert(waypoint.clone()) {
+                let res =
+                    store_waypoint(&mut *settings_storage.lock().await, slot, &waypoint).await;
+                info!("waypoint {}: {:?}", slot, res);
+            }
+        }
+
         let should_blink = {
             let lock = blink_mutex_ref.lock().await;
             *lock
         };
 
-        let draw_future = Timer::after(Duration::from_millis(100));
+        // Nothing changes while the receiver sleeps; let the MCU idle too.
+        let draw_interval = if duty_cycle.is_asleep() { 1000 } else { 100 };
+        let draw_future = Timer::after(Duration::from_millis(draw_interval));
         let gps_future = gps_receiver.receive();
         match select(draw_future, gps_future).await {
             Either::First(_) => {
@@ -330,25 +887,228 @@ async fn main(spawner: Spawner) {
                     if should_blink {
                         draw_blinky(&mut display);
                     }
-                    draw_coords(&last_lat_lon_alt, &mut display);
+                    draw_coords(&last_lat_lon_alt, units, &mut display);
+                    draw_clock(
+                        last_lat_lon_alt
+                            .as_ref()
+                            .and_then(utc_datetime)
+                            .map(|utc| time_zone.to_local(utc)),
+                        session_start.map(|utc| time_zone.to_local(utc)),
+                        &mut display,
+                    );
 
-                    draw_recording_status(is_recording, &mut display);
-                    draw_total_elev_gain(geo_stack.total_elevation_gain.into(), &mut display);
-                    draw_total_distance(geo_stack.total_distance, &mut display);
-                    draw_current_speed(geo_stack.current_speed_mph, &mut display);
-                    draw_hdop(last_fix, geo_stack.current_hdop, &mut display);
+                    draw_recording_status(is_recording, auto_pause.is_paused(), &mut display);
+                    if let Some(status) = &battery {
+                        draw_battery(&mut display, status, should_blink);
+                    }
+                    draw_total_elev_gain(
+                        geo_stack.elevation.total_gain.into(),
+                        units,
+                        &mut display,
+                    );
+                    draw_total_distance(geo_stack.total_distance, units, &mut display);
+                    draw_current_speed(geo_stack.current_speed_mps, units, &mut display);
+                    draw_gnss_summary(&mut display, &gnss);
+                } else if page == STATS {
+                    draw_stats(
+                        &mut display,
+                        &session_stats,
+                        geo_stack.total_distance,
+                        geo_stack.elevation.total_gain.into(),
+                        units,
+                    );
+                } else if page == LAPS {
+                    draw_laps(
+                        &mut display,
+                        &laps,
+                        &laps.current(
+                            &session_stats,
+                            geo_stack.total_distance,
+                            geo_stack.elevation.total_gain,
+                        ),
+                        units,
+                    );
+                } else if page == NAVIGATION {
+                    // A selected waypoint wins over the way home.
+                    let list = waypoints.lock().await;
+                    let goal = match list.target() {
+                        Some(waypoint) => Some((waypoint.name.as_str(), waypoint.position)),
+                        None => home.map(|home| ("home", home)),
+                    };
+                    let target = match (goal, geo_stack.filtered_position) {
+                        (Some((label, goal)), Some(here)) => Some(Target {
+                            label,
+                            distance_m: haversine_distance_m(here, goal),
+                            bearing_deg: initial_bearing_deg(here, goal),
+                        }),
+                        _ => None,
+                    };
+                    let course =
+                        (geo_stack.current_speed_mps > 0.0).then_some(geo_stack.current_heading);
+                    draw_navigation(
+                        &mut display,
+                        target,
+                        course,
+                        geo_stack.current_speed_mps,
+                        units,
+                    );
+                } else if page == WAYPOINTS {
+                    draw_waypoints(&mut display, &*waypoints.lock().await);
+                } else if page == ROUTE {
+                    let points = route.lock().await.len();
+                    draw_route(&mut display, points, route_progress.as_ref(), units);
+                } else if page == MAP {
+                    draw_map(
+                        &mut display,
+                        &breadcrumbs,
+                        geo_stack.filtered_position,
+                        geo_stack.current_heading,
+                        map_orientation,
+                        units,
+                    );
+                } else if page == PROFILE {
+                    draw_profile(
+                        &mut display,
+                        &geo_stack.profile,
+                        &geo_stack.elevation,
+                        units,
+                    );
+                } else if page == GNSS {
+                    draw_gnss(&mut display, &gnss);
                 } else if page == SETTINGS {
                     draw_settings(&mut display, settings_state).await;
+                } else if page == DIAGNOSTICS {
+                    draw_diagnostics(&mut display, &gps_stats);
+                }
+                if let Some(progress) = &route_progress
+                    && progress.off_course
+                {
+                    draw_off_course(&mut display, progress.off_route_m, units);
+                }
+                if let Some(status) = &battery
+                    && battery_warning_until.is_some_and(|until| Instant::now() < until)
+                {
+                    draw_battery_warning(&mut display, status);
                 }
 
                 display.flush().unwrap();
             }
             Either::Second(gps_parse) => {
                 last_fix = gps_parse.fix.or(last_fix);
+                gps_stats = gps_parse.stats;
+                gnss.update(&gps_parse);
                 let new_coords = gps_parse.reader_results;
                 if let Some(coords) = new_coords {
+                    {
+                        let settings = settings_state.lock().await;
+                        if let Some(threshold) =
+                            number_setting(&settings, CLIMB_THRESHOLD_SETTING_ID)
+                        {
+                            geo_stack.elevation.threshold_m = threshold as f32;
+                        }
+                        geo_stack.min_time_interval_ms = UpdateRate::from_setting(
+                            number_setting(&settings, GPS_RATE_SETTING_ID).unwrap_or_default(),
+                        )
+                        .interval_ms()
+                        .into();
+                        auto_pause.enabled =
+                            bool_setting(&settings, AUTO_PAUSE_SETTING_ID).unwrap_or(false);
+                        laps.split_m =
+                            number_setting(&settings, SPLIT_SETTING_ID).unwrap_or_default() as f64;
+                        route_follower.alert_m = number_setting(&settings, OFF_COURSE_SETTING_ID)
+                            .unwrap_or_default()
+                            as f64;
+                    }
                     last_lat_lon_alt = new_coords;
-                    geo_stack.add_coords(coords, last_lat_lon_alt, is_recording);
+                    if is_recording && session_start.is_none() {
+                        session_start = utc_datetime(&coords);
+                    }
+                    if is_recording
+                        && home.is_none()
+                        && let (Some(latitude), Some(longitude), Some(altitude)) =
+                            (coords.lat, coords.lon, coords.alt)
+                    {
+                        home = Some(LatLonAlt {
+                            latitude,
+                            longitude,
+                            altitude,
+                        });
+                    }
+                    let accepted =
+                        geo_stack.add_coords(coords, is_recording && !auto_pause.is_paused());
+                    if let Some(here) = geo_stack.filtered_position {
+                        route_progress = route_follower.update(&*route.lock().await, here);
+                    }
+                    if accepted
+                        && let Some(command) = duty_cycle.fix(Instant::now().as_millis())
+                        && power_sender.try_send(command).is_err()
+                    {
+                        info!("receiver busy, power command dropped");
+                    }
+                    let point = TrackPoint::from_results(&coords);
+                    if accepted && is_recording {
+                        if let Some(time) = coords.timestamp {
+                            session_stats.update(
+                                time,
+                                geo_stack.current_speed_mps,
+                                !auto_pause.is_paused(),
+                            );
+                        }
+                        if let Some(lap) = laps.update(
+                            &session_stats,
+                            geo_stack.total_distance,
+                            geo_stack.elevation.total_gain,
+                        ) {
+                            track_sender.send(TrackEvent::Lap(lap)).await;
+                        }
+                        let now_ms = Instant::now().as_millis();
+                        // The resume window is already recorded, so the
+                        // resume marker goes back to where it began.
+                        let window_ms = match auto_pause.state() {
+                            PauseState::Starting { since_ms } => (now_ms - since_ms) as u32,
+                            _ => 0,
+                        };
+                        let marker = match auto_pause.update(now_ms, geo_stack.current_speed_mps) {
+                            Some(PauseEvent::Paused) => point.map(|p| TrackEvent::Pause(p.time_ms)),
+                            Some(PauseEvent::Resumed) => point.map(|p| {
+                                TrackEvent::Resume(
+                                    (p.time_ms + MS_PER_DAY - window_ms % MS_PER_DAY) % MS_PER_DAY,
+                                )
+                            }),
+                            None => None,
+                        };
+                        // Markers are rare and matter for the export; wait for room.
+                        if let Some(marker) = marker {
+                            track_sender.send(marker).await;
+                        }
+                        if !auto_pause.is_paused()
+                            && let Some(here) = geo_stack.filtered_position
+                        {
+                            breadcrumbs.push(here);
+                        }
+                    }
+                    if accepted
+                        && is_recording
+                        && !auto_pause.is_paused()
+                        && let Some(point) = point
+                        && track_sender.try_send(TrackEvent::Point(point)).is_err()
+                    {
+                        info!("track log busy, point dropped");
+                    }
+                }
+
+                let mut lock = shared_state.lock().await;
+                lock.last_fix = last_fix;
+                lock.last_position = last_lat_lon_alt;
+                lock.speed_mps = geo_stack.current_speed_mps;
+                lock.is_paused = is_recording && auto_pause.is_paused();
+                lock.gps_stats = gps_stats;
+                if gps_parse.dop.is_some() {
+                    lock.dop = gps_parse.dop;
+                    lock.active_satellites = gps_parse.active_satellites;
+                }
+                if let Some(satellites) = &gps_parse.satellites {
+                    lock.satellites_in_view = u8::try_from(satellites.len()).ok();
                 }
             }
         }
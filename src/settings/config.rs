@@ -1,27 +1,28 @@
-use embassy_embedded_hal::adapter::BlockingAsync;
-use embassy_nrf::nvmc::Nvmc;
+use embedded_storage_async::nor_flash::ErrorType;
 use sequential_storage::{
     cache::{Cache, Uncached},
     map::MapStorage,
 };
 
 use crate::{
+    flash::ProjFlashPartition,
     settings::settings::{Setting, SettingsWrapper},
-    utils::vector::CircularTracker,
 };
 
 pub type ProjNVMCStorage =
-    MapStorage<u8, BlockingAsync<Nvmc<'static>>, Cache<Uncached, Uncached, Uncached, u8>>;
+    MapStorage<u8, ProjFlashPartition, Cache<Uncached, Uncached, Uncached, u8>>;
+pub type SettingsStorageError = sequential_storage::Error<<ProjFlashPartition as ErrorType>::Error>;
 
 macro_rules! setting {
     ($storage: expr, $variant: ident, $id: literal, $label: literal, $options: expr) => {{
         let mut buf = [0; 32];
         let saved_val = $storage.fetch_item(&mut buf, &$id).await;
-        SettingsWrapper::$variant(Setting {
-            id: &$id,
-            label: $label,
-            options: CircularTracker::new(&$options, saved_val.unwrap_or(None)),
-        })
+        SettingsWrapper::$variant(Setting::new(
+            &$id,
+            $label,
+            &$options,
+            saved_val.unwrap_or(None),
+        ))
     }};
 }
 
@@ -29,16 +30,217 @@ pub async fn configure_auto_pause_setting(storage: &mut ProjNVMCStorage) -> Sett
     setting!(storage, Bool, 1, "Auto Pause", [("Y", true), ("N", false)])
 }
 
+/// Standard-time UTC offsets in minutes; DST is a separate setting. The
+/// first three keep the indices stored by older firmware.
+const TIME_ZONES: [(&str, isize); 36] = [
+    ("EST", -300),
+    ("CST", -360),
+    ("PST", -480),
+    ("MST", -420),
+    ("AKST", -540),
+    ("HST", -600),
+    ("AST", -240),
+    ("-12", -720),
+    ("-11", -660),
+    ("-3:30", -210),
+    ("-3", -180),
+    ("-2", -120),
+    ("-1", -60),
+    ("UTC", 0),
+    ("+1", 60),
+    ("+2", 120),
+    ("+3", 180),
+    ("+3:30", 210),
+    ("+4", 240),
+    ("+4:30", 270),
+    ("+5", 300),
+    ("+5:30", 330),
+    ("+5:45", 345),
+    ("+6", 360),
+    ("+6:30", 390),
+    ("+7", 420),
+    ("+8", 480),
+    ("+9", 540),
+    ("+9:30", 570),
+    ("+10", 600),
+    ("+10:30", 630),
+    ("+11", 660),
+    ("+12", 720),
+    ("+12:45", 765),
+    ("+13", 780),
+    ("+14", 840),
+];
+
 pub async fn configure_time_zone_setting(storage: &mut ProjNVMCStorage) -> SettingsWrapper {
+    setting!(storage, AnyNumber, 2, "Time Zone", TIME_ZONES)
+}
+
+pub async fn configure_dst_setting(storage: &mut ProjNVMCStorage) -> SettingsWrapper {
     setting!(
         storage,
         AnyNumber,
-        2,
-        "Time Zone",
-        [("EST", -5), ("CST", -7), ("PST", -9)]
+        5,
+        "DST",
+        [("Off", 0), ("US", 1), ("EU", 2)]
     )
 }
 
 pub async fn configure_units_setting(storage: &mut ProjNVMCStorage) -> SettingsWrapper {
-    setting!(storage, AnyNumber, 3, "Units", [("ft/mi", 0), ("m/km", 1)])
+    setting!(
+        storage,
+        AnyNumber,
+        3,
+        "Units",
+        [("ft/mi", 0), ("m/km", 1), ("nm/kn", 2)]
+    )
+}
+
+pub async fn configure_climb_threshold_setting(storage: &mut ProjNVMCStorage) -> SettingsWrapper {
+    setting!(
+        storage,
+        AnyNumber,
+        4,
+        "Climb Thr",
+        [("3m", 3), ("5m", 5), ("10m", 10), ("1m", 1), ("2m", 2)]
+    )
+}
+
+/// Automatic split distance in meters, zero for manual laps only.
+pub async fn configure_split_setting(storage: &mut ProjNVMCStorage) -> SettingsWrapper {
+    setting!(
+        storage,
+        AnyNumber,
+        6,
+        "Splits",
+        [
+            ("Off", 0),
+            ("1km", 1000),
+            ("1mi", 1609),
+            ("5km", 5000),
+            ("5mi", 8047),
+            ("10km", 10000),
+            ("400m", 400)
+        ]
+    )
+}
+
+/// Distance off the route that raises the off-course alert.
+pub async fn configure_off_course_setting(storage: &mut ProjNVMCStorage) -> SettingsWrapper {
+    setting!(
+        storage,
+        AnyNumber,
+        7,
+        "Off course",
+        [
+            ("Off", 0),
+            ("25m", 25),
+            ("50m", 50),
+            ("100m", 100),
+            ("200m", 200)
+        ]
+    )
+}
+
+/// Which way is up on the map page.
+pub async fn configure_map_orientation_setting(storage: &mut ProjNVMCStorage) -> SettingsWrapper {
+    setting!(
+        storage,
+        AnyNumber,
+        8,
+        "Map up",
+        [("North", 0), ("Heading", 1)]
+    )
+}
+
+/// Receiver fix rate in Hz.
+pub async fn configure_gps_rate_setting(storage: &mut ProjNVMCStorage) -> SettingsWrapper {
+    setting!(
+        storage,
+        AnyNumber,
+        9,
+        "GPS rate",
+        [("1Hz", 1), ("5Hz", 5), ("10Hz", 10)]
+    )
+}
+
+/// Satellite systems the receiver searches, one bit each: GPS, GLONASS,
+/// Galileo, BeiDou, QZSS from the lowest.
+pub async fn configure_constellation_setting(storage: &mut ProjNVMCStorage) -> SettingsWrapper {
+    setting!(
+        storage,
+        AnyNumber,
+        10,
+        "GNSS",
+        [
+            ("All", 0b11111),
+            ("GPS", 0b00001),
+            ("GPS+GLO", 0b00011),
+            ("GPS+GAL", 0b00101),
+            ("GPS+BDS", 0b01001),
+            ("GPS+GAL+QZ", 0b10101)
+        ]
+    )
+}
+
+/// Speed in dm/s below which the receiver holds the position still.
+pub async fn configure_static_nav_setting(storage: &mut ProjNVMCStorage) -> SettingsWrapper {
+    setting!(
+        storage,
+        AnyNumber,
+        11,
+        "Static nav",
+        [("Off", 0), ("0.5m/s", 5), ("1m/s", 10), ("2m/s", 20)]
+    )
+}
+
+/// Seconds between fixes; the receiver sleeps in between. 0 keeps it on.
+pub async fn configure_power_setting(storage: &mut ProjNVMCStorage) -> SettingsWrapper {
+    setting!(
+        storage,
+        AnyNumber,
+        12,
+        "Power",
+        [
+            ("Always on", 0),
+            ("1 fix/5s", 5),
+            ("1 fix/30s", 30),
+            ("1 fix/min", 60)
+        ]
+    )
+}
+
+/// Where the constellations saved in the receiver's own flash are kept,
+/// clear of the setting ids.
+const RECEIVER_CONSTELLATIONS_KEY: u8 = 0x40;
+
+/// The constellations last saved in the receiver's own flash, as a GNSS
+/// setting value.
+pub async fn load_receiver_constellations(storage: &mut ProjNVMCStorage) -> Option<isize> {
+    let mut buf = [0u8; 32];
+    let saved = storage
+        .fetch_item::<u8>(&mut buf, &RECEIVER_CONSTELLATIONS_KEY)
+        .await;
+    saved.ok().flatten().map(isize::from)
+}
+
+pub async fn store_receiver_constellations(
+    storage: &mut ProjNVMCStorage,
+    value: isize,
+) -> Result<(), SettingsStorageError> {
+    let mut buf = [0u8; 32];
+    storage
+        .store_item(&mut buf, &RECEIVER_CONSTELLATIONS_KEY, &(value as u8))
+        .await
+}
+
+/// Saves the selected option index of `setting` under its id.
+pub async fn store_setting(
+    storage: &mut ProjNVMCStorage,
+    setting: &SettingsWrapper,
+) -> Result<(), SettingsStorageError> {
+    let (Some(id), Some(index)) = (setting.id(), setting.index()) else {
+        return Ok(());
+    };
+    let mut buf = [0u8; 32];
+    storage.store_item(&mut buf, &id, &index).await
 }
@@ -1,2 +1,4 @@
 pub mod config;
+pub mod route;
 pub mod settings;
+pub mod waypoints;
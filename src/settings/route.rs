@@ -0,0 +1,69 @@
+use crate::{
+    gps::route::{CHUNK_ENCODED_LEN, CHUNK_POINTS, MAX_ROUTE_POINTS, Route},
+    settings::config::{ProjNVMCStorage, SettingsStorageError},
+};
+
+/// Holds the number of points; the chunks follow it, `CHUNK_POINTS` points
+/// each. Clear of the setting ids and the waypoint slots.
+const ROUTE_KEY: u8 = 0xA0;
+const MAX_CHUNKS: usize = MAX_ROUTE_POINTS.div_ceil(CHUNK_POINTS);
+/// Key, value and the map's own item header.
+const ITEM_BUF_LEN: usize = CHUNK_ENCODED_LEN + 32;
+
+fn chunk_key(index: usize) -> u8 {
+    ROUTE_KEY + 1 + index as u8
+}
+
+/// Fills `route` from flash. A route that fails to read comes back empty.
+pub async fn load_route(storage: &mut ProjNVMCStorage, route: &mut Route) {
+    route.clear();
+    let mut buf = [0u8; ITEM_BUF_LEN];
+    let Ok(Some(count)) = storage.fetch_item::<u16>(&mut buf, &ROUTE_KEY).await else {
+        return;
+    };
+    for index in 0..usize::from(count).div_ceil(CHUNK_POINTS) {
+        let loaded = match storage
+            .fetch_item::<&[u8]>(&mut buf, &chunk_key(index))
+            .await
+        {
+            Ok(Some(bytes)) => route.decode_chunk(bytes).is_ok(),
+            _ => false,
+        };
+        if !loaded {
+            route.clear();
+            return;
+        }
+    }
+}
+
+/// Drops the point count first and writes it last, so an interrupted write
+/// leaves no route rather than a mix of old and new chunks.
+pub async fn store_route(
+    storage: &mut ProjNVMCStorage,
+    route: &Route,
+) -> Result<(), SettingsStorageError> {
+    let mut buf = [0u8; ITEM_BUF_LEN];
+    let mut encoded = [0u8; CHUNK_ENCODED_LEN];
+    storage.remove_item(&mut buf, &ROUTE_KEY).await?;
+    for index in 0..route.chunk_count() {
+        let bytes = route.encode_chunk(index, &mut encoded);
+        storage
+            .store_item(&mut buf, &chunk_key(index), &bytes)
+            .await?;
+    }
+    for index in route.chunk_count()..MAX_CHUNKS {
+        storage.remove_item(&mut buf, &chunk_key(index)).await?;
+    }
+    storage
+        .store_item(&mut buf, &ROUTE_KEY, &(route.len() as u16))
+        .await
+}
+
+pub async fn remove_route(storage: &mut ProjNVMCStorage) -> Result<(), SettingsStorageError> {
+    let mut buf = [0u8; ITEM_BUF_LEN];
+    storage.remove_item(&mut buf, &ROUTE_KEY).await?;
+    for index in 0..MAX_CHUNKS {
+        storage.remove_item(&mut buf, &chunk_key(index)).await?;
+    }
+    Ok(())
+}
@@ -1,10 +1,55 @@
 use crate::utils::vector::CircularTracker;
 
 #[derive(Copy, Clone, Debug)]
-pub struct Setting<T: Copy + Default> {
+pub struct Setting<T: Copy + 'static> {
     pub id: &'static u8,
     pub label: &'static str,
-    pub options: CircularTracker<8, (&'static str, T)>,
+    /// The option table stays in flash; only the cursor is kept in RAM.
+    pub options: &'static [(&'static str, T)],
+    pub index: usize,
+}
+
+impl<T: Copy + 'static> Setting<T> {
+    /// Starts at the saved option index, or the first option if it no
+    /// longer fits the `N` options.
+    pub fn new<const N: usize>(
+        id: &'static u8,
+        label: &'static str,
+        options: &'static [(&'static str, T); N],
+        saved: Option<u8>,
+    ) -> Self {
+        let index = saved.map(usize::from).filter(|&index| index < N);
+        Self {
+            id,
+            label,
+            options,
+            index: index.unwrap_or_default(),
+        }
+    }
+
+    pub fn current(&self) -> (&'static str, T) {
+        self.options[self.index]
+    }
+
+    pub fn next_item(&mut self) {
+        self.index = (self.index + 1) % self.options.len();
+    }
+
+    pub fn previous_item(&mut self) {
+        self.index = (self.index + self.options.len() - 1) % self.options.len();
+    }
+
+    /// Moves to the first option matching `pred`, leaving the index alone if
+    /// none does.
+    pub fn select(&mut self, pred: impl Fn(&(&'static str, T)) -> bool) -> bool {
+        match self.options.iter().position(pred) {
+            Some(index) => {
+                self.index = index;
+                true
+            }
+            None => false,
+        }
+    }
 }
 
 #[derive(Copy, Clone, Default, Debug)]
@@ -16,4 +61,114 @@ pub enum SettingsWrapper {
     AnyNumber(Setting<isize>),
 }
 
-pub type SettingsState = CircularTracker<3, SettingsWrapper>;
+pub type SettingsState = CircularTracker<12, SettingsWrapper>;
+
+pub const AUTO_PAUSE_SETTING_ID: u8 = 1;
+pub const TIME_ZONE_SETTING_ID: u8 = 2;
+pub const UNITS_SETTING_ID: u8 = 3;
+pub const CLIMB_THRESHOLD_SETTING_ID: u8 = 4;
+pub const DST_SETTING_ID: u8 = 5;
+pub const SPLIT_SETTING_ID: u8 = 6;
+pub const OFF_COURSE_SETTING_ID: u8 = 7;
+pub const MAP_ORIENTATION_SETTING_ID: u8 = 8;
+pub const GPS_RATE_SETTING_ID: u8 = 9;
+pub const CONSTELLATION_SETTING_ID: u8 = 10;
+pub const STATIC_NAV_SETTING_ID: u8 = 11;
+pub const POWER_SETTING_ID: u8 = 12;
+
+/// Current value of the yes/no setting `id`.
+pub fn bool_setting(state: &SettingsState, id: u8) -> Option<bool> {
+    state.items[..state.len].iter().find_map(|item| match item {
+        SettingsWrapper::Bool(setting) if *setting.id == id => Some(setting.current().1),
+        _ => None,
+    })
+}
+
+/// Current value of the numeric setting `id`.
+pub fn number_setting(state: &SettingsState, id: u8) -> Option<isize> {
+    state.items[..state.len].iter().find_map(|item| match item {
+        SettingsWrapper::AnyNumber(setting) if *setting.id == id => Some(setting.current().1),
+        _ => None,
+    })
+}
+
+impl SettingsWrapper {
+    pub fn id(&self) -> Option<u8> {
+        match self {
+            SettingsWrapper::Default => None,
+            SettingsWrapper::Bool(setting) => Some(*setting.id),
+            SettingsWrapper::Text(setting) => Some(*setting.id),
+            SettingsWrapper::AnyNumber(setting) => Some(*setting.id),
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SettingsWrapper::Default => "",
+            SettingsWrapper::Bool(setting) => setting.label,
+            SettingsWrapper::Text(setting) => setting.label,
+            SettingsWrapper::AnyNumber(setting) => setting.label,
+        }
+    }
+
+    pub fn value_label(&self) -> &'static str {
+        match self {
+            SettingsWrapper::Default => "",
+            SettingsWrapper::Bool(setting) => setting.current().0,
+            SettingsWrapper::Text(setting) => setting.current().0,
+            SettingsWrapper::AnyNumber(setting) => setting.current().0,
+        }
+    }
+
+    pub fn index(&self) -> Option<u8> {
+        let index = match self {
+            SettingsWrapper::Default => return None,
+            SettingsWrapper::Bool(setting) => setting.index,
+            SettingsWrapper::Text(setting) => setting.index,
+            SettingsWrapper::AnyNumber(setting) => setting.index,
+        };
+        u8::try_from(index).ok()
+    }
+
+    pub fn next_option(&mut self) {
+        match self {
+            SettingsWrapper::Default => {}
+            SettingsWrapper::Bool(setting) => {
+                setting.next_item();
+            }
+            SettingsWrapper::Text(setting) => {
+                setting.next_item();
+            }
+            SettingsWrapper::AnyNumber(setting) => {
+                setting.next_item();
+            }
+        }
+    }
+
+    pub fn previous_option(&mut self) {
+        match self {
+            SettingsWrapper::Default => {}
+            SettingsWrapper::Bool(setting) => {
+                setting.previous_item();
+            }
+            SettingsWrapper::Text(setting) => {
+                setting.previous_item();
+            }
+            SettingsWrapper::AnyNumber(setting) => {
+                setting.previous_item();
+            }
+        }
+    }
+
+    /// Selects the option whose label is `value_label`.
+    pub fn select(&mut self, value_label: &str) -> bool {
+        match self {
+            SettingsWrapper::Default => false,
+            SettingsWrapper::Bool(setting) => setting.select(|(label, _)| *label == value_label),
+            SettingsWrapper::Text(setting) => setting.select(|(label, _)| *label == value_label),
+            SettingsWrapper::AnyNumber(setting) => {
+                setting.select(|(label, _)| *label == value_label)
+            }
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use crate::{
+    gps::waypoint::{ENCODED_LEN, MAX_WAYPOINTS, Waypoint, WaypointList},
+    settings::config::{ProjNVMCStorage, SettingsStorageError},
+};
+
+/// Waypoints share the settings map; their keys start here, one per slot,
+/// well clear of the setting ids.
+const WAYPOINT_KEY_BASE: u8 = 0x80;
+/// Key, value and the map's own item header.
+const ITEM_BUF_LEN: usize = 64;
+
+fn key(slot: usize) -> u8 {
+    WAYPOINT_KEY_BASE + slot as u8
+}
+
+/// Fills `list` from flash. Slots that fail to read are left empty.
+pub async fn load_waypoints(storage: &mut ProjNVMCStorage, list: &mut WaypointList) {
+    let mut buf = [0u8; ITEM_BUF_LEN];
+    for slot in 0..MAX_WAYPOINTS {
+        if let Ok(Some(bytes)) = storage.fetch_item::<&[u8]>(&mut buf, &key(slot)).await
+            && let Some(waypoint) = Waypoint::decode(bytes)
+        {
+            list.set(slot, waypoint);
+        }
+    }
+}
+
+pub async fn store_waypoint(
+    storage: &mut ProjNVMCStorage,
+    slot: usize,
+    waypoint: &Waypoint,
+) -> Result<(), SettingsStorageError> {
+    let mut encoded = [0u8; ENCODED_LEN];
+    let bytes = waypoint.encode(&mut encoded);
+    let mut buf = [0u8; ITEM_BUF_LEN];
+    storage.store_item(&mut buf, &key(slot), &bytes).await
+}
+
+pub async fn remove_waypoint(
+    storage: &mut ProjNVMCStorage,
+    slot: usize,
+) -> Result<(), SettingsStorageError> {
+    let mut buf = [0u8; ITEM_BUF_LEN];
+    storage.remove_item(&mut buf, &key(slot)).await
+}
@@ -0,0 +1,117 @@
+use nmea::sentences::FixType;
+
+use crate::{
+    gps::{
+        fns::LatLonAlt,
+        reader::{Dop, GpsReaderResults, GpsReaderStats},
+        route::Route,
+        waypoint::Waypoint,
+    },
+    track::{
+        log::{SessionEntry, SessionSummary, TrackLogError},
+        record::TrackPoint,
+    },
+    utils::units::Units,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BackendError {
+    Storage,
+    NotFound,
+    InvalidValue,
+    SessionActive,
+    Full,
+}
+
+impl BackendError {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackendError::Storage => "storage",
+            BackendError::NotFound => "not found",
+            BackendError::InvalidValue => "invalid value",
+            BackendError::SessionActive => "session is recording",
+            BackendError::Full => "full",
+        }
+    }
+}
+
+impl<E> From<TrackLogError<E>> for BackendError {
+    fn from(err: TrackLogError<E>) -> Self {
+        match err {
+            TrackLogError::SessionActive => BackendError::SessionActive,
+            _ => BackendError::Storage,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct SettingInfo {
+    pub id: u8,
+    pub label: &'static str,
+    pub value: &'static str,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct GpsStatus {
+    pub fix: Option<FixType>,
+    pub position: Option<GpsReaderResults>,
+    pub speed_mps: f64,
+    pub units: Units,
+    pub dop: Option<Dop>,
+    pub active_satellites: Option<u8>,
+    pub satellites_in_view: Option<u8>,
+    pub gps_stats: GpsReaderStats,
+    pub is_recording: bool,
+    pub is_paused: bool,
+}
+
+/// Everything the shell needs from the rest of the device. The device
+/// implementation (`DeviceBackend` in the firmware) locks the shared state;
+/// tests use plain fakes.
+#[allow(async_fn_in_trait)]
+pub trait ShellBackend {
+    async fn sessions(&mut self, f: impl AsyncFnMut(SessionSummary)) -> Result<(), BackendError>;
+
+    /// The session being recorded, if any.
+    async fn active_session(&mut self) -> Option<u16>;
+
+    /// Returns `false` when the session does not exist.
+    async fn session_points(
+        &mut self,
+        session: u16,
+        f: impl AsyncFnMut(&TrackPoint),
+    ) -> Result<bool, BackendError>;
+
+    /// Like `session_points`, with auto-pause markers in between.
+    async fn session_entries(
+        &mut self,
+        session: u16,
+        f: impl AsyncFnMut(&SessionEntry),
+    ) -> Result<bool, BackendError>;
+
+    async fn delete_session(&mut self, session: u16) -> Result<(), BackendError>;
+
+    async fn setting(&mut self, id: u8) -> Result<SettingInfo, BackendError>;
+
+    async fn set_setting(&mut self, id: u8, value: &str) -> Result<SettingInfo, BackendError>;
+
+    /// Calls `f` with every saved waypoint and its slot.
+    async fn waypoints(&mut self, f: impl AsyncFnMut(u8, &Waypoint)) -> Result<(), BackendError>;
+
+    /// Saves to the first free slot and returns it.
+    async fn save_waypoint(&mut self, waypoint: Waypoint) -> Result<u8, BackendError>;
+
+    async fn delete_waypoint(&mut self, slot: u8) -> Result<(), BackendError>;
+
+    /// Calls `f` with every point of the loaded route, in order.
+    async fn route_points(&mut self, f: impl AsyncFnMut(&LatLonAlt)) -> Result<(), BackendError>;
+
+    /// Replaces the loaded route.
+    async fn save_route(&mut self, route: &Route) -> Result<(), BackendError>;
+
+    async fn delete_route(&mut self) -> Result<(), BackendError>;
+
+    async fn gps_status(&mut self) -> GpsStatus;
+
+    async fn reboot(&mut self);
+}
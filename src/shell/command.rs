@@ -0,0 +1,157 @@
+use chrono::NaiveDate;
+use heapless::String;
+
+pub const MAX_VALUE_LEN: usize = 16;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DumpFormat {
+    Gpx,
+    Csv,
+    Fit,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Help,
+    List,
+    Dump {
+        session: u16,
+        format: DumpFormat,
+        date: Option<NaiveDate>,
+    },
+    Delete {
+        session: u16,
+    },
+    Get {
+        id: u8,
+    },
+    Set {
+        id: u8,
+        value: String<MAX_VALUE_LEN>,
+    },
+    ListWaypoints,
+    DumpWaypoints,
+    LoadWaypoints,
+    DeleteWaypoint {
+        slot: u8,
+    },
+    RouteInfo,
+    DumpRoute,
+    LoadRoute,
+    DeleteRoute,
+    Status,
+    Reboot,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ParseError {
+    Empty,
+    UnknownCommand,
+    MissingArgument,
+    InvalidArgument,
+}
+
+impl ParseError {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ParseError::Empty => "empty",
+            ParseError::UnknownCommand => "unknown command",
+            ParseError::MissingArgument => "missing argument",
+            ParseError::InvalidArgument => "invalid argument",
+        }
+    }
+}
+
+pub const HELP_TEXT: &str = "ls                        list sessions\r\n\
+dump <id> gpx|csv|fit [date]\r\n\
+                          dump a session, date (YYYY-MM-DD) for undated points\r\n\
+rm <id>                   delete a session\r\n\
+get <id>                  show a setting\r\n\
+set <id> <value>          change a setting\r\n\
+wpt ls                    list waypoints\r\n\
+wpt dump                  dump waypoints as GPX\r\n\
+wpt load                  read GPX <wpt> elements until </gpx>\r\n\
+wpt rm <slot>             delete a waypoint\r\n\
+route                     show the loaded route\r\n\
+route dump                dump the route as GPX\r\n\
+route load                read GPX <rtept>/<trkpt> elements until </gpx>\r\n\
+route rm                  delete the route\r\n\
+status                    show GPS status\r\n\
+reboot                    restart the device\r\n";
+
+/// Parses one line of shell input. Words are separated by spaces.
+pub fn parse_command(line: &str) -> Result<Command, ParseError> {
+    let mut words = line.split_ascii_whitespace();
+    let Some(name) = words.next() else {
+        return Err(ParseError::Empty);
+    };
+
+    let command = match name {
+        "help" | "?" => Command::Help,
+        "ls" | "list" => Command::List,
+        "dump" => {
+            let session = parse_number(words.next())?;
+            let format = match words.next() {
+                Some("gpx") => DumpFormat::Gpx,
+                Some("csv") => DumpFormat::Csv,
+                Some("fit") => DumpFormat::Fit,
+                Some(_) => return Err(ParseError::InvalidArgument),
+                None => return Err(ParseError::MissingArgument),
+            };
+            let date = match words.next() {
+                Some(word) => Some(
+                    NaiveDate::parse_from_str(word, "%Y-%m-%d")
+                        .map_err(|_| ParseError::InvalidArgument)?,
+                ),
+                None => None,
+            };
+            Command::Dump {
+                session,
+                format,
+                date,
+            }
+        }
+        "rm" | "delete" => Command::Delete {
+            session: parse_number(words.next())?,
+        },
+        "get" => Command::Get {
+            id: parse_number(words.next())?,
+        },
+        "set" => {
+            let id = parse_number(words.next())?;
+            let word = words.next().ok_or(ParseError::MissingArgument)?;
+            let value = String::try_from(word).map_err(|_| ParseError::InvalidArgument)?;
+            Command::Set { id, value }
+        }
+        "wpt" => match words.next() {
+            None | Some("ls") => Command::ListWaypoints,
+            Some("dump") => Command::DumpWaypoints,
+            Some("load") => Command::LoadWaypoints,
+            Some("rm") => Command::DeleteWaypoint {
+                slot: parse_number(words.next())?,
+            },
+            Some(_) => return Err(ParseError::InvalidArgument),
+        },
+        "route" => match words.next() {
+            None => Command::RouteInfo,
+            Some("dump") => Command::DumpRoute,
+            Some("load") => Command::LoadRoute,
+            Some("rm") => Command::DeleteRoute,
+            Some(_) => return Err(ParseError::InvalidArgument),
+        },
+        "status" => Command::Status,
+        "reboot" => Command::Reboot,
+        _ => return Err(ParseError::UnknownCommand),
+    };
+
+    if words.next().is_some() {
+        return Err(ParseError::InvalidArgument);
+    }
+    Ok(command)
+}
+
+fn parse_number<T: core::str::FromStr>(word: Option<&str>) -> Result<T, ParseError> {
+    word.ok_or(ParseError::MissingArgument)?
+        .parse()
+        .map_err(|_| ParseError::InvalidArgument)
+}
@@ -0,0 +1,652 @@
+use core::fmt::Write as _;
+
+use chrono::NaiveDate;
+use embedded_io::ErrorKind;
+use embedded_io_async::{Read, Write};
+use heapless::{String, Vec};
+
+use crate::{
+    gps::{
+        fns::{LatLonAlt, haversine_distance_m},
+        gpx::{GpxEvent, GpxParser},
+        route::Route,
+        waypoint::Waypoint,
+    },
+    shell::{
+        backend::{BackendError, ShellBackend},
+        command::{Command, DumpFormat, HELP_TEXT, ParseError, parse_command},
+    },
+    track::{
+        csv::CsvWriter,
+        fit::FitWriter,
+        gpx::GpxWriter,
+        log::{PointCounter, SessionEntry, SessionSummary},
+        record::TrackPoint,
+    },
+};
+
+pub const MAX_LINE_LEN: usize = 64;
+const CHUNK_LEN: usize = 256;
+
+/// Ctrl-C, to abandon an upload.
+const ABORT: u8 = 0x03;
+
+/// Line-oriented command shell over any async byte stream.
+///
+/// Every command answers with its output followed by a final `OK` or
+/// `ERR <reason>` line. `wpt load` and `route load` are the exception to
+/// line input: until `</gpx>` the bytes go to the GPX parser instead.
+pub struct Shell<R: Read, W: Write, B: ShellBackend> {
+    rx: R,
+    tx: W,
+    backend: B,
+    line: Vec<u8, MAX_LINE_LEN>,
+    overflow: bool,
+    upload: Option<Upload>,
+    /// Collects a `route load` so a failed upload leaves the old route.
+    route: Route,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum UploadTarget {
+    Waypoints,
+    Route,
+}
+
+/// A `wpt load` or `route load` in progress.
+struct Upload {
+    target: UploadTarget,
+    parser: GpxParser,
+    saved: u16,
+    skipped: u16,
+    error: Option<BackendError>,
+}
+
+impl Upload {
+    fn new(target: UploadTarget) -> Self {
+        Upload {
+            target,
+            parser: GpxParser::new(),
+            saved: 0,
+            skipped: 0,
+            error: None,
+        }
+    }
+}
+
+impl<R: Read, W: Write, B: ShellBackend> Shell<R, W, B> {
+    pub fn new(rx: R, tx: W, backend: B) -> Self {
+        Shell {
+            rx,
+            tx,
+            backend,
+            line: Vec::new(),
+            overflow: false,
+            upload: None,
+            route: Route::new(),
+        }
+    }
+
+    /// Serves commands until the reader reports end of input.
+    pub async fn run(&mut self) {
+        let mut chunk = [0u8; 32];
+        loop {
+            match self.rx.read(&mut chunk).await {
+                Ok(0) => return,
+                Ok(n) => {
+                    for &byte in &chunk[..n] {
+                        let _ = self.push_byte(byte).await;
+                    }
+                }
+                Err(_) => {}
+            }
+        }
+    }
+
+    async fn push_byte(&mut self, byte: u8) -> Result<(), W::Error> {
+        if self.upload.is_some() {
+            return self.push_upload_byte(byte).await;
+        }
+        match byte {
+            b'\r' | b'\n' => {
+                if core::mem::take(&mut self.overflow) {
+                    self.line.clear();
+                    return self.tx.write_all(b"ERR line too long\r\n").await;
+                }
+                if self.line.is_empty() {
+                    return Ok(());
+                }
+                let line = core::mem::take(&mut self.line);
+                match core::str::from_utf8(&line) {
+                    Ok(text) => self.execute(text).await,
+                    Err(_) => self.tx.write_all(b"ERR invalid utf-8\r\n").await,
+                }
+            }
+            _ => {
+                if self.line.push(byte).is_err() {
+                    self.overflow = true;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    pub async fn execute(&mut self, line: &str) -> Result<(), W::Error> {
+        let command = match parse_command(line) {
+            Ok(command) => command,
+            Err(ParseError::Empty) => return Ok(()),
+            Err(e) => return write_fmt(&mut self.tx, format_args!("ERR {}\r\n", e.as_str())).await,
+        };
+
+        let result = match command {
+            Command::Help => {
+                self.tx.write_all(HELP_TEXT.as_bytes()).await?;
+                Ok(())
+            }
+            Command::List => self.list().await?,
+            Command::Dump {
+                session,
+                format,
+                date,
+            } => self.dump(session, format, date).await?,
+            Command::Delete { session } => self.backend.delete_session(session).await,
+            Command::Get { id } => match self.backend.setting(id).await {
+                Ok(info) => {
+                    write_fmt(
+                        &mut self.tx,
+                        format_args!("{} {} = {}\r\n", info.id, info.label, info.value),
+                    )
+                    .await?;
+                    Ok(())
+                }
+                Err(e) => Err(e),
+            },
+            Command::Set { id, value } => match self.backend.set_setting(id, &value).await {
+                Ok(info) => {
+                    write_fmt(
+                        &mut self.tx,
+                        format_args!("{} {} = {}\r\n", info.id, info.label, info.value),
+                    )
+                    .await?;
+                    Ok(())
+                }
+                Err(e) => Err(e),
+            },
+            Command::ListWaypoints => self.list_waypoints().await?,
+            Command::DumpWaypoints => self.dump_waypoints().await?,
+            Command::LoadWaypoints => return self.begin_upload(UploadTarget::Waypoints).await,
+            Command::DeleteWaypoint { slot } => self.backend.delete_waypoint(slot).await,
+            Command::RouteInfo => self.route_info().await?,
+            Command::DumpRoute => self.dump_route().await?,
+            Command::LoadRoute => {
+                self.route.clear();
+                return self.begin_upload(UploadTarget::Route).await;
+            }
+            Command::DeleteRoute => self.backend.delete_route().await,
+            Command::Status => {
+                let status = self.backend.gps_status().await;
+                match status.fix {
+                    Some(fix) => write_fmt(&mut self.tx, format_args!("fix {:?}\r\n", fix)).await?,
+                    None => self.tx.write_all(b"fix none\r\n").await?,
+                }
+                if let Some(position) = status.position {
+                    write_fmt(
+                        &mut self.tx,
+                        format_args!(
+                            "lat {:?} lon {:?} alt {:?} date {:?}\r\n",
+                            position.lat, position.lon, position.alt, position.date
+                        ),
+                    )
+                    .await?;
+                    write_fmt(
+                        &mut self.tx,
+                        format_args!(
+                            "sog {:?} kn cog {:?}\r\n",
+                            position.speed_knots, position.course
+                        ),
+                    )
+                    .await?;
+                }
+                if let Some(dop) = status.dop {
+                    write_fmt(
+                        &mut self.tx,
+                        format_args!(
+                            "pdop {:?} hdop {:?} vdop {:?}\r\n",
+                            dop.pdop, dop.hdop, dop.vdop
+                        ),
+                    )
+                    .await?;
+                }
+                write_fmt(
+                    &mut self.tx,
+                    format_args!(
+                        "satellites {:?} used {:?} in view\r\n",
+                        status.active_satellites, status.satellites_in_view
+                    ),
+                )
+                .await?;
+                let stats = status.gps_stats;
+                write_fmt(
+                    &mut self.tx,
+                    format_args!(
+                        "nmea good {} checksum {} overflow {} talker {} parse {}\r\n",
+                        stats.good,
+                        stats.bad_checksum,
+                        stats.overflow,
+                        stats.unknown_talker,
+                        stats.parse_errors
+                    ),
+                )
+                .await?;
+                write_fmt(
+                    &mut self.tx,
+                    format_args!(
+                        "speed {} recording {} paused {}\r\n",
+                        status.units.speed(status.speed_mps),
+                        status.is_recording,
+                        status.is_paused
+                    ),
+                )
+                .await?;
+                if let Some(pace) = status.units.pace(status.speed_mps) {
+                    write_fmt(&mut self.tx, format_args!("pace {}\r\n", pace)).await?;
+                }
+                Ok(())
+            }
+            Command::Reboot => {
+                self.tx.write_all(b"OK\r\n").await?;
+                self.tx.flush().await?;
+                self.backend.reboot().await;
+                return Ok(());
+            }
+        };
+
+        match result {
+            Ok(()) => self.tx.write_all(b"OK\r\n").await,
+            Err(e) => write_fmt(&mut self.tx, format_args!("ERR {}\r\n", e.as_str())).await,
+        }
+    }
+
+    async fn begin_upload(&mut self, target: UploadTarget) -> Result<(), W::Error> {
+        self.upload = Some(Upload::new(target));
+        self.tx
+            .write_all(b"send GPX, end with </gpx> or ctrl-c\r\n")
+            .await
+    }
+
+    async fn push_upload_byte(&mut self, byte: u8) -> Result<(), W::Error> {
+        let Some(upload) = self.upload.as_mut() else {
+            return Ok(());
+        };
+        if byte == ABORT {
+            self.upload = None;
+            return self.tx.write_all(b"ERR aborted\r\n").await;
+        }
+        match (upload.target, upload.parser.push(byte)) {
+            (_, None) => Ok(()),
+            (UploadTarget::Waypoints, Some(GpxEvent::Waypoint(waypoint))) => {
+                if upload.error.is_some() {
+                    upload.skipped += 1;
+                    return Ok(());
+                }
+                match self.backend.save_waypoint(waypoint).await {
+                    Ok(slot) => {
+                        upload.saved += 1;
+                        write_fmt(&mut self.tx, format_args!("saved {}\r\n", slot)).await
+                    }
+                    Err(e) => {
+                        upload.skipped += 1;
+                        upload.error = Some(e);
+                        Ok(())
+                    }
+                }
+            }
+            (UploadTarget::Route, Some(GpxEvent::Point(point))) => {
+                match self.route.push(point) {
+                    Ok(true) => upload.saved += 1,
+                    Ok(false) => upload.skipped += 1,
+                    Err(_) => {
+                        upload.skipped += 1;
+                        upload.error = Some(BackendError::Full);
+                    }
+                }
+                Ok(())
+            }
+            (_, Some(GpxEvent::Invalid)) => {
+                upload.skipped += 1;
+                Ok(())
+            }
+            (_, Some(GpxEvent::Waypoint(_) | GpxEvent::Point(_))) => Ok(()),
+            (target, Some(GpxEvent::End)) => {
+                let Some(mut upload) = self.upload.take() else {
+                    return Ok(());
+                };
+                if target == UploadTarget::Route && upload.error.is_none() {
+                    upload.error = if self.route.len() < 2 {
+                        Some(BackendError::InvalidValue)
+                    } else {
+                        self.backend.save_route(&self.route).await.err()
+                    };
+                }
+                write_fmt(
+                    &mut self.tx,
+                    format_args!("{} saved {} skipped\r\n", upload.saved, upload.skipped),
+                )
+                .await?;
+                match upload.error {
+                    None => self.tx.write_all(b"OK\r\n").await,
+                    Some(e) => {
+                        write_fmt(&mut self.tx, format_args!("ERR {}\r\n", e.as_str())).await
+                    }
+                }
+            }
+        }
+    }
+
+    async fn list_waypoints(&mut self) -> Result<Result<(), BackendError>, W::Error> {
+        let tx = &mut self.tx;
+        let mut write_error = None;
+        let result = self
+            .backend
+            .waypoints(async |slot: u8, waypoint: &Waypoint| {
+                if write_error.is_some() {
+                    return;
+                }
+                if let Err(e) = write_fmt(
+                    tx,
+                    format_args!(
+                        "{} {} {:.7} {:.7} {:.1}\r\n",
+                        slot,
+                        waypoint.name,
+                        waypoint.position.latitude,
+                        waypoint.position.longitude,
+                        waypoint.position.altitude
+                    ),
+                )
+                .await
+                {
+                    write_error = Some(e);
+                }
+            })
+            .await;
+        match write_error {
+            Some(e) => Err(e),
+            None => Ok(result),
+        }
+    }
+
+    async fn dump_waypoints(&mut self) -> Result<Result<(), BackendError>, W::Error> {
+        let tx = &mut self.tx;
+        let mut write_error = None;
+        let mut gpx = GpxWriter::new(ChunkBuffer::new(), NaiveDate::default());
+        gpx.begin_waypoints();
+        drain(tx, gpx.get_mut()).await?;
+        let result = self
+            .backend
+            .waypoints(async |_slot: u8, waypoint: &Waypoint| {
+                if write_error.is_none() {
+                    gpx.waypoint(waypoint);
+                    if let Err(e) = drain(tx, gpx.get_mut()).await {
+                        write_error = Some(e);
+                    }
+                }
+            })
+            .await;
+        if let Ok(mut chunk) = gpx.finish() {
+            drain(tx, &mut chunk).await?;
+        }
+        match write_error {
+            Some(e) => Err(e),
+            None => Ok(result),
+        }
+    }
+
+    async fn route_info(&mut self) -> Result<Result<(), BackendError>, W::Error> {
+        let mut points = 0usize;
+        let mut length_m = 0.0;
+        let mut last: Option<LatLonAlt> = None;
+        let result = self
+            .backend
+            .route_points(async |point: &LatLonAlt| {
+                if let Some(last) = last {
+                    length_m += haversine_distance_m(last, *point);
+                }
+                last = Some(*point);
+                points += 1;
+            })
+            .await;
+        if result.is_ok() {
+            write_fmt(
+                &mut self.tx,
+                format_args!("{} points {:.0} m\r\n", points, length_m),
+            )
+            .await?;
+        }
+        Ok(result)
+    }
+
+    async fn dump_route(&mut self) -> Result<Result<(), BackendError>, W::Error> {
+        let tx = &mut self.tx;
+        let mut write_error = None;
+        let mut gpx = GpxWriter::new(ChunkBuffer::new(), NaiveDate::default());
+        gpx.begin_route();
+        drain(tx, gpx.get_mut()).await?;
+        let result = self
+            .backend
+            .route_points(async |point: &LatLonAlt| {
+                if write_error.is_none() {
+                    gpx.route_point(point);
+                    if let Err(e) = drain(tx, gpx.get_mut()).await {
+                        write_error = Some(e);
+                    }
+                }
+            })
+            .await;
+        if let Ok(mut chunk) = gpx.finish() {
+            drain(tx, &mut chunk).await?;
+        }
+        match write_error {
+            Some(e) => Err(e),
+            None => Ok(result),
+        }
+    }
+
+    async fn list(&mut self) -> Result<Result<(), BackendError>, W::Error> {
+        let tx = &mut self.tx;
+        let mut write_error = None;
+        let result = self
+            .backend
+            .sessions(async |summary: SessionSummary| {
+                if write_error.is_some() {
+                    return;
+                }
+                let state = if summary.closed { "" } else { " open" };
+                let written = match summary.power.period_secs() {
+                    0 => {
+                        write_fmt(
+                            tx,
+                            format_args!(
+                                "{} {} points{}\r\n",
+                                summary.session, summary.points, state
+                            ),
+                        )
+                        .await
+                    }
+                    // Duty-cycled: one fix every `period` seconds.
+                    period => {
+                        write_fmt(
+                            tx,
+                            format_args!(
+                                "{} {} points{} 1/{}s\r\n",
+                                summary.session, summary.points, state, period
+                            ),
+                        )
+                        .await
+                    }
+                };
+                if let Err(e) = written {
+                    write_error = Some(e);
+                }
+            })
+            .await;
+        match write_error {
+            Some(e) => Err(e),
+            None => Ok(result),
+        }
+    }
+
+    async fn dump(
+        &mut self,
+        session: u16,
+        format: DumpFormat,
+        date: Option<NaiveDate>,
+    ) -> Result<Result<(), BackendError>, W::Error> {
+        // A FIT header carries the size of what follows, which the log task
+        // would change under the two passes below.
+        if format == DumpFormat::Fit && self.backend.active_session().await == Some(session) {
+            return Ok(Err(BackendError::SessionActive));
+        }
+        let mut first_date = None;
+        let mut counter = PointCounter::default();
+        let found = self
+            .backend
+            .session_entries(session, async |entry: &SessionEntry| {
+                if let SessionEntry::Point(point) = entry {
+                    first_date = first_date.or(point.date);
+                }
+                entry.send_to(&mut counter);
+            })
+            .await;
+        match found {
+            Ok(true) => {}
+            Ok(false) => return Ok(Err(BackendError::NotFound)),
+            Err(e) => return Ok(Err(e)),
+        }
+        let date = date.or(first_date).unwrap_or_default();
+
+        let tx = &mut self.tx;
+        let mut write_error = None;
+        let result = match format {
+            DumpFormat::Gpx => {
+                let mut gpx = GpxWriter::new(ChunkBuffer::new(), date);
+                gpx.begin(session);
+                drain(tx, gpx.get_mut()).await?;
+                let result = self
+                    .backend
+                    .session_entries(session, async |entry: &SessionEntry| {
+                        if write_error.is_none() {
+                            match entry {
+                                SessionEntry::Point(point) => gpx.point(point),
+                                SessionEntry::Pause { .. } => gpx.pause(),
+                                SessionEntry::Resume { .. }
+                                | SessionEntry::Lap(_)
+                                | SessionEntry::Power(_) => {}
+                            }
+                            if let Err(e) = drain(tx, gpx.get_mut()).await {
+                                write_error = Some(e);
+                            }
+                        }
+                    })
+                    .await;
+                if let Ok(mut chunk) = gpx.finish() {
+                    drain(tx, &mut chunk).await?;
+                }
+                result
+            }
+            DumpFormat::Fit => {
+                let mut fit = FitWriter::new(ChunkBuffer::new(), date, &counter);
+                drain(tx, fit.get_mut()).await?;
+                let result = self
+                    .backend
+                    .session_entries(session, async |entry: &SessionEntry| {
+                        if write_error.is_none() {
+                            entry.send_to(&mut fit);
+                            if let Err(e) = drain(tx, fit.get_mut()).await {
+                                write_error = Some(e);
+                            }
+                        }
+                    })
+                    .await;
+                if let Ok(mut chunk) = fit.finish() {
+                    drain(tx, &mut chunk).await?;
+                }
+                result
+            }
+            DumpFormat::Csv => {
+                let mut csv = CsvWriter::new(ChunkBuffer::new());
+                csv.begin();
+                drain(tx, csv.get_mut()).await?;
+                let result = self
+                    .backend
+                    .session_points(session, async |point: &TrackPoint| {
+                        if write_error.is_none() {
+                            csv.point(point);
+                            if let Err(e) = drain(tx, csv.get_mut()).await {
+                                write_error = Some(e);
+                            }
+                        }
+                    })
+                    .await;
+                if let Ok(mut chunk) = csv.finish() {
+                    drain(tx, &mut chunk).await?;
+                }
+                result
+            }
+        };
+        match write_error {
+            Some(e) => Err(e),
+            None => Ok(result.map(|_| ())),
+        }
+    }
+}
+
+async fn write_fmt<W: Write>(tx: &mut W, args: core::fmt::Arguments<'_>) -> Result<(), W::Error> {
+    let mut text: String<128> = String::new();
+    let _ = text.write_fmt(args);
+    tx.write_all(text.as_bytes()).await
+}
+
+async fn drain<W: Write>(tx: &mut W, chunk: &mut ChunkBuffer) -> Result<(), W::Error> {
+    let result = tx.write_all(chunk.as_bytes()).await;
+    chunk.clear();
+    result
+}
+
+/// Fixed buffer the blocking export writers format into before the shell
+/// forwards the bytes to the async port.
+struct ChunkBuffer {
+    buf: Vec<u8, CHUNK_LEN>,
+}
+
+impl ChunkBuffer {
+    fn new() -> Self {
+        ChunkBuffer { buf: Vec::new() }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    fn clear(&mut self) {
+        self.buf.clear();
+    }
+}
+
+impl embedded_io::ErrorType for ChunkBuffer {
+    type Error = ErrorKind;
+}
+
+impl embedded_io::Write for ChunkBuffer {
+    fn write(&mut self, bytes: &[u8]) -> Result<usize, Self::Error> {
+        let n = bytes.len().min(CHUNK_LEN - self.buf.len());
+        if n == 0 && !bytes.is_empty() {
+            return Err(ErrorKind::OutOfMemory);
+        }
+        let _ = self.buf.extend_from_slice(&bytes[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use core::fmt::Write as _;
+
+use chrono::Datelike;
+use embedded_io::Write;
+use heapless::String;
+
+use crate::track::{log::SessionSink, record::TrackPoint};
+
+const CSV_HEADER: &str = "date,time_utc,lat,lon,alt_m,hdop\n";
+
+/// Streams a session as one CSV row per point. The date column is empty for
+/// points recorded before the receiver reported a date.
+pub struct CsvWriter<W: Write> {
+    out: W,
+    error: Option<W::Error>,
+}
+
+impl<W: Write> CsvWriter<W> {
+    pub fn new(out: W) -> Self {
+        CsvWriter { out, error: None }
+    }
+
+    pub fn begin(&mut self) {
+        self.write_str(CSV_HEADER);
+    }
+
+    pub fn point(&mut self, point: &TrackPoint) {
+        let secs = point.time_ms / 1000;
+        let mut chunk: String<96> = String::new();
+        if let Some(date) = point.date {
+            let _ = write!(
+                chunk,
+                "{:04}-{:02}-{:02}",
+                date.year(),
+                date.month(),
+                date.day()
+            );
+        }
+        let _ = writeln!(
+            chunk,
+            ",{:02}:{:02}:{:02}.{:03},{:.7},{:.7},{:.1},{:.1}",
+            secs / 3600,
+            (secs / 60) % 60,
+            secs % 60,
+            point.time_ms % 1000,
+            point.lat,
+            point.lon,
+            point.alt,
+            point.hdop,
+        );
+        self.write_str(&chunk);
+    }
+
+    pub fn finish(mut self) -> Result<W, W::Error> {
+        if self.error.is_none()
+            && let Err(e) = self.out.flush()
+        {
+            self.error = Some(e);
+        }
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.out),
+        }
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.out
+    }
+
+    fn write_str(&mut self, s: &str) {
+        if self.error.is_none()
+            && let Err(e) = self.out.write_all(s.as_bytes())
+        {
+            self.error = Some(e);
+        }
+    }
+}
+
+impl<W: Write> SessionSink for CsvWriter<W> {
+    fn point(&mut self, point: &TrackPoint) {
+        CsvWriter::point(self, point);
+    }
+}
@@ -0,0 +1,546 @@
+use chrono::NaiveDate;
+use embedded_io::Write;
+
+use crate::{
+    gps::{
+        laps::{Lap, LapKind},
+        stack::GeoStack,
+    },
+    track::{
+        log::{PointCounter, SessionSink},
+        record::TrackPoint,
+    },
+};
+
+const HEADER_SIZE: u8 = 14;
+const PROTOCOL_VERSION: u8 = 0x20;
+const PROFILE_VERSION: u16 = 2132;
+
+/// Seconds between the Unix epoch and the FIT epoch (1989-12-31T00:00:00Z).
+const FIT_EPOCH_OFFSET: i64 = 631_065_600;
+const SEMICIRCLES_PER_DEGREE: f64 = 2_147_483_648.0 / 180.0;
+
+const MESG_FILE_ID: u16 = 0;
+const MESG_SESSION: u16 = 18;
+const MESG_LAP: u16 = 19;
+const MESG_RECORD: u16 = 20;
+const MESG_EVENT: u16 = 21;
+const MESG_ACTIVITY: u16 = 34;
+
+const BASE_ENUM: u8 = 0x00;
+const BASE_UINT16: u8 = 0x84;
+const BASE_SINT32: u8 = 0x85;
+const BASE_UINT32: u8 = 0x86;
+const BASE_UINT32Z: u8 = 0x8C;
+
+const FILE_TYPE_ACTIVITY: u8 = 4;
+const MANUFACTURER_DEVELOPMENT: u16 = 255;
+const EVENT_TIMER: u8 = 0;
+const EVENT_SESSION: u8 = 8;
+const EVENT_LAP: u8 = 9;
+const EVENT_ACTIVITY: u8 = 26;
+const EVENT_TYPE_START: u8 = 0;
+const EVENT_TYPE_STOP: u8 = 1;
+const EVENT_TYPE_STOP_ALL: u8 = 4;
+const LAP_TRIGGER_MANUAL: u8 = 0;
+const LAP_TRIGGER_DISTANCE: u8 = 3;
+const LAP_TRIGGER_SESSION_END: u8 = 7;
+const SPORT_CYCLING: u8 = 2;
+
+const INVALID_UINT16: u16 = 0xFFFF;
+const INVALID_UINT32: u32 = 0xFFFF_FFFF;
+
+const CRC_TABLE: [u16; 16] = [
+    0x0000, 0xCC01, 0xD801, 0x1400, 0xF001, 0x3C00, 0x2800, 0xE401, 0xA001, 0x6C00, 0x7800, 0xB401,
+    0x5000, 0x9C01, 0x8801, 0x4400,
+];
+
+pub fn fit_crc(mut crc: u16, bytes: &[u8]) -> u16 {
+    for &byte in bytes {
+        let tmp = CRC_TABLE[(crc & 0xF) as usize];
+        crc = (crc >> 4) & 0x0FFF;
+        crc = crc ^ tmp ^ CRC_TABLE[(byte & 0xF) as usize];
+
+        let tmp = CRC_TABLE[(crc & 0xF) as usize];
+        crc = (crc >> 4) & 0x0FFF;
+        crc = crc ^ tmp ^ CRC_TABLE[((byte >> 4) & 0xF) as usize];
+    }
+    crc
+}
+
+/// (field definition number, size in bytes, base type)
+type FieldDef = (u8, u8, u8);
+
+struct MessageDef {
+    local: u8,
+    global: u16,
+    fields: &'static [FieldDef],
+}
+
+impl MessageDef {
+    /// Record header, reserved byte, architecture, global number and field
+    /// count, then three bytes per field.
+    const fn definition_len(&self) -> u32 {
+        6 + 3 * self.fields.len() as u32
+    }
+
+    const fn data_len(&self) -> u32 {
+        let mut len = 1;
+        let mut i = 0;
+        while i < self.fields.len() {
+            len += self.fields[i].1 as u32;
+            i += 1;
+        }
+        len
+    }
+}
+
+const FILE_ID: MessageDef = MessageDef {
+    local: 0,
+    global: MESG_FILE_ID,
+    fields: &[
+        (0, 1, BASE_ENUM),    // type
+        (1, 2, BASE_UINT16),  // manufacturer
+        (2, 2, BASE_UINT16),  // product
+        (3, 4, BASE_UINT32Z), // serial_number
+        (4, 4, BASE_UINT32),  // time_created
+    ],
+};
+
+const RECORD: MessageDef = MessageDef {
+    local: 1,
+    global: MESG_RECORD,
+    fields: &[
+        (253, 4, BASE_UINT32), // timestamp
+        (0, 4, BASE_SINT32),   // position_lat
+        (1, 4, BASE_SINT32),   // position_long
+        (2, 2, BASE_UINT16),   // altitude, 5 * (m + 500)
+        (5, 4, BASE_UINT32),   // distance, cm
+        (6, 2, BASE_UINT16),   // speed, mm/s
+    ],
+};
+
+const LAP: MessageDef = MessageDef {
+    local: 2,
+    global: MESG_LAP,
+    fields: &[
+        (253, 4, BASE_UINT32), // timestamp
+        (0, 1, BASE_ENUM),     // event
+        (1, 1, BASE_ENUM),     // event_type
+        (2, 4, BASE_UINT32),   // start_time
+        (7, 4, BASE_UINT32),   // total_elapsed_time, ms
+        (8, 4, BASE_UINT32),   // total_timer_time, ms
+        (9, 4, BASE_UINT32),   // total_distance, cm
+        (21, 2, BASE_UINT16),  // total_ascent, m
+        (22, 2, BASE_UINT16),  // total_descent, m
+        (24, 1, BASE_ENUM),    // lap_trigger
+    ],
+};
+
+const SESSION: MessageDef = MessageDef {
+    local: 3,
+    global: MESG_SESSION,
+    fields: &[
+        (253, 4, BASE_UINT32), // timestamp
+        (0, 1, BASE_ENUM),     // event
+        (1, 1, BASE_ENUM),     // event_type
+        (2, 4, BASE_UINT32),   // start_time
+        (5, 1, BASE_ENUM),     // sport
+        (7, 4, BASE_UINT32),   // total_elapsed_time, ms
+        (8, 4, BASE_UINT32),   // total_timer_time, ms
+        (9, 4, BASE_UINT32),   // total_distance, cm
+        (14, 2, BASE_UINT16),  // avg_speed, mm/s
+        (15, 2, BASE_UINT16),  // max_speed, mm/s
+        (22, 2, BASE_UINT16),  // total_ascent, m
+        (23, 2, BASE_UINT16),  // total_descent, m
+        (25, 2, BASE_UINT16),  // first_lap_index
+        (26, 2, BASE_UINT16),  // num_laps
+    ],
+};
+
+const EVENT: MessageDef = MessageDef {
+    local: 5,
+    global: MESG_EVENT,
+    fields: &[
+        (253, 4, BASE_UINT32), // timestamp
+        (0, 1, BASE_ENUM),     // event
+        (1, 1, BASE_ENUM),     // event_type
+    ],
+};
+
+const ACTIVITY: MessageDef = MessageDef {
+    local: 4,
+    global: MESG_ACTIVITY,
+    fields: &[
+        (253, 4, BASE_UINT32), // timestamp
+        (0, 4, BASE_UINT32),   // total_timer_time, ms
+        (1, 2, BASE_UINT16),   // num_sessions
+        (2, 1, BASE_ENUM),     // type
+        (3, 1, BASE_ENUM),     // event
+        (4, 1, BASE_ENUM),     // event_type
+    ],
+};
+
+/// Size of the data section for a session counted by `counter`.
+pub fn fit_data_size(counter: &PointCounter) -> u32 {
+    // A lap closes whatever follows the last lap marker, or the whole
+    // session when there is none.
+    let final_lap = u32::from(counter.open_lap_points > 0 || counter.laps == 0);
+    FILE_ID.definition_len()
+        + FILE_ID.data_len()
+        + EVENT.definition_len()
+        + EVENT.data_len() * (counter.markers + 2)
+        + RECORD.definition_len()
+        + RECORD.data_len() * counter.count
+        + LAP.definition_len()
+        + LAP.data_len() * (counter.laps + final_lap)
+        + SESSION.definition_len()
+        + SESSION.data_len()
+        + ACTIVITY.definition_len()
+        + ACTIVITY.data_len()
+}
+
+/// Where the lap in progress started.
+#[derive(Copy, Clone, Default)]
+struct LapStart {
+    time_ms: u64,
+    paused_ms: u64,
+    distance_m: f64,
+    gain_m: f32,
+    loss_m: f32,
+    points: u32,
+}
+
+/// Streams a session as a FIT activity file.
+///
+/// FIT puts the data size in the file header, so the caller counts the
+/// session first with a `PointCounter` and passes it in; the writer must
+/// then see exactly the same entries. Distance and speed are recomputed by
+/// replaying the points through a `GeoStack`, the same way they were shown
+/// while recording. Auto-pauses stop the timer, so timer times leave them
+/// out. Each lap marker ends a lap there; whatever follows the last one
+/// becomes a final lap.
+pub struct FitWriter<W: Write> {
+    out: W,
+    crc: u16,
+    error: Option<W::Error>,
+    /// FIT timestamp of midnight on the day being written.
+    day_start: u32,
+    last_time_ms: Option<u32>,
+    /// Times below are milliseconds since the FIT epoch.
+    start_ms: Option<u64>,
+    last_ms: u64,
+    paused_since: Option<u64>,
+    paused_ms: u64,
+    lap: LapStart,
+    laps: u16,
+    max_speed_mps: f64,
+    geo_stack: GeoStack,
+}
+
+impl<W: Write> FitWriter<W> {
+    /// `date` is only used for points recorded without a receiver date.
+    pub fn new(out: W, date: NaiveDate, counter: &PointCounter) -> Self {
+        let day_start = fit_day_start(date);
+        let mut writer = FitWriter {
+            out,
+            crc: 0,
+            error: None,
+            day_start,
+            last_time_ms: None,
+            start_ms: None,
+            last_ms: u64::from(day_start) * 1000,
+            paused_since: None,
+            paused_ms: 0,
+            lap: LapStart::default(),
+            laps: 0,
+            max_speed_mps: 0.0,
+            geo_stack: GeoStack::new(),
+        };
+        writer.write_header(fit_data_size(counter));
+        writer
+    }
+
+    pub fn point(&mut self, point: &TrackPoint) {
+        let time_ms = self.time_ms(point.time_ms, point.date);
+        self.lap.points += 1;
+
+        self.geo_stack.add_coords(point.to_results(), true);
+        let speed_mps = self.geo_stack.current_speed_mps;
+        if speed_mps > self.max_speed_mps {
+            self.max_speed_mps = speed_mps;
+        }
+
+        self.put(&[RECORD.local]);
+        self.put(&fit_seconds(time_ms).to_le_bytes());
+        self.put(&to_semicircles(point.lat).to_le_bytes());
+        self.put(&to_semicircles(point.lon).to_le_bytes());
+        self.put(&scale_u16((point.alt as f64 + 500.0) * 5.0).to_le_bytes());
+        self.put(&self.distance_cm().to_le_bytes());
+        self.put(&scale_u16(speed_mps * 1000.0).to_le_bytes());
+    }
+
+    /// Stops the timer at an auto-pause.
+    pub fn pause(&mut self, time_ms: u32) {
+        let time_ms = self.time_ms(time_ms, None);
+        if self.paused_since.is_none() {
+            self.paused_since = Some(time_ms);
+        }
+        self.write_event(time_ms, EVENT_TYPE_STOP_ALL);
+    }
+
+    /// Restarts the timer when moving again.
+    pub fn resume(&mut self, time_ms: u32) {
+        let time_ms = self.time_ms(time_ms, None);
+        self.close_pause(time_ms);
+        self.paused_since = None;
+        self.write_event(time_ms, EVENT_TYPE_START);
+    }
+
+    /// Ends a lap at the latest point. Its totals come from the replay, so
+    /// the laps add up to the session.
+    pub fn lap(&mut self, lap: &Lap) {
+        if self.start_ms.is_none() {
+            self.begin(self.last_ms);
+        }
+        let trigger = match lap.kind {
+            LapKind::Manual => LAP_TRIGGER_MANUAL,
+            LapKind::Split => LAP_TRIGGER_DISTANCE,
+        };
+        self.write_lap(self.last_ms, trigger);
+    }
+
+    /// Writes the final lap, the session and activity summaries and the
+    /// file CRC, reporting the first write error, if any.
+    pub fn finish(mut self) -> Result<W, W::Error> {
+        if self.start_ms.is_none() {
+            self.begin(self.last_ms);
+        }
+        let start_ms = self.start_ms.unwrap_or(self.last_ms);
+        let end_ms = self.last_ms.max(start_ms);
+        if self.lap.points > 0 || self.laps == 0 {
+            self.write_lap(end_ms, LAP_TRIGGER_SESSION_END);
+        }
+        self.close_pause(end_ms);
+        self.write_event(end_ms, EVENT_TYPE_STOP_ALL);
+
+        let start_time = fit_seconds(start_ms);
+        let end_time = fit_seconds(end_ms);
+        let elapsed_ms = clamp_u32(end_ms - start_ms);
+        let timer_ms = clamp_u32((end_ms - start_ms).saturating_sub(self.paused_ms));
+        let distance_cm = self.distance_cm();
+        let ascent = scale_u16(self.geo_stack.elevation.total_gain as f64);
+        let descent = scale_u16(self.geo_stack.elevation.total_loss as f64);
+        let avg_speed = if timer_ms > 0 {
+            scale_u16(distance_cm as f64 * 10.0 / timer_ms as f64 * 1000.0)
+        } else {
+            0
+        };
+        let max_speed = scale_u16(self.max_speed_mps * 1000.0);
+
+        self.write_definition(&SESSION);
+        self.put(&[SESSION.local]);
+        self.put(&end_time.to_le_bytes());
+        self.put(&[EVENT_SESSION, EVENT_TYPE_STOP]);
+        self.put(&start_time.to_le_bytes());
+        self.put(&[SPORT_CYCLING]);
+        self.put(&elapsed_ms.to_le_bytes());
+        self.put(&timer_ms.to_le_bytes());
+        self.put(&distance_cm.to_le_bytes());
+        self.put(&avg_speed.to_le_bytes());
+        self.put(&max_speed.to_le_bytes());
+        self.put(&ascent.to_le_bytes());
+        self.put(&descent.to_le_bytes());
+        self.put(&0u16.to_le_bytes());
+        self.put(&self.laps.to_le_bytes());
+
+        self.write_definition(&ACTIVITY);
+        self.put(&[ACTIVITY.local]);
+        self.put(&end_time.to_le_bytes());
+        self.put(&timer_ms.to_le_bytes());
+        self.put(&1u16.to_le_bytes());
+        self.put(&[0, EVENT_ACTIVITY, EVENT_TYPE_STOP]);
+
+        let crc = self.crc;
+        self.put(&crc.to_le_bytes());
+        if self.error.is_none()
+            && let Err(e) = self.out.flush()
+        {
+            self.error = Some(e);
+        }
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.out),
+        }
+    }
+
+    /// Definitions for everything written mid-session, and the timer start.
+    fn begin(&mut self, time_ms: u64) {
+        self.start_ms = Some(time_ms);
+        self.last_ms = time_ms;
+        self.lap.time_ms = time_ms;
+        self.write_definition(&FILE_ID);
+        self.put(&[FILE_ID.local, FILE_TYPE_ACTIVITY]);
+        self.put(&MANUFACTURER_DEVELOPMENT.to_le_bytes());
+        self.put(&0u16.to_le_bytes());
+        self.put(&1u32.to_le_bytes());
+        self.put(&fit_seconds(time_ms).to_le_bytes());
+        self.write_definition(&EVENT);
+        self.write_event(time_ms, EVENT_TYPE_START);
+        self.write_definition(&RECORD);
+        self.write_definition(&LAP);
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.out
+    }
+
+    /// Milliseconds since the FIT epoch for a time of day, rolling over to
+    /// the next day when an undated time goes backwards.
+    fn time_ms(&mut self, time_ms: u32, date: Option<NaiveDate>) -> u64 {
+        if let Some(date) = date {
+            self.day_start = fit_day_start(date);
+        } else if let Some(last) = self.last_time_ms
+            && time_ms < last
+        {
+            self.day_start += 86_400;
+        }
+        self.last_time_ms = Some(time_ms);
+        let time_ms = u64::from(self.day_start) * 1000 + u64::from(time_ms);
+        if self.start_ms.is_none() {
+            self.begin(time_ms);
+        }
+        self.last_ms = self.last_ms.max(time_ms);
+        time_ms
+    }
+
+    /// Counts a pause still running at `time_ms` up to there, in the
+    /// session and in the lap.
+    fn close_pause(&mut self, time_ms: u64) {
+        if let Some(since) = self.paused_since {
+            let paused_ms = time_ms.saturating_sub(since);
+            self.paused_ms += paused_ms;
+            self.lap.paused_ms += paused_ms;
+            self.paused_since = Some(since.max(time_ms));
+        }
+    }
+
+    fn write_lap(&mut self, end_ms: u64, trigger: u8) {
+        self.close_pause(end_ms);
+        let lap = self.lap;
+        let elapsed_ms = end_ms.saturating_sub(lap.time_ms);
+        let distance_m = self.geo_stack.total_distance - lap.distance_m;
+        let gain_m = self.geo_stack.elevation.total_gain - lap.gain_m;
+        let loss_m = self.geo_stack.elevation.total_loss - lap.loss_m;
+
+        self.put(&[LAP.local]);
+        self.put(&fit_seconds(end_ms).to_le_bytes());
+        self.put(&[EVENT_LAP, EVENT_TYPE_STOP]);
+        self.put(&fit_seconds(lap.time_ms).to_le_bytes());
+        self.put(&clamp_u32(elapsed_ms).to_le_bytes());
+        self.put(&clamp_u32(elapsed_ms.saturating_sub(lap.paused_ms)).to_le_bytes());
+        self.put(&clamp_u32((distance_m * 100.0) as u64).to_le_bytes());
+        self.put(&scale_u16(gain_m as f64).to_le_bytes());
+        self.put(&scale_u16(loss_m as f64).to_le_bytes());
+        self.put(&[trigger]);
+
+        self.laps += 1;
+        self.lap = LapStart {
+            time_ms: end_ms,
+            paused_ms: 0,
+            distance_m: self.geo_stack.total_distance,
+            gain_m: self.geo_stack.elevation.total_gain,
+            loss_m: self.geo_stack.elevation.total_loss,
+            points: 0,
+        };
+    }
+
+    fn write_event(&mut self, time_ms: u64, event_type: u8) {
+        self.put(&[EVENT.local]);
+        self.put(&fit_seconds(time_ms).to_le_bytes());
+        self.put(&[EVENT_TIMER, event_type]);
+    }
+
+    fn distance_cm(&self) -> u32 {
+        clamp_u32((self.geo_stack.total_distance * 100.0) as u64)
+    }
+
+    fn write_header(&mut self, data_size: u32) {
+        let mut header = [0u8; HEADER_SIZE as usize];
+        header[0] = HEADER_SIZE;
+        header[1] = PROTOCOL_VERSION;
+        header[2..4].copy_from_slice(&PROFILE_VERSION.to_le_bytes());
+        header[4..8].copy_from_slice(&data_size.to_le_bytes());
+        header[8..12].copy_from_slice(b".FIT");
+        let header_crc = fit_crc(0, &header[..12]);
+        header[12..14].copy_from_slice(&header_crc.to_le_bytes());
+        self.put(&header);
+    }
+
+    fn write_definition(&mut self, def: &MessageDef) {
+        self.put(&[0x40 | def.local, 0, 0]);
+        self.put(&def.global.to_le_bytes());
+        self.put(&[def.fields.len() as u8]);
+        for &(num, size, base) in def.fields {
+            self.put(&[num, size, base]);
+        }
+    }
+
+    fn put(&mut self, bytes: &[u8]) {
+        self.crc = fit_crc(self.crc, bytes);
+        if self.error.is_none()
+            && let Err(e) = self.out.write_all(bytes)
+        {
+            self.error = Some(e);
+        }
+    }
+}
+
+impl<W: Write> SessionSink for FitWriter<W> {
+    fn point(&mut self, point: &TrackPoint) {
+        FitWriter::point(self, point);
+    }
+
+    fn pause(&mut self, time_ms: u32) {
+        FitWriter::pause(self, time_ms);
+    }
+
+    fn resume(&mut self, time_ms: u32) {
+        FitWriter::resume(self, time_ms);
+    }
+
+    fn lap(&mut self, lap: &Lap) {
+        FitWriter::lap(self, lap);
+    }
+}
+
+/// FIT timestamp of midnight UTC on `date`.
+fn fit_day_start(date: NaiveDate) -> u32 {
+    date.and_hms_opt(0, 0, 0)
+        .map(|dt| dt.and_utc().timestamp() - FIT_EPOCH_OFFSET)
+        .unwrap_or_default()
+        .max(0) as u32
+}
+
+fn fit_seconds(time_ms: u64) -> u32 {
+    clamp_u32(time_ms / 1000)
+}
+
+/// Saturates below the invalid value.
+fn clamp_u32(value: u64) -> u32 {
+    value.min(u64::from(INVALID_UINT32 - 1)) as u32
+}
+
+fn to_semicircles(degrees: f64) -> i32 {
+    (degrees * SEMICIRCLES_PER_DEGREE) as i32
+}
+
+fn scale_u16(value: f64) -> u16 {
+    if value < 0.0 {
+        0
+    } else if value >= INVALID_UINT16 as f64 {
+        INVALID_UINT16 - 1
+    } else {
+        value as u16
+    }
+}
@@ -0,0 +1,185 @@
+use core::fmt::Write as _;
+
+use chrono::{Datelike, NaiveDate};
+use embedded_io::Write;
+use heapless::String;
+
+use crate::{
+    gps::{fns::LatLonAlt, waypoint::Waypoint},
+    track::{
+        log::SessionSink,
+        record::{MS_PER_DAY, TrackPoint},
+    },
+};
+
+const GPX_HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+<gpx version=\"1.1\" creator=\"HiJo\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n";
+const GPX_FOOTER: &str = "</gpx>\n";
+
+/// Streams a session as a GPX 1.1 track.
+///
+/// Each element is formatted into a small stack buffer and written to the
+/// sink before the next one is built, so a whole session never has to be in
+/// RAM. Points carry their own UTC date; `date` only covers points recorded
+/// before the receiver reported one, which roll over to the next day when
+/// their time of day goes backwards. Each auto-pause ends the current
+/// `<trkseg>`; the next point starts a new one. Waypoints and routes go in
+/// documents of their own, started with `begin_waypoints` and
+/// `begin_route`.
+pub struct GpxWriter<W: Write> {
+    out: W,
+    date: NaiveDate,
+    last_time_ms: Option<u32>,
+    in_track: bool,
+    in_route: bool,
+    in_segment: bool,
+    error: Option<W::Error>,
+}
+
+impl<W: Write> GpxWriter<W> {
+    pub fn new(out: W, date: NaiveDate) -> Self {
+        GpxWriter {
+            out,
+            date,
+            last_time_ms: None,
+            in_track: false,
+            in_route: false,
+            in_segment: false,
+            error: None,
+        }
+    }
+
+    pub fn begin(&mut self, session: u16) {
+        self.write_str(GPX_HEADER);
+        let mut chunk: String<64> = String::new();
+        let _ = writeln!(chunk, "<trk>\n<name>HiJo session {}</name>", session);
+        self.write_str(&chunk);
+        self.in_track = true;
+    }
+
+    pub fn begin_waypoints(&mut self) {
+        self.write_str(GPX_HEADER);
+    }
+
+    pub fn begin_route(&mut self) {
+        self.write_str(GPX_HEADER);
+        self.write_str("<rte>\n<name>HiJo route</name>\n");
+        self.in_route = true;
+    }
+
+    pub fn route_point(&mut self, point: &LatLonAlt) {
+        let mut chunk: String<96> = String::new();
+        let _ = writeln!(
+            chunk,
+            "<rtept lat=\"{:.7}\" lon=\"{:.7}\"><ele>{:.1}</ele></rtept>",
+            point.latitude, point.longitude, point.altitude,
+        );
+        self.write_str(&chunk);
+    }
+
+    pub fn waypoint(&mut self, waypoint: &Waypoint) {
+        let mut chunk: String<160> = String::new();
+        let _ = write!(
+            chunk,
+            "<wpt lat=\"{:.7}\" lon=\"{:.7}\"><ele>{:.1}</ele><name>",
+            waypoint.position.latitude, waypoint.position.longitude, waypoint.position.altitude,
+        );
+        for c in waypoint.name.chars() {
+            let _ = match c {
+                '&' => chunk.push_str("&amp;"),
+                '<' => chunk.push_str("&lt;"),
+                '>' => chunk.push_str("&gt;"),
+                '"' => chunk.push_str("&quot;"),
+                _ => chunk.push(c),
+            };
+        }
+        let _ = chunk.push_str("</name></wpt>\n");
+        self.write_str(&chunk);
+    }
+
+    pub fn point(&mut self, point: &TrackPoint) {
+        if let Some(date) = point.date {
+            self.date = date;
+        } else if let Some(last) = self.last_time_ms
+            && point.time_ms < last
+            && let Some(next_day) = self.date.succ_opt()
+        {
+            self.date = next_day;
+        }
+        self.last_time_ms = Some(point.time_ms);
+        if !self.in_segment {
+            self.write_str("<trkseg>\n");
+            self.in_segment = true;
+        }
+
+        let time_ms = point.time_ms % MS_PER_DAY;
+        let secs = time_ms / 1000;
+        let mut chunk: String<160> = String::new();
+        let _ = writeln!(
+            chunk,
+            "<trkpt lat=\"{:.7}\" lon=\"{:.7}\"><ele>{:.1}</ele>\
+<time>{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z</time></trkpt>",
+            point.lat,
+            point.lon,
+            point.alt,
+            self.date.year(),
+            self.date.month(),
+            self.date.day(),
+            secs / 3600,
+            (secs / 60) % 60,
+            secs % 60,
+            time_ms % 1000,
+        );
+        self.write_str(&chunk);
+    }
+
+    pub fn pause(&mut self) {
+        if self.in_segment {
+            self.write_str("</trkseg>\n");
+            self.in_segment = false;
+        }
+    }
+
+    /// Closes the document and reports the first write error, if any.
+    pub fn finish(mut self) -> Result<W, W::Error> {
+        self.pause();
+        if self.in_track {
+            self.write_str("</trk>\n");
+        }
+        if self.in_route {
+            self.write_str("</rte>\n");
+        }
+        self.write_str(GPX_FOOTER);
+        if self.error.is_none()
+            && let Err(e) = self.out.flush()
+        {
+            self.error = Some(e);
+        }
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.out),
+        }
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.out
+    }
+
+    fn write_str(&mut self, s: &str) {
+        if self.error.is_none()
+            && let Err(e) = self.out.write_all(s.as_bytes())
+        {
+            self.error = Some(e);
+        }
+    }
+}
+
+impl<W: Write> SessionSink for GpxWriter<W> {
+    fn point(&mut self, point: &TrackPoint) {
+        GpxWriter::point(self, point);
+    }
+
+    fn pause(&mut self, _time_ms: u32) {
+        GpxWriter::pause(self);
+    }
+}
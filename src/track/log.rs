@@ -0,0 +1,425 @@
+use core::ops::Range;
+
+use embedded_storage_async::nor_flash::MultiwriteNorFlash;
+use heapless::Vec;
+use sequential_storage::{
+    cache::{Cache, Uncached},
+    queue::{QueueConfig, QueueStorage},
+};
+
+use crate::{
+    gps::{laps::Lap, power::PowerProfile},
+    track::record::{MAX_RECORD_LEN, RecordError, TrackPoint, TrackRecord},
+};
+
+pub type TrackCache = Cache<Uncached, Uncached, Uncached, ()>;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TrackLogError<E> {
+    Storage(sequential_storage::Error<E>),
+    Record(RecordError),
+    SessionActive,
+}
+
+impl<E> From<sequential_storage::Error<E>> for TrackLogError<E> {
+    fn from(err: sequential_storage::Error<E>) -> Self {
+        TrackLogError::Storage(err)
+    }
+}
+
+impl<E> From<RecordError> for TrackLogError<E> {
+    fn from(err: RecordError) -> Self {
+        TrackLogError::Record(err)
+    }
+}
+
+/// Messages sent from the main loop to the task that owns the track log.
+#[derive(Debug, Copy, Clone)]
+pub enum TrackEvent {
+    Start,
+    Point(TrackPoint),
+    Pause(u32),
+    Resume(u32),
+    Lap(Lap),
+    Power(PowerProfile),
+    Stop,
+    /// Writes nothing. Events are handled in order, so once this one is
+    /// handled everything sent before it is on flash.
+    Flush,
+}
+
+/// One entry of a recorded session, as read back for export.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SessionEntry {
+    Point(TrackPoint),
+    Pause { time_ms: u32 },
+    Resume { time_ms: u32 },
+    Lap(Lap),
+    Power(PowerProfile),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SessionSummary {
+    pub session: u16,
+    pub points: u32,
+    pub closed: bool,
+    /// Power profile the session was last recorded with.
+    pub power: PowerProfile,
+}
+
+/// Receives the points of a session as they are read back from flash.
+pub trait SessionSink {
+    fn point(&mut self, point: &TrackPoint);
+
+    fn pause(&mut self, _time_ms: u32) {}
+
+    fn resume(&mut self, _time_ms: u32) {}
+
+    fn lap(&mut self, _lap: &Lap) {}
+
+    fn power(&mut self, _profile: PowerProfile) {}
+}
+
+impl SessionEntry {
+    /// Hands the entry to the matching `SessionSink` method.
+    pub fn send_to<K: SessionSink>(&self, sink: &mut K) {
+        match self {
+            SessionEntry::Point(point) => sink.point(point),
+            SessionEntry::Pause { time_ms } => sink.pause(*time_ms),
+            SessionEntry::Resume { time_ms } => sink.resume(*time_ms),
+            SessionEntry::Lap(lap) => sink.lap(lap),
+            SessionEntry::Power(profile) => sink.power(*profile),
+        }
+    }
+}
+
+/// Counts the points, markers and laps of a session without keeping them,
+/// for exports that need their size up front.
+#[derive(Debug, Default)]
+pub struct PointCounter {
+    pub count: u32,
+    /// Auto-pause and resume markers.
+    pub markers: u32,
+    pub laps: u32,
+    /// Points after the last lap marker.
+    pub open_lap_points: u32,
+}
+
+impl SessionSink for PointCounter {
+    fn point(&mut self, _point: &TrackPoint) {
+        self.count += 1;
+        self.open_lap_points += 1;
+    }
+
+    fn pause(&mut self, _time_ms: u32) {
+        self.markers += 1;
+    }
+
+    fn resume(&mut self, _time_ms: u32) {
+        self.markers += 1;
+    }
+
+    fn lap(&mut self, _lap: &Lap) {
+        self.laps += 1;
+        self.open_lap_points = 0;
+    }
+}
+
+/// Append-only session log kept in a `sequential-storage` queue.
+///
+/// A session is every `Point` record between a `SessionStart` and its
+/// matching `SessionEnd`. When the region fills up the oldest records are
+/// overwritten.
+pub struct TrackLog<S: MultiwriteNorFlash> {
+    queue: QueueStorage<S, TrackCache>,
+    last_session: u16,
+    active_session: Option<u16>,
+}
+
+impl<S: MultiwriteNorFlash> TrackLog<S> {
+    /// Opens the log and closes any session left open by a reset.
+    pub async fn new(flash: S, flash_range: Range<u32>) -> Result<Self, TrackLogError<S::Error>> {
+        let queue = QueueStorage::new(flash, QueueConfig::new(flash_range), Cache::new_uncached());
+        let mut log = TrackLog {
+            queue,
+            last_session: 0,
+            active_session: None,
+        };
+
+        let mut open_session = None;
+        let mut buf = [0u8; MAX_RECORD_LEN];
+        let mut iter = log.queue.iter().await?;
+        while let Some(entry) = iter.next(&mut buf).await? {
+            match TrackRecord::decode(&entry) {
+                Ok(TrackRecord::SessionStart { session }) => {
+                    log.last_session = session;
+                    open_session = Some(session);
+                }
+                Ok(TrackRecord::SessionEnd { .. }) => open_session = None,
+                _ => {}
+            }
+        }
+
+        if let Some(session) = open_session {
+            log.push(&TrackRecord::SessionEnd { session }).await?;
+        }
+
+        Ok(log)
+    }
+
+    pub fn active_session(&self) -> Option<u16> {
+        self.active_session
+    }
+
+    pub fn last_session(&self) -> u16 {
+        self.last_session
+    }
+
+    pub async fn start_session(&mut self) -> Result<u16, TrackLogError<S::Error>> {
+        if let Some(session) = self.active_session {
+            return Ok(session);
+        }
+        let session = self.last_session.wrapping_add(1);
+        self.push(&TrackRecord::SessionStart { session }).await?;
+        self.last_session = session;
+        self.active_session = Some(session);
+        Ok(session)
+    }
+
+    /// Points that arrive while no session is open are dropped.
+    pub async fn push_point(&mut self, point: &TrackPoint) -> Result<(), TrackLogError<S::Error>> {
+        if self.active_session.is_some() {
+            self.push(&TrackRecord::Point(*point)).await?;
+        }
+        Ok(())
+    }
+
+    /// Records an auto-pause, lap or power marker in the open session, if any.
+    pub async fn push_marker(
+        &mut self,
+        record: TrackRecord,
+    ) -> Result<(), TrackLogError<S::Error>> {
+        if self.active_session.is_some() {
+            self.push(&record).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn end_session(&mut self) -> Result<Option<u16>, TrackLogError<S::Error>> {
+        let Some(session) = self.active_session else {
+            return Ok(None);
+        };
+        self.push(&TrackRecord::SessionEnd { session }).await?;
+        self.active_session = None;
+        Ok(Some(session))
+    }
+
+    pub async fn handle(&mut self, event: TrackEvent) -> Result<(), TrackLogError<S::Error>> {
+        match event {
+            TrackEvent::Start => self.start_session().await.map(|_| ()),
+            TrackEvent::Point(point) => self.push_point(&point).await,
+            TrackEvent::Pause(time_ms) => self.push_marker(TrackRecord::Pause { time_ms }).await,
+            TrackEvent::Resume(time_ms) => self.push_marker(TrackRecord::Resume { time_ms }).await,
+            TrackEvent::Lap(lap) => self.push_marker(TrackRecord::Lap(lap)).await,
+            TrackEvent::Power(profile) => self.push_marker(TrackRecord::Power(profile)).await,
+            TrackEvent::Stop => self.end_session().await.map(|_| ()),
+            TrackEvent::Flush => Ok(()),
+        }
+    }
+
+    /// Calls `f` once per session still in the log, oldest first.
+    pub async fn sessions(
+        &mut self,
+        mut f: impl AsyncFnMut(SessionSummary),
+    ) -> Result<(), TrackLogError<S::Error>> {
+        let mut current: Option<SessionSummary> = None;
+        let mut buf = [0u8; MAX_RECORD_LEN];
+        let mut iter = self.queue.iter().await?;
+        while let Some(entry) = iter.next(&mut buf).await? {
+            match TrackRecord::decode(&entry) {
+                Ok(TrackRecord::SessionStart { session }) => {
+                    if let Some(summary) = current.take() {
+                        f(summary).await;
+                    }
+                    current = Some(SessionSummary {
+                        session,
+                        points: 0,
+                        closed: false,
+                        power: PowerProfile::Continuous,
+                    });
+                }
+                Ok(TrackRecord::Point(_)) => {
+                    if let Some(summary) = current.as_mut() {
+                        summary.points += 1;
+                    }
+                }
+                Ok(TrackRecord::Power(profile)) => {
+                    if let Some(summary) = current.as_mut() {
+                        summary.power = profile;
+                    }
+                }
+                Ok(TrackRecord::SessionEnd { session }) => {
+                    if let Some(mut summary) = current.take() {
+                        summary.closed = summary.session == session;
+                        f(summary).await;
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some(summary) = current {
+            f(summary).await;
+        }
+        Ok(())
+    }
+
+    /// Copies the summaries after the first `skip` into `batch`, as many as
+    /// fit. A batch that comes back short is the last one.
+    pub async fn sessions_batch<const N: usize>(
+        &mut self,
+        skip: usize,
+        batch: &mut Vec<SessionSummary, N>,
+    ) -> Result<(), TrackLogError<S::Error>> {
+        batch.clear();
+        let mut seen = 0;
+        self.sessions(async |summary| {
+            if seen >= skip {
+                let _ = batch.push(summary);
+            }
+            seen += 1;
+        })
+        .await
+    }
+
+    /// Calls `f` with every point and marker of `session`, oldest first.
+    /// Returns `false` when the session is not in the log.
+    pub async fn for_each_entry(
+        &mut self,
+        session: u16,
+        mut f: impl AsyncFnMut(&SessionEntry),
+    ) -> Result<bool, TrackLogError<S::Error>> {
+        let mut found = false;
+        let mut in_session = false;
+        let mut buf = [0u8; MAX_RECORD_LEN];
+        let mut iter = self.queue.iter().await?;
+        while let Some(entry) = iter.next(&mut buf).await? {
+            let item = match TrackRecord::decode(&entry) {
+                Ok(TrackRecord::SessionStart { session: id }) => {
+                    if in_session {
+                        break;
+                    }
+                    if id == session {
+                        found = true;
+                        in_session = true;
+                    }
+                    continue;
+                }
+                Ok(TrackRecord::SessionEnd { session: id }) => {
+                    if in_session && id == session {
+                        break;
+                    }
+                    continue;
+                }
+                Ok(TrackRecord::Point(point)) => SessionEntry::Point(point),
+                Ok(TrackRecord::Pause { time_ms }) => SessionEntry::Pause { time_ms },
+                Ok(TrackRecord::Resume { time_ms }) => SessionEntry::Resume { time_ms },
+                Ok(TrackRecord::Lap(lap)) => SessionEntry::Lap(lap),
+                Ok(TrackRecord::Power(profile)) => SessionEntry::Power(profile),
+                Err(_) => continue,
+            };
+            if in_session {
+                f(&item).await;
+            }
+        }
+        Ok(found)
+    }
+
+    /// Copies the entries of `session` after the first `skip` into `batch`,
+    /// as many as fit, so a log shared behind a lock can be read out without
+    /// holding it while each batch is written somewhere slow. A batch that
+    /// comes back short is the last one. Returns `false` when the session is
+    /// not in the log.
+    pub async fn entries_batch<const N: usize>(
+        &mut self,
+        session: u16,
+        skip: usize,
+        batch: &mut Vec<SessionEntry, N>,
+    ) -> Result<bool, TrackLogError<S::Error>> {
+        batch.clear();
+        let mut seen = 0;
+        self.for_each_entry(session, async |entry: &SessionEntry| {
+            if seen >= skip {
+                let _ = batch.push(*entry);
+            }
+            seen += 1;
+        })
+        .await
+    }
+
+    /// Calls `f` with every point of `session`, oldest first. Returns
+    /// `false` when the session is not in the log.
+    pub async fn for_each_point(
+        &mut self,
+        session: u16,
+        mut f: impl AsyncFnMut(&TrackPoint),
+    ) -> Result<bool, TrackLogError<S::Error>> {
+        self.for_each_entry(session, async |entry: &SessionEntry| {
+            if let SessionEntry::Point(point) = entry {
+                f(point).await;
+            }
+        })
+        .await
+    }
+
+    /// Feeds every point and marker of `session` to `sink`, oldest first.
+    pub async fn read_session<K: SessionSink>(
+        &mut self,
+        session: u16,
+        sink: &mut K,
+    ) -> Result<bool, TrackLogError<S::Error>> {
+        self.for_each_entry(session, async |entry: &SessionEntry| entry.send_to(sink))
+            .await
+    }
+
+    /// Removes every record of `session`. The session being recorded cannot
+    /// be deleted.
+    pub async fn delete_session(&mut self, session: u16) -> Result<bool, TrackLogError<S::Error>> {
+        if self.active_session == Some(session) {
+            return Err(TrackLogError::SessionActive);
+        }
+        let mut found = false;
+        let mut in_session = false;
+        let mut buf = [0u8; MAX_RECORD_LEN];
+        let mut iter = self.queue.iter().await?;
+        while let Some(entry) = iter.next(&mut buf).await? {
+            let remove = match TrackRecord::decode(&entry) {
+                Ok(TrackRecord::SessionStart { session: id }) => {
+                    in_session = id == session;
+                    found |= in_session;
+                    in_session
+                }
+                Ok(TrackRecord::SessionEnd { .. }) => core::mem::take(&mut in_session),
+                Ok(
+                    TrackRecord::Point(_)
+                    | TrackRecord::Pause { .. }
+                    | TrackRecord::Resume { .. }
+                    | TrackRecord::Lap(_)
+                    | TrackRecord::Power(_),
+                ) => in_session,
+                Err(_) => false,
+            };
+            if remove {
+                entry.pop().await?;
+            }
+        }
+        Ok(found)
+    }
+
+    async fn push(&mut self, record: &TrackRecord) -> Result<(), TrackLogError<S::Error>> {
+        let mut buf = [0u8; MAX_RECORD_LEN];
+        let bytes = record.encode(&mut buf)?;
+        self.queue.push(bytes, true).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,5 @@
+pub mod csv;
+pub mod fit;
+pub mod gpx;
+pub mod log;
+pub mod record;
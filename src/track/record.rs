@@ -0,0 +1,238 @@
+use chrono::{Datelike, NaiveDate, NaiveTime, Timelike};
+
+use crate::gps::{
+    laps::{Lap, LapKind},
+    power::PowerProfile,
+    reader::GpsReaderResults,
+};
+
+const TAG_SESSION_START: u8 = 0x01;
+const TAG_SESSION_END: u8 = 0x03;
+const TAG_POINT: u8 = 0x04;
+const TAG_PAUSE: u8 = 0x05;
+const TAG_RESUME: u8 = 0x06;
+const TAG_LAP: u8 = 0x07;
+const TAG_POWER: u8 = 0x08;
+
+pub const MAX_RECORD_LEN: usize = 48;
+/// Record times are milliseconds since UTC midnight and wrap at this.
+pub const MS_PER_DAY: u32 = 86_400_000;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RecordError {
+    BufferTooSmall,
+    Truncated,
+    UnknownTag(u8),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TrackPoint {
+    pub lat: f64,
+    pub lon: f64,
+    pub alt: f32,
+    pub hdop: f32,
+    pub time_ms: u32,
+    pub date: Option<NaiveDate>,
+}
+
+impl TrackPoint {
+    pub fn from_results(results: &GpsReaderResults) -> Option<Self> {
+        if let GpsReaderResults {
+            lat: Some(lat),
+            lon: Some(lon),
+            alt: Some(alt),
+            hdop: Some(hdop),
+            timestamp: Some(timestamp),
+            date,
+            ..
+        } = *results
+        {
+            Some(TrackPoint {
+                lat,
+                lon,
+                alt,
+                hdop,
+                time_ms: timestamp.num_seconds_from_midnight() * 1000
+                    + timestamp.nanosecond() / 1_000_000,
+                date,
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn to_results(&self) -> GpsReaderResults {
+        GpsReaderResults {
+            lat: Some(self.lat),
+            lon: Some(self.lon),
+            alt: Some(self.alt),
+            hdop: Some(self.hdop),
+            timestamp: NaiveTime::from_num_seconds_from_midnight_opt(
+                self.time_ms / 1000,
+                (self.time_ms % 1000) * 1_000_000,
+            ),
+            date: self.date,
+            speed_knots: None,
+            course: None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TrackRecord {
+    SessionStart {
+        session: u16,
+    },
+    Point(TrackPoint),
+    SessionEnd {
+        session: u16,
+    },
+    /// Auto-pause markers, stamped with the UTC time of day of the fix
+    /// that triggered them.
+    Pause {
+        time_ms: u32,
+    },
+    Resume {
+        time_ms: u32,
+    },
+    /// Written when a lap ends, manually or at a split.
+    Lap(Lap),
+    /// Receiver power profile, written at the start of a session and
+    /// whenever it changes.
+    Power(PowerProfile),
+}
+
+impl TrackRecord {
+    pub fn encode<'b>(&self, buf: &'b mut [u8]) -> Result<&'b [u8], RecordError> {
+        let mut writer = ByteWriter { buf, len: 0 };
+        match self {
+            TrackRecord::SessionStart { session } => {
+                writer.put(&[TAG_SESSION_START])?;
+                writer.put(&session.to_le_bytes())?;
+            }
+            TrackRecord::Point(point) => {
+                writer.put(&[TAG_POINT])?;
+                writer.put(&point.lat.to_le_bytes())?;
+                writer.put(&point.lon.to_le_bytes())?;
+                writer.put(&point.alt.to_le_bytes())?;
+                writer.put(&point.hdop.to_le_bytes())?;
+                writer.put(&point.time_ms.to_le_bytes())?;
+                let days = point.date.map_or(0, |date| date.num_days_from_ce());
+                writer.put(&days.to_le_bytes())?;
+            }
+            TrackRecord::SessionEnd { session } => {
+                writer.put(&[TAG_SESSION_END])?;
+                writer.put(&session.to_le_bytes())?;
+            }
+            TrackRecord::Pause { time_ms } => {
+                writer.put(&[TAG_PAUSE])?;
+                writer.put(&time_ms.to_le_bytes())?;
+            }
+            TrackRecord::Resume { time_ms } => {
+                writer.put(&[TAG_RESUME])?;
+                writer.put(&time_ms.to_le_bytes())?;
+            }
+            TrackRecord::Lap(lap) => {
+                writer.put(&[TAG_LAP])?;
+                writer.put(&lap.number.to_le_bytes())?;
+                let kind = match lap.kind {
+                    LapKind::Manual => 0u8,
+                    LapKind::Split => 1,
+                };
+                writer.put(&[kind])?;
+                writer.put(&lap.distance_m.to_le_bytes())?;
+                writer.put(&lap.elapsed_ms.to_le_bytes())?;
+                writer.put(&lap.moving_ms.to_le_bytes())?;
+                writer.put(&lap.gain_m.to_le_bytes())?;
+            }
+            TrackRecord::Power(profile) => {
+                writer.put(&[TAG_POWER])?;
+                writer.put(&profile.period_secs().to_le_bytes())?;
+            }
+        }
+        let len = writer.len;
+        Ok(&writer.buf[..len])
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, RecordError> {
+        let mut reader = ByteReader { bytes, pos: 0 };
+        let tag = reader.take::<1>()?[0];
+        match tag {
+            TAG_SESSION_START => Ok(TrackRecord::SessionStart {
+                session: u16::from_le_bytes(reader.take()?),
+            }),
+            TAG_POINT => Ok(TrackRecord::Point(TrackPoint {
+                lat: f64::from_le_bytes(reader.take()?),
+                lon: f64::from_le_bytes(reader.take()?),
+                alt: f32::from_le_bytes(reader.take()?),
+                hdop: f32::from_le_bytes(reader.take()?),
+                time_ms: u32::from_le_bytes(reader.take()?),
+                date: match i32::from_le_bytes(reader.take()?) {
+                    0 => None,
+                    days => NaiveDate::from_num_days_from_ce_opt(days),
+                },
+            })),
+            TAG_SESSION_END => Ok(TrackRecord::SessionEnd {
+                session: u16::from_le_bytes(reader.take()?),
+            }),
+            TAG_PAUSE => Ok(TrackRecord::Pause {
+                time_ms: u32::from_le_bytes(reader.take()?),
+            }),
+            TAG_RESUME => Ok(TrackRecord::Resume {
+                time_ms: u32::from_le_bytes(reader.take()?),
+            }),
+            TAG_LAP => Ok(TrackRecord::Lap(Lap {
+                number: u16::from_le_bytes(reader.take()?),
+                kind: match reader.take::<1>()?[0] {
+                    1 => LapKind::Split,
+                    _ => LapKind::Manual,
+                },
+                distance_m: f32::from_le_bytes(reader.take()?),
+                elapsed_ms: u32::from_le_bytes(reader.take()?),
+                moving_ms: u32::from_le_bytes(reader.take()?),
+                gain_m: f32::from_le_bytes(reader.take()?),
+            })),
+            TAG_POWER => Ok(TrackRecord::Power(PowerProfile::from_setting(
+                u16::from_le_bytes(reader.take()?) as isize,
+            ))),
+            other => Err(RecordError::UnknownTag(other)),
+        }
+    }
+}
+
+struct ByteWriter<'b> {
+    buf: &'b mut [u8],
+    len: usize,
+}
+
+impl ByteWriter<'_> {
+    fn put(&mut self, bytes: &[u8]) -> Result<(), RecordError> {
+        let end = self.len + bytes.len();
+        if end > self.buf.len() {
+            return Err(RecordError::BufferTooSmall);
+        }
+        self.buf[self.len..end].copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+}
+
+struct ByteReader<'b> {
+    bytes: &'b [u8],
+    pos: usize,
+}
+
+impl ByteReader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], RecordError> {
+        let end = self.pos + N;
+        let slice = self
+            .bytes
+            .get(self.pos..end)
+            .ok_or(RecordError::Truncated)?;
+        self.pos = end;
+        let mut out = [0u8; N];
+        out.copy_from_slice(slice);
+        Ok(out)
+    }
+}
@@ -0,0 +1,146 @@
+//! Battery charge from the cell voltage of a single LiPo.
+
+/// Charge at or below which the user is warned.
+pub const LOW_PERCENT: u8 = 15;
+/// Charge at or below which the session is closed and the device shuts down
+/// while there is still enough left to write flash safely.
+pub const CRITICAL_PERCENT: u8 = 3;
+/// A low battery has to recover this much past `LOW_PERCENT` to clear the
+/// warning, so a cell sagging under the receiver's load doesn't flap.
+const RECOVER_MARGIN_PERCENT: u8 = 5;
+/// Weight of a new reading in the running average. Readings taken while
+/// the radio or the display draw current sit lower than the cell really is.
+const SMOOTHING: f32 = 0.25;
+
+/// Resting cell voltage in mV against state of charge, from full down to
+/// the protection cutoff. Light loads only; under a heavy draw the cell
+/// reads lower.
+const LIPO_CURVE: [(u16, u8); 21] = [
+    (4200, 100),
+    (4150, 95),
+    (4110, 90),
+    (4080, 85),
+    (4020, 80),
+    (3980, 75),
+    (3950, 70),
+    (3910, 65),
+    (3870, 60),
+    (3850, 55),
+    (3840, 50),
+    (3820, 45),
+    (3800, 40),
+    (3790, 35),
+    (3770, 30),
+    (3750, 25),
+    (3730, 20),
+    (3710, 15),
+    (3690, 10),
+    (3610, 5),
+    (3270, 0),
+];
+
+/// Reads the battery voltage. The device samples it with the SAADC; tests
+/// feed in whatever they like.
+#[allow(async_fn_in_trait)]
+pub trait BatterySource {
+    type Error;
+
+    /// Cell voltage in mV.
+    async fn read_mv(&mut self) -> Result<u16, Self::Error>;
+}
+
+/// State of charge in percent for a cell at `mv`, interpolated along the
+/// discharge curve.
+pub fn state_of_charge(mv: u16) -> u8 {
+    let (full_mv, _) = LIPO_CURVE[0];
+    if mv >= full_mv {
+        return 100;
+    }
+    for pair in LIPO_CURVE.windows(2) {
+        let [(high_mv, high_pct), (low_mv, low_pct)] = [pair[0], pair[1]];
+        if mv >= low_mv {
+            let t = f32::from(mv - low_mv) / f32::from(high_mv - low_mv);
+            return low_pct + (t * f32::from(high_pct - low_pct) + 0.5) as u8;
+        }
+    }
+    0
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BatteryLevel {
+    #[default]
+    Normal,
+    Low,
+    Critical,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BatteryStatus {
+    pub millivolts: u16,
+    pub percent: u8,
+    pub level: BatteryLevel,
+}
+
+/// Smooths battery readings and tracks the charge level. Critical is final:
+/// the device shuts down, so it is never cleared.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct BatteryMonitor {
+    average_mv: Option<f32>,
+    level: BatteryLevel,
+}
+
+impl BatteryMonitor {
+    pub const fn new() -> Self {
+        BatteryMonitor {
+            average_mv: None,
+            level: BatteryLevel::Normal,
+        }
+    }
+
+    /// Smoothed state, `None` before the first reading.
+    pub fn status(&self) -> Option<BatteryStatus> {
+        let millivolts = self.average_mv? as u16;
+        Some(BatteryStatus {
+            millivolts,
+            percent: state_of_charge(millivolts),
+            level: self.level,
+        })
+    }
+
+    /// Folds in a reading of `mv`. Returns the new level when it changes.
+    pub fn update(&mut self, mv: u16) -> Option<BatteryLevel> {
+        let mv = f32::from(mv);
+        let average = match self.average_mv {
+            Some(average) => average + SMOOTHING * (mv - average),
+            None => mv,
+        };
+        self.average_mv = Some(average);
+
+        let percent = state_of_charge(average as u16);
+        let level = match self.level {
+            BatteryLevel::Critical => BatteryLevel::Critical,
+            _ if percent <= CRITICAL_PERCENT => BatteryLevel::Critical,
+            _ if percent <= LOW_PERCENT => BatteryLevel::Low,
+            BatteryLevel::Low if percent <= LOW_PERCENT + RECOVER_MARGIN_PERCENT => {
+                BatteryLevel::Low
+            }
+            _ => BatteryLevel::Normal,
+        };
+        if level == self.level {
+            return None;
+        }
+        self.level = level;
+        Some(level)
+    }
+
+    /// Takes a reading from `source` and folds it in.
+    pub async fn sample<B: BatterySource>(
+        &mut self,
+        source: &mut B,
+    ) -> Result<Option<BatteryLevel>, B::Error> {
+        let mv = source.read_mv().await?;
+        Ok(self.update(mv))
+    }
+}
@@ -1,2 +1,5 @@
+pub mod battery;
 pub mod float;
+pub mod time;
+pub mod units;
 pub mod vector;
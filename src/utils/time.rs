@@ -0,0 +1,120 @@
+use core::fmt;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+
+use crate::gps::reader::GpsReaderResults;
+
+/// Daylight saving schedule applied on top of a zone's standard offset.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum DstRule {
+    #[default]
+    None,
+    /// Second Sunday of March to the first Sunday of November, switching at
+    /// 02:00 local time.
+    Us,
+    /// Last Sunday of March to the last Sunday of October, switching at
+    /// 01:00 UTC.
+    Eu,
+}
+
+impl DstRule {
+    /// Maps the value stored by the DST setting.
+    pub fn from_setting(value: isize) -> Self {
+        match value {
+            1 => DstRule::Us,
+            2 => DstRule::Eu,
+            _ => DstRule::None,
+        }
+    }
+}
+
+/// A fixed standard offset from UTC plus an optional DST rule.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct TimeZone {
+    pub offset_minutes: i32,
+    pub dst: DstRule,
+}
+
+impl TimeZone {
+    pub const UTC: TimeZone = TimeZone {
+        offset_minutes: 0,
+        dst: DstRule::None,
+    };
+
+    pub fn new(offset_minutes: i32, dst: DstRule) -> Self {
+        TimeZone {
+            offset_minutes,
+            dst,
+        }
+    }
+
+    pub fn is_dst(&self, utc: NaiveDateTime) -> bool {
+        let year = utc.year();
+        let (start, end) = match self.dst {
+            DstRule::None => return false,
+            DstRule::Us => {
+                let standard = Duration::minutes(self.offset_minutes.into());
+                let (Some(start), Some(end)) = (
+                    NaiveDate::from_weekday_of_month_opt(year, 3, Weekday::Sun, 2),
+                    NaiveDate::from_weekday_of_month_opt(year, 11, Weekday::Sun, 1),
+                ) else {
+                    return false;
+                };
+                // 02:00 standard time in, 02:00 daylight (01:00 standard) out.
+                (at(start, 2) - standard, at(end, 1) - standard)
+            }
+            DstRule::Eu => {
+                let (Some(start), Some(end)) = (last_sunday(year, 3), last_sunday(year, 10)) else {
+                    return false;
+                };
+                (at(start, 1), at(end, 1))
+            }
+        };
+        utc >= start && utc < end
+    }
+
+    /// Offset from UTC in effect at `utc`, DST included.
+    pub fn offset_at(&self, utc: NaiveDateTime) -> Duration {
+        let dst_minutes = if self.is_dst(utc) { 60 } else { 0 };
+        Duration::minutes((self.offset_minutes + dst_minutes).into())
+    }
+
+    pub fn to_local(&self, utc: NaiveDateTime) -> NaiveDateTime {
+        utc + self.offset_at(utc)
+    }
+}
+
+/// A span of seconds shown as `h:mm:ss`, or `m:ss` under an hour.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Hms(pub u32);
+
+impl fmt::Display for Hms {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (hours, minutes, seconds) = (self.0 / 3600, self.0 / 60 % 60, self.0 % 60);
+        if hours > 0 {
+            write!(f, "{}:{:02}:{:02}", hours, minutes, seconds)
+        } else {
+            write!(f, "{}:{:02}", minutes, seconds)
+        }
+    }
+}
+
+/// UTC date and time of a fix, once the receiver has reported both.
+pub fn utc_datetime(results: &GpsReaderResults) -> Option<NaiveDateTime> {
+    Some(results.date?.and_time(results.timestamp?))
+}
+
+fn at(date: NaiveDate, hour: u32) -> NaiveDateTime {
+    date.and_time(NaiveTime::from_hms_opt(hour, 0, 0).unwrap_or_default())
+}
+
+fn last_sunday(year: i32, month: u32) -> Option<NaiveDate> {
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    let last_day = NaiveDate::from_ymd_opt(next_year, next_month, 1)?.pred_opt()?;
+    let back = last_day.weekday().num_days_from_sunday();
+    last_day.checked_sub_signed(Duration::days(back.into()))
+}
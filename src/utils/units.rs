@@ -0,0 +1,231 @@
+use core::fmt;
+
+pub const FT_PER_METER: f64 = 3.28084;
+pub const FT_IN_A_MILE: f64 = 5280.0;
+pub const M_PER_MILE: f64 = 1609.344;
+pub const M_PER_NAUTICAL_MILE: f64 = 1852.0;
+pub const MPS_PER_MPH: f64 = 0.44704;
+pub const MPS_PER_KPH: f64 = 1.0 / 3.6;
+pub const MPS_PER_KNOT: f64 = M_PER_NAUTICAL_MILE / 3600.0;
+
+/// Slower than this a pace readout is meaningless (over 50 min/mi).
+const MIN_PACE_MPS: f64 = 0.5;
+
+/// Round map scale bar lengths in meters, shortest first, with labels.
+const IMPERIAL_SCALE_BARS: [(f64, &str); 14] = [
+    (20.0 / FT_PER_METER, "20 ft"),
+    (50.0 / FT_PER_METER, "50 ft"),
+    (100.0 / FT_PER_METER, "100 ft"),
+    (200.0 / FT_PER_METER, "200 ft"),
+    (500.0 / FT_PER_METER, "500 ft"),
+    (1000.0 / FT_PER_METER, "1000 ft"),
+    (2000.0 / FT_PER_METER, "2000 ft"),
+    (M_PER_MILE, "1 mi"),
+    (2.0 * M_PER_MILE, "2 mi"),
+    (5.0 * M_PER_MILE, "5 mi"),
+    (10.0 * M_PER_MILE, "10 mi"),
+    (20.0 * M_PER_MILE, "20 mi"),
+    (50.0 * M_PER_MILE, "50 mi"),
+    (100.0 * M_PER_MILE, "100 mi"),
+];
+const METRIC_SCALE_BARS: [(f64, &str); 14] = [
+    (5.0, "5 m"),
+    (10.0, "10 m"),
+    (20.0, "20 m"),
+    (50.0, "50 m"),
+    (100.0, "100 m"),
+    (200.0, "200 m"),
+    (500.0, "500 m"),
+    (1000.0, "1 km"),
+    (2000.0, "2 km"),
+    (5000.0, "5 km"),
+    (10000.0, "10 km"),
+    (20000.0, "20 km"),
+    (50000.0, "50 km"),
+    (100000.0, "100 km"),
+];
+const NAUTICAL_SCALE_BARS: [(f64, &str); 14] = [
+    (5.0, "5 m"),
+    (10.0, "10 m"),
+    (20.0, "20 m"),
+    (50.0, "50 m"),
+    (100.0, "100 m"),
+    (200.0, "200 m"),
+    (500.0, "500 m"),
+    (M_PER_NAUTICAL_MILE, "1 nm"),
+    (2.0 * M_PER_NAUTICAL_MILE, "2 nm"),
+    (5.0 * M_PER_NAUTICAL_MILE, "5 nm"),
+    (10.0 * M_PER_NAUTICAL_MILE, "10 nm"),
+    (20.0 * M_PER_NAUTICAL_MILE, "20 nm"),
+    (50.0 * M_PER_NAUTICAL_MILE, "50 nm"),
+    (100.0 * M_PER_NAUTICAL_MILE, "100 nm"),
+];
+
+/// Display unit system. Everything is computed in SI; this only decides
+/// how a value is shown.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Units {
+    #[default]
+    Imperial,
+    Metric,
+    Nautical,
+}
+
+/// A value converted for display, with the precision it reads best at.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Measure {
+    pub value: f64,
+    pub precision: u8,
+    pub unit: &'static str,
+}
+
+/// Time per unit of distance, e.g. `8:05/mi`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Pace {
+    pub secs: u32,
+    pub unit: &'static str,
+}
+
+impl fmt::Display for Measure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.*} {}",
+            self.precision as usize, self.value, self.unit
+        )
+    }
+}
+
+impl fmt::Display for Pace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{:02}{}", self.secs / 60, self.secs % 60, self.unit)
+    }
+}
+
+impl Units {
+    /// Maps the value stored by the Units setting.
+    pub fn from_setting(value: isize) -> Self {
+        match value {
+            1 => Units::Metric,
+            2 => Units::Nautical,
+            _ => Units::Imperial,
+        }
+    }
+
+    pub fn distance(self, meters: f64) -> Measure {
+        match self {
+            Units::Imperial => {
+                let feet = meters * FT_PER_METER;
+                if feet > FT_IN_A_MILE {
+                    Measure {
+                        value: feet / FT_IN_A_MILE,
+                        precision: 3,
+                        unit: "mi",
+                    }
+                } else {
+                    Measure {
+                        value: feet,
+                        precision: 0,
+                        unit: "ft",
+                    }
+                }
+            }
+            Units::Metric => {
+                if meters > 1000.0 {
+                    Measure {
+                        value: meters / 1000.0,
+                        precision: 3,
+                        unit: "km",
+                    }
+                } else {
+                    Measure {
+                        value: meters,
+                        precision: 0,
+                        unit: "m",
+                    }
+                }
+            }
+            Units::Nautical => {
+                if meters > M_PER_NAUTICAL_MILE / 10.0 {
+                    Measure {
+                        value: meters / M_PER_NAUTICAL_MILE,
+                        precision: 2,
+                        unit: "nm",
+                    }
+                } else {
+                    Measure {
+                        value: meters,
+                        precision: 0,
+                        unit: "m",
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn altitude(self, meters: f64) -> Measure {
+        match self {
+            Units::Imperial => Measure {
+                value: meters * FT_PER_METER,
+                precision: 0,
+                unit: "ft",
+            },
+            Units::Metric | Units::Nautical => Measure {
+                value: meters,
+                precision: 0,
+                unit: "m",
+            },
+        }
+    }
+
+    pub fn speed(self, mps: f64) -> Measure {
+        match self {
+            Units::Imperial => Measure {
+                value: mps / MPS_PER_MPH,
+                precision: 2,
+                unit: "mph",
+            },
+            Units::Metric => Measure {
+                value: mps / MPS_PER_KPH,
+                precision: 1,
+                unit: "kph",
+            },
+            Units::Nautical => Measure {
+                value: mps / MPS_PER_KNOT,
+                precision: 1,
+                unit: "kn",
+            },
+        }
+    }
+
+    /// The longest round map scale length, in meters and as a label, that
+    /// is no longer than `max_m`; the shortest one if none is.
+    pub fn scale_bar(self, max_m: f64) -> (f64, &'static str) {
+        let bars = match self {
+            Units::Imperial => &IMPERIAL_SCALE_BARS,
+            Units::Metric => &METRIC_SCALE_BARS,
+            Units::Nautical => &NAUTICAL_SCALE_BARS,
+        };
+        bars.iter()
+            .rev()
+            .find(|(meters, _)| *meters <= max_m)
+            .copied()
+            .unwrap_or(bars[0])
+    }
+
+    /// `None` while standing still or creeping.
+    pub fn pace(self, mps: f64) -> Option<Pace> {
+        if mps < MIN_PACE_MPS {
+            return None;
+        }
+        let (meters, unit) = match self {
+            Units::Imperial => (M_PER_MILE, "/mi"),
+            Units::Metric => (1000.0, "/km"),
+            Units::Nautical => (M_PER_NAUTICAL_MILE, "/nm"),
+        };
+        Some(Pace {
+            secs: (meters / mps + 0.5) as u32,
+            unit,
+        })
+    }
+}
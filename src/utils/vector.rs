@@ -20,20 +20,32 @@ impl<const N: usize, T: Default + Copy> CircularTracker<N, T> {
         }
     }
 
-    pub fn current(self) -> (usize, T) {
+    pub fn current(&self) -> (usize, T) {
         let val = self.items[self.index];
         (self.index, val)
     }
 
-    pub fn next(&mut self) -> (usize, T) {
+    pub fn next_item(&mut self) -> (usize, T) {
         let next_idx = (self.index + 1) % self.len;
         self.index = next_idx;
         self.current()
     }
 
-    pub fn previous(&mut self) -> (usize, T) {
+    pub fn previous_item(&mut self) -> (usize, T) {
         let next_idx = (self.index + self.len - 1) % self.len;
         self.index = next_idx;
         self.current()
     }
+
+    /// Moves to the first item matching `pred`, leaving the index alone if
+    /// none does.
+    pub fn select(&mut self, pred: impl Fn(&T) -> bool) -> bool {
+        match self.items[..self.len].iter().position(pred) {
+            Some(idx) => {
+                self.index = idx;
+                true
+            }
+            None => false,
+        }
+    }
 }
@@ -0,0 +1,121 @@
+mod common;
+
+use common::time;
+use hijo::gps::{
+    auto_pause::{AutoPause, PauseEvent, PauseState},
+    session::SessionStats,
+};
+
+/// Feeds one speed per second starting at `start_s`, collecting events.
+fn drive(pause: &mut AutoPause, start_s: u64, speeds: &[f64]) -> Vec<(u64, PauseEvent)> {
+    let mut events = Vec::new();
+    for (i, speed) in speeds.iter().enumerate() {
+        let now_s = start_s + i as u64;
+        if let Some(event) = pause.update(now_s * 1000, *speed) {
+            events.push((now_s, event));
+        }
+    }
+    events
+}
+
+fn enabled() -> AutoPause {
+    let mut pause = AutoPause::new();
+    pause.enabled = true;
+    pause
+}
+
+#[test]
+fn pauses_after_sustained_stop_and_resumes_on_movement() {
+    let mut pause = enabled();
+    let mut speeds = vec![3.0; 10];
+    speeds.extend([0.0; 20]);
+    speeds.extend([3.0; 10]);
+
+    let events = drive(&mut pause, 0, &speeds);
+
+    assert_eq!(
+        events,
+        vec![(15, PauseEvent::Paused), (33, PauseEvent::Resumed)]
+    );
+    assert_eq!(pause.state(), PauseState::Moving);
+}
+
+#[test]
+fn short_stops_do_not_pause() {
+    let mut pause = enabled();
+    let mut speeds = vec![3.0; 10];
+    speeds.extend([0.0; 4]);
+    speeds.extend([3.0; 10]);
+
+    assert!(drive(&mut pause, 0, &speeds).is_empty());
+    assert!(!pause.is_paused());
+}
+
+#[test]
+fn creeping_between_thresholds_stays_paused() {
+    let mut pause = enabled();
+    drive(&mut pause, 0, &[0.0; 10]);
+    assert!(pause.is_paused());
+
+    assert!(drive(&mut pause, 10, &[0.7; 20]).is_empty());
+    assert!(pause.is_paused());
+}
+
+#[test]
+fn the_resume_window_is_already_recording() {
+    let mut pause = enabled();
+    drive(&mut pause, 0, &[0.0; 10]);
+    assert!(pause.is_paused());
+
+    // A false start goes back to paused.
+    assert!(drive(&mut pause, 10, &[3.0, 3.0, 0.0]).is_empty());
+    assert!(pause.is_paused());
+
+    for now_s in 13..16 {
+        assert_eq!(pause.update(now_s * 1000, 3.0), None);
+        assert!(matches!(pause.state(), PauseState::Starting { .. }));
+        assert!(!pause.is_paused(), "paused at {now_s} s");
+    }
+    assert_eq!(pause.update(16_000, 3.0), Some(PauseEvent::Resumed));
+}
+
+#[test]
+fn disabling_during_the_resume_window_resumes() {
+    let mut pause = enabled();
+    drive(&mut pause, 0, &[0.0; 10]);
+    drive(&mut pause, 10, &[3.0]);
+    assert!(!pause.is_paused());
+
+    pause.enabled = false;
+    assert_eq!(pause.update(11_000, 3.0), Some(PauseEvent::Resumed));
+    assert_eq!(pause.state(), PauseState::Moving);
+}
+
+#[test]
+fn disabling_resumes_immediately() {
+    let mut pause = enabled();
+    drive(&mut pause, 0, &[0.0; 10]);
+    assert!(pause.is_paused());
+
+    pause.enabled = false;
+    assert_eq!(pause.update(10_000, 0.0), Some(PauseEvent::Resumed));
+    assert!(!pause.is_paused());
+}
+
+#[test]
+fn session_stats_keep_the_clocks() {
+    let mut pause = enabled();
+    let mut stats = SessionStats::new();
+    let mut speeds = vec![3.0; 10];
+    speeds.extend([0.0; 20]);
+    speeds.extend([3.0; 10]);
+    for (secs, speed) in (0..).zip(speeds) {
+        // Fed the way the firmware does: stats first, then the pause state.
+        stats.update(time(secs), speed, !pause.is_paused());
+        pause.update(u64::from(secs) * 1000, speed);
+    }
+    assert_eq!(stats.elapsed_secs(), 39);
+    // Each moving fix counts the second before it: 1-9 s, and 31-39 s once
+    // the resume window has started.
+    assert_eq!(stats.moving_secs(), 18);
+}
@@ -0,0 +1,90 @@
+use embassy_futures::block_on;
+use hijo::utils::battery::{
+    BatteryLevel, BatteryMonitor, BatterySource, CRITICAL_PERCENT, LOW_PERCENT, state_of_charge,
+};
+
+/// Hands out scripted readings, then fails.
+struct FakeBattery {
+    readings: &'static [u16],
+}
+
+impl BatterySource for FakeBattery {
+    type Error = ();
+
+    async fn read_mv(&mut self) -> Result<u16, ()> {
+        let (&first, rest) = self.readings.split_first().ok_or(())?;
+        self.readings = rest;
+        Ok(first)
+    }
+}
+
+#[test]
+fn charge_follows_the_lipo_curve() {
+    assert_eq!(state_of_charge(4300), 100);
+    assert_eq!(state_of_charge(4200), 100);
+    assert_eq!(state_of_charge(3840), 50);
+    assert_eq!(state_of_charge(3845), 53);
+    assert_eq!(state_of_charge(3710), 15);
+    assert_eq!(state_of_charge(3270), 0);
+    assert_eq!(state_of_charge(3000), 0);
+}
+
+#[test]
+fn first_reading_is_taken_as_is() {
+    let mut monitor = BatteryMonitor::new();
+    assert_eq!(monitor.status(), None);
+    assert_eq!(monitor.update(3840), None);
+    let status = monitor.status().unwrap();
+    assert_eq!(status.millivolts, 3840);
+    assert_eq!(status.percent, 50);
+    assert_eq!(status.level, BatteryLevel::Normal);
+}
+
+#[test]
+fn a_single_dip_does_not_warn() {
+    let mut monitor = BatteryMonitor::new();
+    monitor.update(3800);
+    // A radio burst drags one reading down.
+    assert_eq!(monitor.update(3500), None);
+    assert!(monitor.status().unwrap().percent > LOW_PERCENT);
+}
+
+#[test]
+fn low_warns_once_and_clears_with_margin() {
+    let mut monitor = BatteryMonitor::new();
+    assert_eq!(monitor.update(3750), None);
+    let events: Vec<_> = (0..20).filter_map(|_| monitor.update(3700)).collect();
+    assert_eq!(events, [BatteryLevel::Low]);
+
+    // Back to 18%: still inside the margin.
+    for _ in 0..20 {
+        assert_eq!(monitor.update(3725), None);
+    }
+    let events: Vec<_> = (0..20).filter_map(|_| monitor.update(3800)).collect();
+    assert_eq!(events, [BatteryLevel::Normal]);
+}
+
+#[test]
+fn critical_is_final() {
+    let mut monitor = BatteryMonitor::new();
+    assert_eq!(monitor.update(3400), Some(BatteryLevel::Critical));
+    assert!(monitor.status().unwrap().percent <= CRITICAL_PERCENT);
+    for _ in 0..20 {
+        assert_eq!(monitor.update(4200), None);
+    }
+    assert_eq!(monitor.status().unwrap().level, BatteryLevel::Critical);
+}
+
+#[test]
+fn samples_come_from_the_source() {
+    let mut battery = FakeBattery {
+        readings: &[3700, 3700],
+    };
+    let mut monitor = BatteryMonitor::new();
+    assert_eq!(
+        block_on(monitor.sample(&mut battery)),
+        Ok(Some(BatteryLevel::Low))
+    );
+    assert_eq!(block_on(monitor.sample(&mut battery)), Ok(None));
+    assert_eq!(block_on(monitor.sample(&mut battery)), Err(()));
+}
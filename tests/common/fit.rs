@@ -0,0 +1,109 @@
+//! Minimal FIT reader for checking exports: walks the definition and data
+//! messages and checks the header, the data size and both CRCs.
+
+/// One data message, fields as raw little-endian values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub global: u16,
+    pub fields: Vec<(u8, u32)>,
+}
+
+impl Message {
+    pub fn field(&self, number: u8) -> u32 {
+        self.fields
+            .iter()
+            .find(|(num, _)| *num == number)
+            .map(|(_, value)| *value)
+            .unwrap_or_else(|| panic!("message {} has no field {number}", self.global))
+    }
+}
+
+pub const FILE_ID: u16 = 0;
+pub const SESSION: u16 = 18;
+pub const LAP: u16 = 19;
+pub const RECORD: u16 = 20;
+pub const EVENT: u16 = 21;
+pub const ACTIVITY: u16 = 34;
+
+/// CRC-16 as given in the FIT protocol description.
+pub fn crc(bytes: &[u8]) -> u16 {
+    const TABLE: [u16; 16] = [
+        0x0000, 0xCC01, 0xD801, 0x1400, 0xF001, 0x3C00, 0x2800, 0xE401, 0xA001, 0x6C00, 0x7800,
+        0xB401, 0x5000, 0x9C01, 0x8801, 0x4400,
+    ];
+    let mut crc = 0u16;
+    for &byte in bytes {
+        for nibble in [byte & 0xF, byte >> 4] {
+            let tmp = TABLE[usize::from(crc & 0xF)];
+            crc = (crc >> 4) & 0x0FFF;
+            crc = crc ^ tmp ^ TABLE[usize::from(nibble)];
+        }
+    }
+    crc
+}
+
+/// Global message number and `(field number, size)` of each field.
+type Definition = (u16, Vec<(u8, usize)>);
+
+fn le(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0, |value, &byte| (value << 8) | u32::from(byte))
+}
+
+/// Decodes a whole file, panicking on anything malformed.
+pub fn decode(file: &[u8]) -> Vec<Message> {
+    let header_len = usize::from(file[0]);
+    assert_eq!(header_len, 14, "header size");
+    assert_eq!(&file[8..12], b".FIT");
+    assert_eq!(le(&file[12..14]) as u16, crc(&file[..12]), "header CRC");
+    let data_len = le(&file[4..8]) as usize;
+    assert_eq!(file.len(), header_len + data_len + 2, "data size");
+    let end = header_len + data_len;
+    assert_eq!(le(&file[end..]) as u16, crc(&file[..end]), "file CRC");
+
+    let mut definitions: [Option<Definition>; 16] = Default::default();
+    let mut messages = Vec::new();
+    let mut pos = header_len;
+    while pos < end {
+        let header = file[pos];
+        pos += 1;
+        assert_eq!(header & 0xA0, 0, "compressed or developer header at {pos}");
+        let local = usize::from(header & 0x0F);
+        if header & 0x40 != 0 {
+            assert_eq!(file[pos + 1], 0, "big-endian definition at {pos}");
+            let global = le(&file[pos + 2..pos + 4]) as u16;
+            let count = usize::from(file[pos + 4]);
+            pos += 5;
+            let fields = (0..count)
+                .map(|i| {
+                    let field = &file[pos + 3 * i..pos + 3 * i + 3];
+                    (field[0], usize::from(field[1]))
+                })
+                .collect();
+            pos += 3 * count;
+            definitions[local] = Some((global, fields));
+        } else {
+            let (global, fields) = definitions[local]
+                .as_ref()
+                .unwrap_or_else(|| panic!("undefined local message {local} at {pos}"));
+            let mut values = Vec::new();
+            for &(number, size) in fields {
+                values.push((number, le(&file[pos..pos + size])));
+                pos += size;
+            }
+            messages.push(Message {
+                global: *global,
+                fields: values,
+            });
+        }
+    }
+    assert_eq!(pos, end, "last message runs past the data");
+    messages
+}
+
+/// The messages of type `global`, in file order.
+pub fn of(messages: &[Message], global: u16) -> Vec<&Message> {
+    messages.iter().filter(|m| m.global == global).collect()
+}
@@ -0,0 +1,82 @@
+use std::{cell::RefCell, rc::Rc};
+
+use embedded_storage_async::nor_flash::{
+    ErrorType, MultiwriteNorFlash, NorFlash, NorFlashErrorKind, ReadNorFlash,
+};
+
+/// Same geometry as the nRF5340 NVMC.
+pub const PAGE_SIZE: usize = 4096;
+const WORD_SIZE: usize = 4;
+
+/// NOR flash held in RAM. Erased bytes read `0xFF` and writes can only
+/// clear bits, like the real part. Clones share the same memory, so a test
+/// can drop whatever owned the flash and open it again as after a reset.
+#[derive(Clone)]
+pub struct RamFlash {
+    bytes: Rc<RefCell<Vec<u8>>>,
+}
+
+impl RamFlash {
+    pub fn new(pages: usize) -> Self {
+        RamFlash {
+            bytes: Rc::new(RefCell::new(vec![0xFF; pages * PAGE_SIZE])),
+        }
+    }
+
+    pub fn len(&self) -> u32 {
+        self.bytes.borrow().len() as u32
+    }
+
+    fn check(&self, offset: u32, len: usize, align: usize) -> Result<(), NorFlashErrorKind> {
+        let offset = offset as usize;
+        if !offset.is_multiple_of(align) || !len.is_multiple_of(align) {
+            return Err(NorFlashErrorKind::NotAligned);
+        }
+        if offset + len > self.bytes.borrow().len() {
+            return Err(NorFlashErrorKind::OutOfBounds);
+        }
+        Ok(())
+    }
+}
+
+impl ErrorType for RamFlash {
+    type Error = NorFlashErrorKind;
+}
+
+impl ReadNorFlash for RamFlash {
+    const READ_SIZE: usize = 1;
+
+    async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        self.check(offset, bytes.len(), Self::READ_SIZE)?;
+        let start = offset as usize;
+        bytes.copy_from_slice(&self.bytes.borrow()[start..start + bytes.len()]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.bytes.borrow().len()
+    }
+}
+
+impl NorFlash for RamFlash {
+    const WRITE_SIZE: usize = WORD_SIZE;
+    const ERASE_SIZE: usize = PAGE_SIZE;
+
+    async fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        self.check(from, (to - from) as usize, Self::ERASE_SIZE)?;
+        self.bytes.borrow_mut()[from as usize..to as usize].fill(0xFF);
+        Ok(())
+    }
+
+    async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.check(offset, bytes.len(), Self::WRITE_SIZE)?;
+        let start = offset as usize;
+        let mut memory = self.bytes.borrow_mut();
+        for (cell, byte) in memory[start..start + bytes.len()].iter_mut().zip(bytes) {
+            *cell &= byte;
+        }
+        Ok(())
+    }
+}
+
+impl MultiwriteNorFlash for RamFlash {}
@@ -0,0 +1,54 @@
+//! Helpers shared by the integration tests. Each test binary only uses
+//! some of them.
+#![allow(dead_code)]
+
+pub mod fit;
+pub mod flash;
+
+use chrono::NaiveTime;
+use hijo::gps::{fns::LatLonAlt, reader::GpsReaderResults};
+
+/// Meters per degree of latitude, and of longitude at the equator.
+pub const M_PER_DEG: f64 = 111_194.93;
+
+#[track_caller]
+pub fn assert_close(actual: impl Into<f64>, expected: f64, tolerance: f64) {
+    let actual = actual.into();
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "expected {expected} +/- {tolerance}, got {actual}"
+    );
+}
+
+/// A position at sea level.
+pub fn at(latitude: f64, longitude: f64) -> LatLonAlt {
+    LatLonAlt {
+        latitude,
+        longitude,
+        altitude: 0.0,
+    }
+}
+
+/// `north` and `east` meters from the origin, near the equator.
+pub fn offset(north: f64, east: f64) -> LatLonAlt {
+    at(north / M_PER_DEG, east / M_PER_DEG)
+}
+
+/// `secs` past UTC midnight.
+pub fn time(secs: u32) -> NaiveTime {
+    NaiveTime::from_num_seconds_from_midnight_opt(secs, 0).unwrap()
+}
+
+/// A fix `north` meters from the origin, `secs` past midnight.
+pub fn fix(secs: u32, north: f64) -> GpsReaderResults {
+    GpsReaderResults {
+        lat: Some(north / M_PER_DEG),
+        lon: Some(0.0),
+        alt: Some(100.0),
+        hdop: Some(1.0),
+        timestamp: Some(time(secs)),
+        date: None,
+        speed_knots: None,
+        course: None,
+    }
+}
@@ -0,0 +1,130 @@
+use hijo::gps::elevation::ElevationTracker;
+
+/// Feeds `altitudes` one fix at a time, `run_m` apart, while recording.
+fn feed(tracker: &mut ElevationTracker, altitudes: impl IntoIterator<Item = f32>, run_m: f64) {
+    for altitude in altitudes {
+        tracker.update(altitude, run_m, true);
+    }
+}
+
+#[test]
+fn a_descent_adds_to_the_loss_only() {
+    let mut tracker = ElevationTracker::new();
+    // Steady at 100 m, down 1 m per fix to 40 m, then steady again.
+    feed(&mut tracker, [100.0; 10], 5.0);
+    feed(&mut tracker, (0..=60).map(|i| 100.0 - i as f32), 5.0);
+    feed(&mut tracker, [40.0; 30], 5.0);
+
+    assert_eq!(tracker.total_gain, 0.0);
+    // Counted in threshold-sized steps, so up to one threshold is left over.
+    assert!(
+        (57.0..=60.0).contains(&tracker.total_loss),
+        "{}",
+        tracker.total_loss
+    );
+    assert_eq!(tracker.max_altitude, Some(100.0));
+    let min = tracker.min_altitude.unwrap();
+    assert!((40.0..40.1).contains(&min), "{min}");
+}
+
+#[test]
+fn noise_inside_the_threshold_adds_nothing() {
+    let mut tracker = ElevationTracker::new();
+    assert_eq!(tracker.threshold_m, 3.0);
+    // ±2 m of fix-to-fix wander around 50 m.
+    feed(&mut tracker, [50.0; 10], 0.0);
+    feed(
+        &mut tracker,
+        (0..200).map(|i| if i % 2 == 0 { 52.0 } else { 48.0 }),
+        0.0,
+    );
+    // A step that stays under the threshold.
+    feed(&mut tracker, [52.5; 30], 0.0);
+    assert_eq!((tracker.total_gain, tracker.total_loss), (0.0, 0.0));
+
+    // Crossing it counts once, from the last turning point.
+    feed(&mut tracker, [54.0; 30], 0.0);
+    assert!(
+        (3.0..3.5).contains(&tracker.total_gain),
+        "{}",
+        tracker.total_gain
+    );
+    assert_eq!(tracker.total_loss, 0.0);
+
+    // Falling back under the threshold again is not a descent.
+    let gain = tracker.total_gain;
+    feed(&mut tracker, [52.0; 30], 0.0);
+    assert_eq!((tracker.total_gain, tracker.total_loss), (gain, 0.0));
+    feed(&mut tracker, [49.0; 30], 0.0);
+    assert_eq!(tracker.total_gain, gain);
+    assert!(
+        (3.0..3.5).contains(&tracker.total_loss),
+        "{}",
+        tracker.total_loss
+    );
+}
+
+#[test]
+fn a_higher_threshold_ignores_a_smaller_climb() {
+    let mut tracker = ElevationTracker::new();
+    tracker.threshold_m = 5.0;
+    feed(&mut tracker, [50.0; 10], 0.0);
+    feed(&mut tracker, [54.0; 30], 0.0);
+    assert_eq!(tracker.total_gain, 0.0);
+    feed(&mut tracker, [56.0; 30], 0.0);
+    assert!(tracker.total_gain >= 5.0, "{}", tracker.total_gain);
+}
+
+#[test]
+fn climbing_while_stopped_is_not_counted() {
+    let mut tracker = ElevationTracker::new();
+    feed(&mut tracker, [50.0; 10], 0.0);
+    for _ in 0..30 {
+        tracker.update(60.0, 0.0, false);
+    }
+    feed(&mut tracker, [60.0; 30], 0.0);
+    assert_eq!((tracker.total_gain, tracker.total_loss), (0.0, 0.0));
+    let max = tracker.max_altitude.unwrap();
+    assert!((59.9..=60.0).contains(&max), "{max}");
+}
+
+#[test]
+fn grade_follows_the_slope() {
+    let mut tracker = ElevationTracker::new();
+    assert_eq!(tracker.grade_percent, 0.0);
+
+    // Up 0.5 m every 5 m: a 10 % climb.
+    feed(&mut tracker, (0..=40).map(|i| 100.0 + i as f32 * 0.5), 5.0);
+    let up = tracker.grade_percent;
+    assert!((9.0..=11.0).contains(&up), "{up}");
+
+    // And back down at the same rate.
+    feed(&mut tracker, (0..=40).map(|i| 120.0 - i as f32 * 0.5), 5.0);
+    let down = tracker.grade_percent;
+    assert!((-11.0..=-9.0).contains(&down), "{down}");
+
+    // Standing still has no grade.
+    tracker.update(100.0, 0.0, true);
+    assert_eq!(tracker.grade_percent, 0.0);
+}
+
+#[test]
+fn a_session_reset_clears_totals_extremes_and_grade() {
+    let mut tracker = ElevationTracker::new();
+    tracker.threshold_m = 5.0;
+    feed(&mut tracker, (0..=40).map(|i| 100.0 + i as f32 * 0.5), 5.0);
+    assert!(tracker.total_gain > 0.0 && tracker.grade_percent > 0.0);
+
+    tracker.reset_session();
+    assert_eq!((tracker.total_gain, tracker.total_loss), (0.0, 0.0));
+    assert_eq!((tracker.max_altitude, tracker.min_altitude), (None, None));
+    assert_eq!(tracker.grade_percent, 0.0);
+    assert_eq!(tracker.threshold_m, 5.0);
+    let altitude = tracker.altitude().unwrap();
+
+    // Level riding afterwards adds nothing and only sees the new altitude.
+    feed(&mut tracker, [altitude; 10], 5.0);
+    assert_eq!((tracker.total_gain, tracker.total_loss), (0.0, 0.0));
+    assert_eq!(tracker.max_altitude, tracker.min_altitude);
+    assert_eq!(tracker.grade_percent, 0.0);
+}
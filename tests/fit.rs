@@ -0,0 +1,203 @@
+//! Decodes the FIT export of a session with an auto-pause and laps.
+//!
+//! `fixtures/session.fit` is the reference export of the same session,
+//! kept once its fields matched the expectations below. A change in the
+//! writer's output shows up as a difference in the decoded messages.
+
+mod common;
+
+use chrono::NaiveDate;
+use common::{
+    M_PER_DEG,
+    fit::{self, ACTIVITY, EVENT, FILE_ID, LAP, Message, RECORD, SESSION},
+};
+use hijo::{
+    gps::laps::{Lap, LapKind},
+    track::{
+        fit::FitWriter,
+        log::{PointCounter, SessionEntry},
+        record::TrackPoint,
+    },
+};
+
+/// 2024-05-18T10:00:00Z in seconds since the FIT epoch.
+const START: u32 = 1_084_960_800;
+
+fn point(secs: u32, north_m: f64) -> SessionEntry {
+    SessionEntry::Point(TrackPoint {
+        lat: 51.5 + north_m / M_PER_DEG,
+        lon: -0.125,
+        alt: 20.0 + north_m as f32 / 10.0,
+        hdop: 0.8,
+        time_ms: 36_000_000 + secs * 1000,
+        date: NaiveDate::from_ymd_opt(2024, 5, 18),
+    })
+}
+
+fn lap(number: u16, kind: LapKind) -> SessionEntry {
+    // Only the position of a lap marker matters to the export.
+    SessionEntry::Lap(Lap {
+        number,
+        kind,
+        distance_m: 0.0,
+        elapsed_ms: 0,
+        moving_ms: 0,
+        gain_m: 0.0,
+    })
+}
+
+/// 5 m/s north and climbing for 20 s, 30 s auto-paused in place, then
+/// another 30 s on. A split lap at 10 s and a manual one at 65 s.
+fn session() -> Vec<SessionEntry> {
+    let mut entries = Vec::new();
+    for secs in 0..=20 {
+        entries.push(point(secs, f64::from(secs) * 5.0));
+        if secs == 10 {
+            entries.push(lap(1, LapKind::Split));
+        }
+    }
+    entries.push(SessionEntry::Pause {
+        time_ms: 36_020_000,
+    });
+    entries.push(SessionEntry::Resume {
+        time_ms: 36_050_000,
+    });
+    for secs in 50..=80 {
+        entries.push(point(secs, f64::from(secs - 30) * 5.0));
+        if secs == 65 {
+            entries.push(lap(2, LapKind::Manual));
+        }
+    }
+    entries
+}
+
+fn export(entries: &[SessionEntry]) -> Vec<u8> {
+    let mut counter = PointCounter::default();
+    for entry in entries {
+        entry.send_to(&mut counter);
+    }
+    let mut writer = FitWriter::new(Vec::new(), NaiveDate::default(), &counter);
+    for entry in entries {
+        entry.send_to(&mut writer);
+    }
+    writer.finish().unwrap()
+}
+
+fn fields(message: &Message, numbers: &[u8]) -> Vec<u32> {
+    numbers
+        .iter()
+        .map(|&number| message.field(number))
+        .collect()
+}
+
+#[test]
+fn matches_the_reference_file() {
+    let messages = fit::decode(&export(&session()));
+    let reference = fit::decode(include_bytes!("fixtures/session.fit"));
+    assert_eq!(messages, reference);
+}
+
+#[test]
+fn one_record_per_point() {
+    let messages = fit::decode(&export(&session()));
+    assert_eq!(messages[0].global, FILE_ID);
+    assert_eq!(messages[0].field(0), 4);
+
+    let records = fit::of(&messages, RECORD);
+    assert_eq!(records.len(), 52);
+    assert_eq!(records[0].field(253), START);
+    assert_eq!(records[51].field(253), START + 80);
+    // Distance only grows, and the session total is the last record's.
+    assert!(records.windows(2).all(|w| w[0].field(5) <= w[1].field(5)));
+    let session = fit::of(&messages, SESSION)[0];
+    assert_eq!(session.field(9), records[51].field(5));
+}
+
+#[test]
+fn pauses_stop_the_timer() {
+    let messages = fit::decode(&export(&session()));
+    let events: Vec<Vec<u32>> = fit::of(&messages, EVENT)
+        .into_iter()
+        .map(|event| fields(event, &[253, 0, 1]))
+        .collect();
+    assert_eq!(
+        events,
+        [
+            [START, 0, 0],
+            [START + 20, 0, 4],
+            [START + 50, 0, 0],
+            [START + 80, 0, 4],
+        ]
+    );
+
+    let session = fit::of(&messages, SESSION)[0];
+    // start_time, total_elapsed_time, total_timer_time, num_laps
+    assert_eq!(fields(session, &[2, 7, 8, 26]), [START, 80_000, 50_000, 3]);
+    let distance_m = f64::from(session.field(9)) / 100.0;
+    assert!((230.0..=260.0).contains(&distance_m), "{distance_m} m");
+    // avg_speed over the timer time, mm/s.
+    assert_eq!(
+        session.field(14),
+        (distance_m * 1000.0 / 50.0) as u32,
+        "{session:?}"
+    );
+    let activity = fit::of(&messages, ACTIVITY)[0];
+    assert_eq!(activity.field(0), 50_000);
+}
+
+#[test]
+fn laps_follow_the_lap_markers() {
+    let messages = fit::decode(&export(&session()));
+    let laps = fit::of(&messages, LAP);
+    // timestamp, start_time, total_elapsed_time, total_timer_time, lap_trigger
+    let timing: Vec<Vec<u32>> = laps
+        .iter()
+        .map(|lap| fields(lap, &[253, 2, 7, 8, 24]))
+        .collect();
+    assert_eq!(
+        timing,
+        [
+            [START + 10, START, 10_000, 10_000, 3],
+            [START + 65, START + 10, 55_000, 25_000, 0],
+            [START + 80, START + 65, 15_000, 15_000, 7],
+        ]
+    );
+
+    // The laps add up to the session.
+    let session = fit::of(&messages, SESSION)[0];
+    let distance_cm: u32 = laps.iter().map(|lap| lap.field(9)).sum();
+    assert!(session.field(9).abs_diff(distance_cm) <= 2, "{distance_cm}");
+    let ascent: u32 = laps.iter().map(|lap| lap.field(21)).sum();
+    assert!(session.field(22).abs_diff(ascent) <= 2, "{ascent}");
+    assert!(session.field(22) > 15, "{session:?}");
+}
+
+#[test]
+fn no_lap_markers_make_one_lap() {
+    let entries: Vec<SessionEntry> = session()
+        .into_iter()
+        .filter(|entry| !matches!(entry, SessionEntry::Lap(_)))
+        .collect();
+    let messages = fit::decode(&export(&entries));
+    let laps = fit::of(&messages, LAP);
+    assert_eq!(laps.len(), 1);
+    assert_eq!(fields(laps[0], &[2, 7, 8, 24]), [START, 80_000, 50_000, 7]);
+}
+
+#[test]
+fn a_lap_marker_at_the_end_leaves_no_empty_lap() {
+    let mut entries = session();
+    entries.push(lap(3, LapKind::Manual));
+    let messages = fit::decode(&export(&entries));
+    let laps = fit::of(&messages, LAP);
+    assert_eq!(laps.len(), 3);
+    assert_eq!(laps[2].field(24), 0);
+}
+
+#[test]
+fn an_empty_session_is_still_a_valid_file() {
+    let messages = fit::decode(&export(&[]));
+    assert_eq!(fit::of(&messages, RECORD).len(), 0);
+    assert_eq!(fit::of(&messages, LAP).len(), 1);
+    assert_eq!(fit::of(&messages, SESSION)[0].field(8), 0);
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use common::{assert_close, at};
+use hijo::gps::fns::{
+    LatLonAlt, along_track_distance_m, cross_track_distance_m, haversine_distance_m,
+    initial_bearing_deg, relative_bearing_deg, segment_offset,
+};
+
+#[test]
+fn bearings_along_the_axes() {
+    let origin = at(0.0, 0.0);
+    assert_close(initial_bearing_deg(origin, at(1.0, 0.0)), 0.0, 1e-9);
+    assert_close(initial_bearing_deg(origin, at(0.0, 1.0)), 90.0, 1e-9);
+    assert_close(initial_bearing_deg(origin, at(-1.0, 0.0)), 180.0, 1e-9);
+    assert_close(initial_bearing_deg(origin, at(0.0, -1.0)), 270.0, 1e-9);
+}
+
+#[test]
+fn london_to_paris_and_back() {
+    let london = at(51.5074, -0.1278);
+    let paris = at(48.8566, 2.3522);
+
+    assert_close(haversine_distance_m(london, paris), 343_556.1, 1.0);
+    assert_close(initial_bearing_deg(london, paris), 148.116, 0.001);
+    // Not the reciprocal: great circles bend.
+    assert_close(initial_bearing_deg(paris, london), 330.021, 0.001);
+}
+
+#[test]
+fn relative_bearing_picks_the_short_way_round() {
+    assert_close(relative_bearing_deg(10.0, 350.0), 20.0, 1e-9);
+    assert_close(relative_bearing_deg(350.0, 10.0), -20.0, 1e-9);
+    assert_close(relative_bearing_deg(90.0, 90.0), 0.0, 1e-9);
+    assert_close(relative_bearing_deg(270.0, 90.0), -180.0, 1e-9);
+}
+
+#[test]
+fn cross_track_is_signed_by_side() {
+    // Northbound along the prime meridian; a thousandth of a degree is
+    // about 111 m.
+    let start = at(0.0, 0.0);
+    let end = at(0.01, 0.0);
+    assert_close(
+        cross_track_distance_m(start, end, at(0.005, 0.001)),
+        111.2,
+        0.1,
+    );
+    assert_close(
+        cross_track_distance_m(start, end, at(0.005, -0.001)),
+        -111.2,
+        0.1,
+    );
+    assert_close(cross_track_distance_m(start, end, at(0.02, 0.0)), 0.0, 1e-6);
+}
+
+#[test]
+fn along_track_is_negative_behind_the_start() {
+    let start = at(0.0, 0.0);
+    let end = at(0.01, 0.0);
+    assert_close(
+        along_track_distance_m(start, end, at(0.005, 0.001)),
+        556.0,
+        0.1,
+    );
+    assert_close(
+        along_track_distance_m(start, end, at(-0.002, 0.001)),
+        -222.4,
+        0.1,
+    );
+}
+
+#[test]
+fn segment_offset_clamps_to_the_ends() {
+    let start = at(0.0, 0.0);
+    let end = at(0.01, 0.0);
+
+    let beside = segment_offset(start, end, at(0.005, 0.001));
+    assert_close(beside.distance_m, 111.2, 0.1);
+    assert_close(beside.along_track_m, 556.0, 0.1);
+
+    let behind = segment_offset(start, end, at(-0.002, 0.0));
+    assert_close(behind.along_track_m, 0.0, 1e-9);
+    assert_close(behind.distance_m, 222.4, 0.1);
+
+    let past = segment_offset(start, end, at(0.011, 0.001));
+    assert_close(past.along_track_m, 1111.9, 0.1);
+    assert_close(
+        past.distance_m,
+        haversine_distance_m(end, at(0.011, 0.001)),
+        1e-9,
+    );
+
+    let point = segment_offset(start, start, at(0.0, 0.001));
+    assert_close(point.distance_m, 111.2, 0.1);
+}
+
+#[test]
+fn positions_round_trip_through_bytes() {
+    let position = LatLonAlt {
+        latitude: 45.5231234,
+        longitude: -122.6765432,
+        altitude: 15.5,
+    };
+    let mut buf = [0u8; LatLonAlt::ENCODED_LEN];
+    position.encode(&mut buf);
+    assert_eq!(LatLonAlt::decode(&buf), Some(position));
+    assert_eq!(LatLonAlt::decode(&buf[..19]), None);
+}
@@ -0,0 +1,187 @@
+use hijo::input::gesture::{
+    Button, ButtonEvent, CHORD_WINDOW_MS, DEBOUNCE_MS, DOUBLE_PRESS_GAP_MS, GestureRecognizer,
+    LONG_PRESS_MS, REPEAT_INTERVAL_MS,
+};
+
+/// Feeds `(time, button, pressed)` level changes, updating at every one of
+/// them and at every deadline up to `until_ms`. Returns what came out, with
+/// the time of the update that produced it.
+fn run(
+    recognizer: &mut GestureRecognizer,
+    levels: &[(u64, Button, bool)],
+    until_ms: u64,
+) -> Vec<(u64, ButtonEvent)> {
+    let mut events = Vec::new();
+    let mut levels = levels.iter().peekable();
+    let mut now = 0;
+    loop {
+        let next_level = levels.peek().map(|&&(at, ..)| at);
+        let next = [next_level, recognizer.next_deadline()]
+            .into_iter()
+            .flatten()
+            .min();
+        let Some(next) = next.filter(|&next| next <= until_ms) else {
+            break;
+        };
+        now = next.max(now);
+        while let Some(&&(at, button, pressed)) = levels.peek()
+            && at <= now
+        {
+            recognizer.input(button, pressed, at);
+            levels.next();
+        }
+        recognizer.update(now);
+        while let Some(event) = recognizer.next_event() {
+            events.push((now, event));
+        }
+    }
+    events
+}
+
+fn press(at: u64, button: Button, held_ms: u64) -> [(u64, Button, bool); 2] {
+    [(at, button, true), (at + held_ms, button, false)]
+}
+
+#[test]
+fn bounces_are_ignored() {
+    let mut recognizer = GestureRecognizer::new();
+    let events = run(
+        &mut recognizer,
+        &[
+            (100, Button::Up, true),
+            (103, Button::Up, false),
+            (105, Button::Up, true),
+            (250, Button::Up, false),
+            (254, Button::Up, true),
+            (256, Button::Up, false),
+        ],
+        2_000,
+    );
+    assert_eq!(events, [(276, ButtonEvent::Short(Button::Up))]);
+}
+
+#[test]
+fn a_glitch_shorter_than_the_debounce_is_not_a_press() {
+    let mut recognizer = GestureRecognizer::new();
+    let glitch = press(100, Button::Page, DEBOUNCE_MS - 1);
+    assert_eq!(run(&mut recognizer, &glitch, 2_000), []);
+}
+
+#[test]
+fn long_press_repeats_while_held() {
+    let mut recognizer = GestureRecognizer::new();
+    let levels = press(0, Button::Down, LONG_PRESS_MS + 2 * REPEAT_INTERVAL_MS + 10);
+    let events = run(&mut recognizer, &levels, 5_000);
+    assert_eq!(
+        events,
+        [
+            (LONG_PRESS_MS, ButtonEvent::Long(Button::Down)),
+            (
+                LONG_PRESS_MS + REPEAT_INTERVAL_MS,
+                ButtonEvent::Repeat(Button::Down)
+            ),
+            (
+                LONG_PRESS_MS + 2 * REPEAT_INTERVAL_MS,
+                ButtonEvent::Repeat(Button::Down)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn short_press_is_immediate_without_double_press() {
+    let mut recognizer = GestureRecognizer::new();
+    let levels = [press(0, Button::Page, 100), press(200, Button::Page, 100)].concat();
+    let events = run(&mut recognizer, &levels, 2_000);
+    assert_eq!(
+        events,
+        [
+            (100 + DEBOUNCE_MS, ButtonEvent::Short(Button::Page)),
+            (300 + DEBOUNCE_MS, ButtonEvent::Short(Button::Page)),
+        ]
+    );
+}
+
+#[test]
+fn double_press() {
+    let mut recognizer = GestureRecognizer::new().with_double_press(Button::Action);
+    let levels = [press(0, Button::Action, 80), press(200, Button::Action, 80)].concat();
+    let events = run(&mut recognizer, &levels, 2_000);
+    assert_eq!(
+        events,
+        [(200 + DEBOUNCE_MS, ButtonEvent::Double(Button::Action))]
+    );
+}
+
+#[test]
+fn short_press_waits_out_the_double_press_gap() {
+    let mut recognizer = GestureRecognizer::new().with_double_press(Button::Action);
+    let levels = press(0, Button::Action, 80);
+    let events = run(&mut recognizer, &levels, 2_000);
+    assert_eq!(
+        events,
+        [(80 + DOUBLE_PRESS_GAP_MS, ButtonEvent::Short(Button::Action))]
+    );
+}
+
+#[test]
+fn another_button_ends_the_wait_for_a_double_press() {
+    let mut recognizer = GestureRecognizer::new().with_double_press(Button::Action);
+    let levels = [press(0, Button::Action, 80), press(150, Button::Up, 80)].concat();
+    let events = run(&mut recognizer, &levels, 2_000);
+    assert_eq!(
+        events,
+        [
+            (150 + DEBOUNCE_MS, ButtonEvent::Short(Button::Action)),
+            (230 + DEBOUNCE_MS, ButtonEvent::Short(Button::Up)),
+        ]
+    );
+}
+
+#[test]
+fn chord_swallows_both_presses() {
+    let mut recognizer = GestureRecognizer::new();
+    let levels = [
+        (0, Button::Down, true),
+        (CHORD_WINDOW_MS - 10, Button::Up, true),
+        (400, Button::Down, false),
+        (2_000, Button::Up, false),
+    ];
+    let events = run(&mut recognizer, &levels, 5_000);
+    assert_eq!(
+        events,
+        [(
+            CHORD_WINDOW_MS - 10 + DEBOUNCE_MS,
+            ButtonEvent::Chord(Button::Up, Button::Down)
+        )]
+    );
+}
+
+#[test]
+fn presses_further_apart_than_the_chord_window_stay_apart() {
+    let mut recognizer = GestureRecognizer::new();
+    let levels = [
+        (0, Button::Action, true),
+        (CHORD_WINDOW_MS + 50, Button::Page, true),
+        (300, Button::Page, false),
+        (400, Button::Action, false),
+    ];
+    let events = run(&mut recognizer, &levels, 2_000);
+    assert_eq!(
+        events,
+        [
+            (300 + DEBOUNCE_MS, ButtonEvent::Short(Button::Page)),
+            (400 + DEBOUNCE_MS, ButtonEvent::Short(Button::Action)),
+        ]
+    );
+}
+
+#[test]
+fn nothing_pending_when_idle() {
+    let mut recognizer = GestureRecognizer::new();
+    assert_eq!(recognizer.next_deadline(), None);
+    recognizer.input(Button::Up, true, 10);
+    assert_eq!(recognizer.next_deadline(), Some(10 + DEBOUNCE_MS));
+    recognizer.update(10 + DEBOUNCE_MS);
+    assert_eq!(recognizer.next_deadline(), Some(10 + LONG_PRESS_MS));
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use common::at;
+use hijo::gps::{
+    fns::LatLonAlt,
+    gpx::{GpxEvent, GpxParser},
+    waypoint::Waypoint,
+};
+
+fn parse(document: &str) -> Vec<GpxEvent> {
+    let mut parser = GpxParser::new();
+    document
+        .bytes()
+        .filter_map(|byte| parser.push(byte))
+        .collect()
+}
+
+#[test]
+fn picks_waypoints_out_of_a_document() {
+    let events = parse(concat!(
+        "<?xml version=\"1.0\"?>\n<gpx version=\"1.1\">\n",
+        "<wpt lat=\"45.5\" lon='-122.25'>\n  <ele>120.5</ele>\n",
+        "  <name>Fish &amp; Chips</name>\n</wpt>\n",
+        "<trk><trkseg><trkpt lat=\"1\" lon=\"2\"></trkpt></trkseg></trk>\n",
+        "<wpt lat=\"10\" lon=\"20\"></wpt>\n",
+        "<wpt lon=\"20\"><name>nowhere</name></wpt>\n",
+        "</gpx>\n",
+    ));
+    assert_eq!(
+        events,
+        [
+            GpxEvent::Waypoint(Waypoint::new(
+                "Fish & Chips",
+                LatLonAlt {
+                    latitude: 45.5,
+                    longitude: -122.25,
+                    altitude: 120.5,
+                },
+            )),
+            GpxEvent::Point(at(1.0, 2.0)),
+            GpxEvent::Waypoint(Waypoint::new("", at(10.0, 20.0))),
+            GpxEvent::Invalid,
+            GpxEvent::End,
+        ]
+    );
+}
+
+#[test]
+fn skips_overlong_elements_and_takes_self_closing_ones() {
+    let mut document = String::from("<gpx><wpt lat=\"1\" lon=\"2\"><desc>");
+    document.push_str(&"x".repeat(400));
+    document.push_str("</desc></wpt><wpt lat=\"3\" lon=\"4\"/></gpx>");
+    let events = parse(&document);
+    assert_eq!(
+        events,
+        [
+            GpxEvent::Invalid,
+            GpxEvent::Waypoint(Waypoint::new("", at(3.0, 4.0))),
+            GpxEvent::End,
+        ]
+    );
+}
+
+#[test]
+fn route_and_track_points_come_in_order() {
+    let events = parse(concat!(
+        "<gpx>\n<rte><name>loop</name>\n",
+        "<rtept lat=\"1.5\" lon=\"2.5\"><ele>30</ele></rtept>\n",
+        "<rtept lat=\"1.6\" lon=\"2.6\"/>\n</rte>\n",
+        "<trk><trkseg>\n<trkpt lat=\"1.7\" lon=\"2.7\">",
+        "<ele>31</ele><time>2024-05-04T13:37:00Z</time></trkpt>\n",
+        "</trkseg></trk>\n</gpx>",
+    ));
+    assert_eq!(
+        events,
+        [
+            GpxEvent::Point(LatLonAlt {
+                latitude: 1.5,
+                longitude: 2.5,
+                altitude: 30.0,
+            }),
+            GpxEvent::Point(at(1.6, 2.6)),
+            GpxEvent::Point(LatLonAlt {
+                latitude: 1.7,
+                longitude: 2.7,
+                altitude: 31.0,
+            }),
+            GpxEvent::End,
+        ]
+    );
+}
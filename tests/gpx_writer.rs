@@ -0,0 +1,155 @@
+use chrono::NaiveDate;
+use hijo::{
+    gps::{
+        fns::LatLonAlt,
+        gpx::{GpxEvent, GpxParser},
+        waypoint::Waypoint,
+    },
+    track::{gpx::GpxWriter, log::SessionSink, record::TrackPoint},
+};
+
+const HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+<gpx version=\"1.1\" creator=\"HiJo\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n";
+
+fn day(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+fn point(time_ms: u32, date: Option<NaiveDate>) -> TrackPoint {
+    TrackPoint {
+        lat: 51.5,
+        lon: -0.125,
+        alt: 30.0,
+        hdop: 0.9,
+        time_ms,
+        date,
+    }
+}
+
+fn finish(writer: GpxWriter<Vec<u8>>) -> String {
+    String::from_utf8(writer.finish().unwrap()).unwrap()
+}
+
+fn times(document: &str) -> Vec<&str> {
+    document
+        .split("<time>")
+        .skip(1)
+        .map(|rest| &rest[..rest.find("</time>").unwrap()])
+        .collect()
+}
+
+#[test]
+fn header_and_track_name() {
+    let mut writer = GpxWriter::new(Vec::new(), day(2024, 5, 18));
+    writer.begin(7);
+    let document = finish(writer);
+    assert_eq!(
+        document,
+        format!("{HEADER}<trk>\n<name>HiJo session 7</name>\n</trk>\n</gpx>\n")
+    );
+}
+
+#[test]
+fn track_point_format() {
+    let mut writer = GpxWriter::new(Vec::new(), day(2024, 5, 18));
+    writer.begin(1);
+    writer.point(&point(45_296_789, None));
+    let document = finish(writer);
+    assert!(
+        document.contains(
+            "<trkseg>\n<trkpt lat=\"51.5000000\" lon=\"-0.1250000\"><ele>30.0</ele>\
+<time>2024-05-18T12:34:56.789Z</time></trkpt>\n</trkseg>\n</trk>\n"
+        ),
+        "{document}"
+    );
+}
+
+#[test]
+fn each_pause_ends_a_segment() {
+    let mut writer = GpxWriter::new(Vec::new(), day(2024, 5, 18));
+    writer.begin(1);
+    // A pause before the first point leaves no empty segment behind.
+    SessionSink::pause(&mut writer, 0);
+    SessionSink::point(&mut writer, &point(1_000, None));
+    SessionSink::point(&mut writer, &point(2_000, None));
+    SessionSink::pause(&mut writer, 2_500);
+    SessionSink::resume(&mut writer, 9_000);
+    SessionSink::point(&mut writer, &point(10_000, None));
+    SessionSink::pause(&mut writer, 10_500);
+    SessionSink::pause(&mut writer, 11_000);
+    SessionSink::point(&mut writer, &point(20_000, None));
+    let document = finish(writer);
+
+    let segments: Vec<usize> = document
+        .split("<trkseg>\n")
+        .skip(1)
+        .map(|segment| {
+            assert_eq!(segment.matches("</trkseg>").count(), 1, "{document}");
+            segment.matches("<trkpt ").count()
+        })
+        .collect();
+    assert_eq!(segments, [2, 1, 1]);
+}
+
+#[test]
+fn undated_points_roll_over_at_midnight() {
+    let mut writer = GpxWriter::new(Vec::new(), day(2024, 2, 28));
+    writer.begin(1);
+    writer.point(&point(86_399_500, None));
+    writer.point(&point(500, None));
+    writer.point(&point(1_500, None));
+    // A point that carries its date wins over the rollover guess.
+    writer.point(&point(2_500, Some(day(2024, 3, 2))));
+    let document = finish(writer);
+    assert_eq!(
+        times(&document),
+        [
+            "2024-02-28T23:59:59.500Z",
+            "2024-02-29T00:00:00.500Z",
+            "2024-02-29T00:00:01.500Z",
+            "2024-03-02T00:00:02.500Z",
+        ]
+    );
+}
+
+#[test]
+fn dated_points_keep_their_own_date_past_midnight() {
+    let mut writer = GpxWriter::new(Vec::new(), day(2000, 1, 1));
+    writer.begin(1);
+    writer.point(&point(86_399_000, Some(day(2024, 12, 31))));
+    writer.point(&point(1_000, Some(day(2025, 1, 1))));
+    let document = finish(writer);
+    assert_eq!(
+        times(&document),
+        ["2024-12-31T23:59:59.000Z", "2025-01-01T00:00:01.000Z"]
+    );
+}
+
+#[test]
+fn waypoint_names_are_escaped() {
+    let position = LatLonAlt {
+        latitude: 45.5,
+        longitude: -122.25,
+        altitude: 120.5,
+    };
+    let waypoint = Waypoint::new("A&B <\"x\">", position);
+    let mut writer = GpxWriter::new(Vec::new(), day(2024, 5, 18));
+    writer.begin_waypoints();
+    writer.waypoint(&waypoint);
+    let document = finish(writer);
+    assert_eq!(
+        document,
+        format!(
+            "{HEADER}<wpt lat=\"45.5000000\" lon=\"-122.2500000\"><ele>120.5</ele>\
+<name>A&amp;B &lt;&quot;x&quot;&gt;</name></wpt>\n</gpx>\n"
+        )
+    );
+
+    // And read back as they were.
+    let mut parser = GpxParser::new();
+    let events: Vec<GpxEvent> = document
+        .bytes()
+        .filter_map(|byte| parser.push(byte))
+        .collect();
+    assert_eq!(events, [GpxEvent::Waypoint(waypoint), GpxEvent::End]);
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::time;
+use hijo::gps::{
+    laps::{LapKind, LapTracker},
+    session::SessionStats,
+};
+
+/// Session stats after `secs` seconds of steady riding from midnight.
+fn ride(stats: &mut SessionStats, from: u32, secs: u32) {
+    for s in from..=from + secs {
+        stats.update(time(s), 5.0, true);
+    }
+}
+
+#[test]
+fn splits_every_configured_distance() {
+    let mut stats = SessionStats::new();
+    let mut laps = LapTracker::new();
+    laps.split_m = 1000.0;
+
+    ride(&mut stats, 0, 150);
+    assert_eq!(laps.update(&stats, 750.0, 4.0), None);
+
+    ride(&mut stats, 150, 60);
+    let lap = laps.update(&stats, 1050.0, 6.0).unwrap();
+    assert_eq!(lap.number, 1);
+    assert_eq!(lap.kind, LapKind::Split);
+    assert_eq!(lap.distance_m, 1050.0);
+    assert_eq!(lap.elapsed_ms, 210_000);
+    assert_eq!(lap.gain_m, 6.0);
+    assert_eq!(lap.average_speed_mps(), 5.0);
+
+    // The next split counts from where the last one was taken.
+    assert_eq!(laps.update(&stats, 2000.0, 6.0), None);
+    assert!(laps.update(&stats, 2050.0, 6.0).is_some());
+    assert_eq!(laps.count(), 2);
+}
+
+#[test]
+fn manual_laps_keep_the_last_two_for_comparison() {
+    let mut stats = SessionStats::new();
+    let mut laps = LapTracker::new();
+
+    ride(&mut stats, 0, 100);
+    laps.lap(&stats, 500.0, 1.0);
+    ride(&mut stats, 100, 80);
+    let second = laps.lap(&stats, 900.0, 3.0);
+
+    assert_eq!(second.number, 2);
+    assert_eq!(second.kind, LapKind::Manual);
+    assert_eq!(second.distance_m, 400.0);
+    assert_eq!(second.elapsed_ms, 80_000);
+    assert_eq!(laps.last(), Some(&second));
+    assert_eq!(laps.previous().map(|lap| lap.number), Some(1));
+
+    let current = laps.current(&stats, 950.0, 3.0);
+    assert_eq!(current.number, 3);
+    assert_eq!(current.distance_m, 50.0);
+    assert_eq!(current.elapsed_ms, 0);
+
+    laps.reset();
+    assert_eq!(laps.count(), 0);
+    assert_eq!(laps.last(), None);
+    assert_eq!(laps.split_m, 0.0);
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use common::{M_PER_DEG, assert_close, offset};
+use hijo::gps::map::{Breadcrumbs, MAX_BREADCRUMBS, MapOrientation, MapView};
+
+#[test]
+fn breadcrumbs_skip_points_closer_than_the_spacing() {
+    let mut trail = Breadcrumbs::new();
+    assert!(trail.push(offset(0.0, 0.0)));
+    assert!(!trail.push(offset(2.0, 0.0)));
+    assert!(trail.push(offset(6.0, 0.0)));
+    assert_eq!(trail.len(), 2);
+    let start = trail.start().unwrap();
+    assert_close(start.latitude, 0.0, 1e-7);
+}
+
+#[test]
+fn full_trail_thins_out_and_keeps_the_start() {
+    let mut trail = Breadcrumbs::new();
+    for i in 0..=MAX_BREADCRUMBS {
+        assert!(trail.push(offset(i as f64 * 10.0, 0.0)));
+    }
+    assert_eq!(trail.len(), MAX_BREADCRUMBS / 2 + 1);
+    assert_close(trail.spacing_m(), 10.0, 1e-9);
+    assert_close(trail.start().unwrap().latitude, 0.0, 1e-7);
+    let last = trail.iter().last().unwrap();
+    assert_close(
+        last.latitude * M_PER_DEG,
+        MAX_BREADCRUMBS as f64 * 10.0,
+        0.1,
+    );
+
+    trail.reset();
+    assert!(trail.is_empty());
+}
+
+#[test]
+fn zoom_fits_the_farthest_point() {
+    let here = offset(0.0, 0.0);
+    let trail = [offset(0.0, 0.0), offset(300.0, 0.0), offset(300.0, -400.0)];
+    let view = MapView::fit(here, 0.0, trail.into_iter(), 22.0);
+    // 500 m away needs more than 20 m per pixel at a 22 pixel radius.
+    assert_eq!(view.meters_per_pixel, 50.0);
+
+    let empty = MapView::fit(here, 0.0, core::iter::empty(), 22.0);
+    assert_eq!(empty.meters_per_pixel, 0.5);
+}
+
+#[test]
+fn north_up_puts_north_at_the_top() {
+    let view = MapView::fit(offset(0.0, 0.0), 0.0, core::iter::empty(), 22.0);
+    let (x, y) = view.project(offset(10.0, 0.0));
+    assert_close(x, 0.0, 1e-6);
+    assert_close(y, -20.0, 1e-3);
+    let (x, y) = view.project(offset(0.0, 10.0));
+    assert_close(x, 20.0, 1e-3);
+    assert_close(y, 0.0, 1e-6);
+}
+
+#[test]
+fn heading_up_turns_the_course_to_the_top() {
+    // Heading east: east is up, north is to the left.
+    let view = MapView::fit(offset(0.0, 0.0), 90.0, core::iter::empty(), 22.0);
+    let (x, y) = view.project(offset(0.0, 10.0));
+    assert_close(x, 0.0, 1e-3);
+    assert_close(y, -20.0, 1e-3);
+    let (x, y) = view.project(offset(10.0, 0.0));
+    assert_close(x, -20.0, 1e-3);
+    assert_close(y, 0.0, 1e-3);
+}
+
+#[test]
+fn orientation_from_setting() {
+    assert_eq!(MapOrientation::from_setting(0), MapOrientation::NorthUp);
+    assert_eq!(MapOrientation::from_setting(1), MapOrientation::HeadingUp);
+    assert_eq!(MapOrientation::from_setting(7), MapOrientation::NorthUp);
+}
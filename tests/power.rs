@@ -0,0 +1,105 @@
+mod common;
+
+use common::{assert_close, fix};
+use hijo::gps::{
+    power::{DutyCycle, DutyState, MAX_AWAKE_MS, PowerProfile},
+    receiver::Command,
+    stack::GeoStack,
+};
+
+#[test]
+fn profile_follows_the_setting() {
+    assert_eq!(PowerProfile::from_setting(0), PowerProfile::Continuous);
+    assert_eq!(PowerProfile::from_setting(5), PowerProfile::Every5s);
+    assert_eq!(PowerProfile::from_setting(30), PowerProfile::Every30s);
+    assert_eq!(PowerProfile::from_setting(60), PowerProfile::EveryMinute);
+    assert_eq!(PowerProfile::from_setting(7), PowerProfile::Continuous);
+    for profile in [
+        PowerProfile::Continuous,
+        PowerProfile::Every5s,
+        PowerProfile::Every30s,
+        PowerProfile::EveryMinute,
+    ] {
+        let stored = profile.period_secs() as isize;
+        assert_eq!(PowerProfile::from_setting(stored), profile);
+    }
+}
+
+#[test]
+fn continuous_never_sleeps() {
+    let mut duty = DutyCycle::new();
+    assert_eq!(duty.fix(1_000), None);
+    assert_eq!(duty.poll(MAX_AWAKE_MS * 2), None);
+    assert!(!duty.is_asleep());
+}
+
+#[test]
+fn short_periods_use_standby_and_wake_early() {
+    let mut duty = DutyCycle::new();
+    duty.set_profile(PowerProfile::Every5s, 0);
+
+    assert_eq!(duty.fix(10_000), Some(Command::Standby));
+    assert!(duty.is_asleep());
+    // Further fixes in flight don't send it to sleep twice.
+    assert_eq!(duty.fix(10_100), None);
+    assert_eq!(duty.poll(13_999), None);
+    assert_eq!(duty.poll(14_000), Some(Command::Wake));
+    assert_eq!(duty.state(), DutyState::Awake { since_ms: 14_000 });
+    assert_eq!(duty.fix(15_000), Some(Command::Standby));
+}
+
+#[test]
+fn long_periods_use_backup_and_wake_on_their_own() {
+    let mut duty = DutyCycle::new();
+    duty.set_profile(PowerProfile::EveryMinute, 0);
+
+    assert_eq!(duty.fix(1_000), Some(Command::Backup(55)));
+    assert_eq!(duty.poll(55_999), None);
+    assert!(duty.is_asleep());
+    assert_eq!(duty.poll(56_000), None);
+    assert!(!duty.is_asleep());
+
+    duty.set_profile(PowerProfile::Every30s, 56_000);
+    assert_eq!(duty.fix(60_000), Some(Command::Backup(25)));
+}
+
+#[test]
+fn gives_up_searching_after_a_while() {
+    let mut duty = DutyCycle::new();
+    duty.set_profile(PowerProfile::Every5s, 1_000);
+    assert_eq!(duty.poll(MAX_AWAKE_MS), None);
+    assert_eq!(duty.poll(MAX_AWAKE_MS + 1_000), Some(Command::Standby));
+}
+
+#[test]
+fn switching_to_continuous_wakes_from_standby() {
+    let mut duty = DutyCycle::new();
+    duty.set_profile(PowerProfile::Every5s, 0);
+    assert_eq!(duty.fix(1_000), Some(Command::Standby));
+
+    duty.set_profile(PowerProfile::Continuous, 2_000);
+    assert_eq!(duty.poll(2_000), Some(Command::Wake));
+    assert_eq!(duty.fix(3_000), None);
+}
+
+#[test]
+fn sparse_fixes_count_the_straight_leg() {
+    let mut stack = GeoStack::new();
+    // A minute apart, 60 m each: a slow climb.
+    for (i, north) in [0.0, 60.0, 120.0, 180.0].into_iter().enumerate() {
+        assert!(stack.add_coords(fix(i as u32 * 60, north), true));
+    }
+    assert_close(stack.total_distance, 180.0, 0.5);
+    assert_close(stack.current_speed_mps, 1.0, 0.01);
+    assert_close(stack.current_heading, 0.0, 0.1);
+}
+
+#[test]
+fn sparse_fixes_ignore_jitter() {
+    let mut stack = GeoStack::new();
+    for (i, north) in [0.0, 8.0, -5.0, 3.0].into_iter().enumerate() {
+        assert!(stack.add_coords(fix(i as u32 * 60, north), true));
+    }
+    assert_eq!(stack.total_distance, 0.0);
+    assert_eq!(stack.current_speed_mps, 0.0);
+}
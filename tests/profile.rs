@@ -0,0 +1,59 @@
+mod common;
+
+use common::assert_close;
+use hijo::gps::profile::{ElevationProfile, MAX_PROFILE_SAMPLES};
+
+#[test]
+fn keeps_spaced_samples_and_always_the_latest() {
+    let mut profile = ElevationProfile::new();
+    assert!(profile.is_empty());
+    assert!(profile.push(0.0, 100.0));
+    assert!(!profile.push(4.0, 101.0));
+    assert!(profile.push(10.0, 102.0));
+    assert!(!profile.push(15.0, 110.0));
+
+    let points: Vec<_> = profile.iter().collect();
+    assert_eq!(points, [(0.0, 100.0), (10.0, 102.0), (15.0, 110.0)]);
+    assert_eq!(profile.distance_m(), 15.0);
+    assert_eq!(profile.range(), Some((100.0, 110.0)));
+}
+
+#[test]
+fn interpolates_between_points() {
+    let mut profile = ElevationProfile::new();
+    profile.push(0.0, 100.0);
+    profile.push(20.0, 120.0);
+    profile.push(40.0, 100.0);
+
+    assert_close(profile.altitude_at(0.0).unwrap(), 100.0, 1e-4);
+    assert_close(profile.altitude_at(5.0).unwrap(), 105.0, 1e-4);
+    assert_close(profile.altitude_at(30.0).unwrap(), 110.0, 1e-4);
+    assert_close(profile.altitude_at(40.0).unwrap(), 100.0, 1e-4);
+    assert_eq!(profile.altitude_at(41.0), None);
+    assert_eq!(ElevationProfile::new().altitude_at(0.0), None);
+}
+
+#[test]
+fn long_session_is_thinned_but_keeps_its_extremes() {
+    let mut profile = ElevationProfile::new();
+    // A 60 km ride sampled every 10 m with a single sharp peak.
+    for i in 0..6000 {
+        let altitude = if i == 1001 {
+            900.0
+        } else {
+            200.0 + i as f32 * 0.01
+        };
+        profile.push(i as f32 * 10.0, altitude);
+    }
+    let kept = profile.iter().count();
+    assert!(kept <= MAX_PROFILE_SAMPLES + 1, "{kept} points");
+    assert!(kept > MAX_PROFILE_SAMPLES / 2, "{kept} points");
+    assert!(profile.spacing_m() > 200.0);
+    assert_eq!(profile.iter().next(), Some((0.0, 200.0)));
+    assert_eq!(profile.distance_m(), 59_990.0);
+    assert_eq!(profile.range(), Some((200.0, 900.0)));
+
+    profile.reset();
+    assert!(profile.is_empty());
+    assert_eq!(profile.range(), None);
+}
@@ -0,0 +1,259 @@
+//! `ReceiverConfig` against a scripted fake receiver: every command written
+//! gets the next canned list of `$PAIR001` answers.
+
+use std::{cell::RefCell, collections::VecDeque};
+
+use embassy_futures::{block_on, yield_now};
+use embassy_sync::{
+    blocking_mutex::raw::NoopRawMutex,
+    channel::{Channel, Sender},
+};
+use embedded_io_async::{ErrorType, Write};
+use hijo::gps::{
+    reader::GpsReader,
+    receiver::{
+        ACK_QUEUE_LEN, Ack, AckResult, Command, ConfigError, ConstellationMask, NmeaSentence,
+        ReceiverConfig, ReceiverSettings, UpdateRate,
+    },
+};
+
+struct FakeReceiver<'a> {
+    written: &'a RefCell<Vec<String>>,
+    script: VecDeque<Vec<&'static str>>,
+    acks: Sender<'a, NoopRawMutex, Ack, ACK_QUEUE_LEN>,
+}
+
+impl ErrorType for FakeReceiver<'_> {
+    type Error = core::convert::Infallible;
+}
+
+impl Write for FakeReceiver<'_> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.written
+            .borrow_mut()
+            .push(String::from_utf8(buf.to_vec()).unwrap());
+        for answer in self.script.pop_front().unwrap_or_default() {
+            self.acks
+                .try_send(Ack::parse(answer.as_bytes()).unwrap())
+                .unwrap();
+        }
+        Ok(buf.len())
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// A timeout that fires once everything else has had a few polls.
+async fn soon() {
+    for _ in 0..4 {
+        yield_now().await;
+    }
+}
+
+/// Sends `commands` to a receiver answering with `script`, returning the
+/// result and every sentence written.
+fn run(
+    commands: &[Command],
+    script: Vec<Vec<&'static str>>,
+) -> (
+    Result<(), ConfigError<core::convert::Infallible>>,
+    Vec<String>,
+) {
+    let channel = Channel::<NoopRawMutex, Ack, ACK_QUEUE_LEN>::new();
+    let written = RefCell::new(Vec::new());
+    let fake = FakeReceiver {
+        written: &written,
+        script: script.into(),
+        acks: channel.sender(),
+    };
+    let mut config = ReceiverConfig::new(fake, channel.receiver());
+    let result = block_on(config.send_all(commands, soon));
+    (result, written.into_inner())
+}
+
+#[test]
+fn sentences_carry_the_checksum() {
+    assert_eq!(
+        Command::FixInterval(UpdateRate::Hz1).sentence(),
+        "$PAIR050,1000*12\r\n"
+    );
+    assert_eq!(
+        Command::SentenceRate(NmeaSentence::Gsv, 1).sentence(),
+        "$PAIR062,3,1*3C\r\n"
+    );
+    assert_eq!(
+        Command::Constellations(ConstellationMask::ALL).sentence(),
+        "$PAIR066,1,1,1,1,1,0*3B\r\n"
+    );
+    assert_eq!(Command::StaticThreshold(5).sentence(), "$PAIR070,5*24\r\n");
+    assert_eq!(Command::Standby.sentence(), "$PAIR003*39\r\n");
+    assert_eq!(Command::Wake.sentence(), "$PAIR002*38\r\n");
+    assert_eq!(Command::HotStart.sentence(), "$PAIR004*3E\r\n");
+    assert_eq!(Command::SaveConfig.sentence(), "$PAIR513*3D\r\n");
+    assert_eq!(
+        Command::BaudRate(115_200).sentence(),
+        "$PAIR864,0,0,115200*1B\r\n"
+    );
+}
+
+#[test]
+fn parses_acknowledgements() {
+    assert_eq!(
+        Ack::parse(b"$PAIR001,050,0*3E"),
+        Some(Ack {
+            command: 50,
+            result: AckResult::Success
+        })
+    );
+    assert_eq!(
+        Ack::parse(b"$PAIR001,066,4*3F").map(|ack| ack.result),
+        Some(AckResult::BadParameter)
+    );
+    assert_eq!(Ack::parse(b"$PAIR001,066,9*32"), None);
+    assert_eq!(Ack::parse(b"$PAIR010,0,2,2250,367100*3F"), None);
+}
+
+#[test]
+fn reader_passes_on_acknowledgements_only() {
+    let log = b"$PAIR001,050,0*3E\r\n$PAIR010,0,2,2250,367100*3F\r\n$PAIR001,050,1*3F\r\n";
+    let mut reader = GpsReader::new(&log[..]);
+    let acks: Vec<_> = block_on(async {
+        let mut acks = Vec::new();
+        while let Some(out) = reader.next().await {
+            acks.push(out.ack.unwrap());
+        }
+        acks
+    });
+    assert_eq!(
+        acks.iter().map(|ack| ack.result).collect::<Vec<_>>(),
+        [AckResult::Success, AckResult::Processing]
+    );
+}
+
+#[test]
+fn waits_through_processing_and_ignores_other_answers() {
+    let (result, written) = run(
+        &[Command::FixInterval(UpdateRate::Hz5)],
+        vec![vec![
+            "$PAIR001,066,0*3B",
+            "$PAIR001,050,1*3F",
+            "$PAIR001,050,0*3E",
+        ]],
+    );
+    assert_eq!(result, Ok(()));
+    assert_eq!(written, ["$PAIR050,200*21\r\n"]);
+}
+
+#[test]
+fn baud_rate_change_is_acknowledged() {
+    let (result, written) = run(
+        &[Command::BaudRate(115_200)],
+        vec![vec!["$PAIR001,864,0*31"]],
+    );
+    assert_eq!(result, Ok(()));
+    assert_eq!(written, ["$PAIR864,0,0,115200*1B\r\n"]);
+}
+
+#[test]
+fn retries_when_busy_or_silent() {
+    let (result, written) = run(
+        &[Command::Standby],
+        vec![vec![], vec!["$PAIR001,003,5*3D"], vec!["$PAIR001,003,0*38"]],
+    );
+    assert_eq!(result, Ok(()));
+    assert_eq!(written.len(), 3);
+
+    let (result, written) = run(&[Command::Standby], vec![]);
+    assert_eq!(result, Err(ConfigError::Timeout));
+    assert_eq!(written.len(), 3);
+}
+
+#[test]
+fn stops_at_a_rejected_command() {
+    let (result, written) = run(
+        &[
+            Command::Constellations(ConstellationMask::from_setting(0b00101)),
+            Command::SaveConfig,
+        ],
+        vec![vec!["$PAIR001,066,4*3F"]],
+    );
+    assert_eq!(result, Err(ConfigError::Rejected(AckResult::BadParameter)));
+    assert_eq!(written, ["$PAIR066,1,0,1,0,0,0*3A\r\n"]);
+}
+
+#[test]
+fn settings_changes_send_only_what_changed() {
+    let initial = ReceiverSettings::default();
+    let all = initial.commands(None);
+    assert_eq!(all[0], Command::FixInterval(UpdateRate::Hz1));
+    assert!(all.contains(&Command::SentenceRate(NmeaSentence::Gll, 0)));
+    assert!(all.contains(&Command::StaticThreshold(0)));
+    // Saved and restarted last, so the restart keeps the rest.
+    assert_eq!(
+        all[all.len() - 3..],
+        [
+            Command::Constellations(ConstellationMask::ALL),
+            Command::SaveConfig,
+            Command::HotStart,
+        ]
+    );
+    assert_eq!(all.len(), all.capacity());
+
+    let faster = ReceiverSettings {
+        rate: UpdateRate::Hz10,
+        ..initial
+    };
+    let commands = faster.commands(Some(&initial));
+    assert_eq!(commands[0], Command::FixInterval(UpdateRate::Hz10));
+    // Satellites in view still come once a second.
+    assert!(commands.contains(&Command::SentenceRate(NmeaSentence::Gsv, 10)));
+    assert!(
+        !commands
+            .iter()
+            .any(|c| matches!(c, Command::StaticThreshold(_)))
+    );
+
+    let still = ReceiverSettings {
+        static_threshold: 10,
+        ..initial
+    };
+    assert_eq!(
+        still.commands(Some(&initial)).as_slice(),
+        [Command::StaticThreshold(10)]
+    );
+    assert!(initial.commands(Some(&initial)).is_empty());
+
+    let gps_only = ReceiverSettings {
+        constellations: ConstellationMask::from_setting(0),
+        ..initial
+    };
+    assert_eq!(
+        gps_only.commands(Some(&initial)).as_slice(),
+        [
+            Command::Constellations(gps_only.constellations),
+            Command::SaveConfig,
+            Command::HotStart,
+        ]
+    );
+}
+
+#[test]
+fn boot_saves_and_restarts_only_for_new_constellations() {
+    let settings = ReceiverSettings::default();
+    let boot = settings.boot_commands(ConstellationMask::ALL);
+    assert_eq!(boot[..], settings.commands(None)[..boot.len()]);
+    assert!(!boot.iter().any(|c| matches!(
+        c,
+        Command::Constellations(_) | Command::SaveConfig | Command::HotStart
+    )));
+
+    let gps_only = ConstellationMask::from_setting(0);
+    let boot = settings.boot_commands(gps_only);
+    assert_eq!(boot.as_slice(), settings.commands(None).as_slice());
+
+    for value in [0b11111, 0b00001, 0b00101, 0b10101] {
+        assert_eq!(ConstellationMask::from_setting(value).to_setting(), value);
+    }
+}
@@ -0,0 +1,163 @@
+//! Replays NMEA logs through `GpsReader` and `GeoStack` on the host.
+//!
+//! Run with `cargo test --no-default-features --target <host triple>`.
+//! Expected values come from an independent model of the same pipeline:
+//! fixes at least 1 s apart, run through the constant-velocity filter, with
+//! distance taken between filtered positions while moving.
+//!
+//! `rys352a_ride.nmea` is checked against the ride it was made from
+//! instead. It is synthesized, not captured: the RYS352A's NMEA 4.10 output
+//! (`GN` fixes, one GSA per system, GSV with signal IDs, ZDA, `$PAIR001`
+//! acknowledgements) around a known track, with slowly wandering position
+//! and altitude errors, a cold start, a skipped epoch and a corrupted byte.
+
+mod common;
+
+use common::assert_close;
+use embassy_futures::block_on;
+use hijo::gps::{
+    reader::GpsReaderStats,
+    replay::{ReplaySummary, replay},
+};
+
+fn run(log: &[u8]) -> ReplaySummary {
+    block_on(replay(log))
+}
+
+#[test]
+fn walk_loop() {
+    let summary = run(include_bytes!("fixtures/walk_loop.nmea"));
+
+    assert_eq!(
+        summary.sentences,
+        GpsReaderStats {
+            good: 362,
+            bad_checksum: 1,
+            overflow: 1,
+            unknown_talker: 0,
+            parse_errors: 0,
+        }
+    );
+    assert_eq!(summary.fixes, 120);
+    assert_eq!(summary.accepted, 120);
+    assert_close(summary.total_distance_m, 156.547, 0.2);
+    assert_close(summary.total_elevation_gain, 0.0, 0.01);
+    assert_close(summary.final_speed_mps, 1.298, 0.02);
+    assert_close(summary.max_speed_mps, 1.373, 0.02);
+    // The corrupted GGA costs a fix, not time: 120 fixes span 120 s.
+    assert_eq!(summary.stats.elapsed_secs(), 120);
+    assert_eq!(summary.stats.moving_secs(), 118);
+    assert_close(
+        summary.stats.average_speed_mps(summary.total_distance_m),
+        1.327,
+        0.01,
+    );
+}
+
+#[test]
+fn hill_climb() {
+    let summary = run(include_bytes!("fixtures/hill_climb.nmea"));
+
+    assert_eq!(summary.sentences.good, 543);
+    assert_eq!(summary.fixes, 181);
+    assert_eq!(summary.accepted, 181);
+    assert_close(summary.total_distance_m, 717.839, 0.3);
+    // 0.3 m/s climb with a 0.5 m dip every 10 s: the dips stay inside the
+    // hysteresis band, and the last partial step below 3 m is not counted.
+    assert_close(summary.total_elevation_gain, 37.694, 0.05);
+    assert_close(summary.total_elevation_loss, 0.0, 0.01);
+    assert_close(summary.final_speed_mps, 4.001, 0.02);
+    assert_close(summary.max_speed_mps, 4.043, 0.02);
+    assert_eq!(summary.stats.elapsed_secs(), 180);
+    assert_eq!(summary.stats.moving_secs(), 179);
+}
+
+#[test]
+fn drive_5hz() {
+    let summary = run(include_bytes!("fixtures/drive_5hz.nmea"));
+
+    // 5 Hz output with a repeated epoch and 3 s of HDOP 6.5: only fixes a
+    // full second apart are used, and the poor ones are down-weighted.
+    assert_eq!(summary.sentences.good, 906);
+    assert_eq!(summary.fixes, 302);
+    assert_eq!(summary.accepted, 61);
+    assert_close(summary.total_distance_m, 1007.671, 0.3);
+    assert_close(summary.total_elevation_gain, 0.0, 0.01);
+    assert_close(summary.final_speed_mps, 21.289, 0.05);
+    assert_close(summary.max_speed_mps, 21.289, 0.05);
+    assert_eq!(summary.stats.elapsed_secs(), 60);
+    assert_eq!(summary.stats.moving_secs(), 60);
+}
+
+#[test]
+fn rys352a_ride() {
+    let summary = run(include_bytes!("fixtures/rys352a_ride.nmea"));
+
+    assert_eq!(
+        summary.sentences,
+        GpsReaderStats {
+            good: 4830,
+            bad_checksum: 1,
+            overflow: 0,
+            unknown_talker: 0,
+            // The no-fix GSAs of the cold start: `nmea` can't take empty
+            // DOP fields followed by a system ID.
+            parse_errors: 48,
+        }
+    );
+    // 12 s without a fix, then 276 epochs less the skipped and the corrupted.
+    assert_eq!(summary.fixes, 286);
+    assert_eq!(summary.accepted, 274);
+
+    // The ride: 20 s waiting, 300 m east on the flat, 360 m north up a
+    // 25 m hill, 25 s stopped, 360 m west down 15 m, 20 s stopped.
+    assert_close(summary.total_distance_m, 1016.0, 15.0);
+    // Climbs count in 3 m steps of smoothed altitude, so each one can come
+    // up a step short, give or take the 2 m of vertical noise.
+    assert_close(summary.total_elevation_gain, 26.5, 5.0);
+    assert_close(summary.total_elevation_loss, 15.0, 6.0);
+    assert_close(summary.max_speed_mps, 6.0, 0.3);
+    assert_close(summary.final_speed_mps, 0.0, 0.01);
+    assert_eq!(summary.stats.elapsed_secs(), 275);
+    assert_close(summary.stats.moving_secs(), 218.0, 5.0);
+}
+
+#[test]
+fn stationary_jitter_adds_nothing() {
+    let summary = run(include_bytes!("fixtures/stationary.nmea"));
+
+    assert_eq!(summary.fixes, 601);
+    assert_close(summary.total_distance_m, 0.0, 0.3);
+    // 1.5 m of altitude noise around a fixed point.
+    assert_close(summary.total_elevation_gain, 0.0, 0.01);
+    assert_close(summary.total_elevation_loss, 0.0, 0.01);
+    assert_close(summary.max_speed_mps, 0.0, 0.01);
+    assert_eq!(summary.stats.elapsed_secs(), 600);
+    assert_eq!(summary.stats.moving_secs(), 0);
+    assert_close(summary.stats.average_speed_mps(0.0), 0.0, 0.0);
+}
+
+#[test]
+fn binary_sentences_are_counted_not_parsed() {
+    // Checksummed, but 0xE9 is no UTF-8 on its own.
+    let mut body = b"GPGGA,".to_vec();
+    body.extend([0xE9; 60]);
+    let checksum = body.iter().fold(0u8, |acc, b| acc ^ b);
+    let mut log = b"$".to_vec();
+    log.extend(&body);
+    log.extend(format!("*{checksum:02X}\r\n").bytes());
+    let summary = run(&log);
+
+    assert_eq!(summary.sentences.parse_errors, 1);
+    assert_eq!(summary.sentences.good, 0);
+    assert_eq!(summary.fixes, 0);
+}
+
+#[test]
+fn noise_between_sentences_is_ignored() {
+    let log = b"\x00\xff garbage\r\n$GPGGA,120000.00,4531.40700,N,12240.57260,W,1,08,0.9,52.3,M,-19.6,M,,*68\r\n";
+    let summary = run(log);
+
+    assert_eq!(summary.sentences.good, 1);
+    assert_eq!(summary.fixes, 1);
+}
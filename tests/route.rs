@@ -0,0 +1,117 @@
+mod common;
+
+use common::{assert_close, offset};
+use hijo::gps::route::{CHUNK_ENCODED_LEN, MAX_ROUTE_POINTS, Route, RouteError, RouteFollower};
+
+/// 1 km north then 1 km east, a point every 100 m.
+fn corner() -> Route {
+    let mut route = Route::new();
+    for i in 0..=10 {
+        route.push(offset(f64::from(i) * 100.0, 0.0)).unwrap();
+    }
+    for i in 1..=10 {
+        route.push(offset(1000.0, f64::from(i) * 100.0)).unwrap();
+    }
+    route
+}
+
+#[test]
+fn push_drops_crowded_points_and_stops_when_full() {
+    let mut route = Route::new();
+    assert_eq!(route.push(offset(0.0, 0.0)), Ok(true));
+    assert_eq!(route.push(offset(3.0, 0.0)), Ok(false));
+    assert_eq!(route.push(offset(10.0, 0.0)), Ok(true));
+    assert_close(route.length_m(), 10.0, 0.01);
+
+    for i in 2..MAX_ROUTE_POINTS {
+        route.push(offset(i as f64 * 10.0, 0.0)).unwrap();
+    }
+    assert_eq!(route.push(offset(1e5, 0.0)), Err(RouteError::Full));
+}
+
+#[test]
+fn chunks_round_trip() {
+    let route = corner();
+    assert_eq!(route.chunk_count(), 2);
+
+    let mut loaded = Route::new();
+    let mut buf = [0u8; CHUNK_ENCODED_LEN];
+    for index in 0..route.chunk_count() {
+        loaded
+            .decode_chunk(route.encode_chunk(index, &mut buf))
+            .unwrap();
+    }
+    assert_eq!(loaded.points(), route.points());
+    assert_close(loaded.length_m(), 2000.0, 0.5);
+    assert_eq!(loaded.decode_chunk(&buf[..7]), Err(RouteError::Corrupt));
+}
+
+#[test]
+fn progress_beside_the_first_leg() {
+    let route = corner();
+    let mut follower = RouteFollower::new();
+
+    let progress = follower.update(&route, offset(450.0, 30.0)).unwrap();
+    assert_eq!(progress.segment, 4);
+    assert_close(progress.cross_track_m, 30.0, 0.1);
+    assert_close(progress.off_route_m, 30.0, 0.1);
+    assert_close(progress.along_track_m, 450.0, 0.5);
+    assert_close(progress.remaining_m, 1550.0, 0.5);
+    assert!(!progress.off_course);
+
+    let turn = progress.next_turn.unwrap();
+    assert_close(turn.distance_m, 550.0, 0.5);
+    assert_close(turn.angle_deg, 90.0, 0.1);
+
+    // Left of the line, past the corner there is nothing left to turn.
+    let progress = follower.update(&route, offset(1020.0, 500.0)).unwrap();
+    assert_close(progress.cross_track_m, -20.0, 0.1);
+    assert_close(progress.remaining_m, 500.0, 0.5);
+    assert_eq!(progress.next_turn, None);
+}
+
+#[test]
+fn off_course_alert_has_hysteresis() {
+    let route = corner();
+    let mut follower = RouteFollower::new();
+    follower.alert_m = 25.0;
+
+    let mut off = |east: f64| {
+        follower
+            .update(&route, offset(300.0, east))
+            .unwrap()
+            .off_course
+    };
+    assert!(!off(24.0));
+    assert!(off(30.0));
+    assert!(off(22.0));
+    assert!(!off(18.0));
+}
+
+#[test]
+fn out_and_back_stays_on_the_leg_being_ridden() {
+    // Up and back down 6 m apart, ridden down the middle.
+    let mut route = Route::new();
+    for i in 0..=10 {
+        route.push(offset(f64::from(i) * 100.0, 0.0)).unwrap();
+    }
+    for i in (0..=10).rev() {
+        route.push(offset(f64::from(i) * 100.0, 6.0)).unwrap();
+    }
+    let mut follower = RouteFollower::new();
+
+    let mut along = 0.0;
+    for step in (0..=1000).step_by(50).chain((0..=950).rev().step_by(50)) {
+        let progress = follower
+            .update(&route, offset(f64::from(step), 3.0))
+            .unwrap();
+        assert!(progress.along_track_m >= along, "went back at {step}");
+        along = progress.along_track_m;
+    }
+    assert_close(route.length_m() - along, 0.0, 0.5);
+
+    // Without the history the start wins.
+    follower.reset();
+    let progress = follower.update(&route, offset(200.0, 3.0)).unwrap();
+    assert_close(progress.along_track_m, 200.0, 0.5);
+}
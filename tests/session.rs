@@ -0,0 +1,34 @@
+mod common;
+
+use common::time;
+use hijo::gps::session::SessionStats;
+
+#[test]
+fn stops_and_pauses_only_count_as_elapsed() {
+    let mut stats = SessionStats::new();
+    stats.update(time(100), 0.0, true);
+    stats.update(time(110), 3.0, true);
+    stats.update(time(120), 0.0, true);
+    stats.update(time(130), 9.0, false);
+    stats.update(time(140), 2.0, true);
+
+    assert_eq!(stats.elapsed_secs(), 40);
+    assert_eq!(stats.moving_secs(), 20);
+    // The paused 9 m/s fix is left out of the maximum.
+    assert_eq!(stats.max_speed_mps, 3.0);
+    assert_eq!(stats.average_speed_mps(50.0), 2.5);
+}
+
+#[test]
+fn clock_wraps_at_midnight() {
+    let mut stats = SessionStats::new();
+    stats.update(time(86_398), 1.0, true);
+    stats.update(time(2), 1.0, true);
+
+    assert_eq!(stats.elapsed_secs(), 4);
+    assert_eq!(stats.moving_secs(), 4);
+
+    stats.reset();
+    assert_eq!(stats.elapsed_ms, 0);
+    assert_eq!(stats.average_speed_mps(10.0), 0.0);
+}
@@ -0,0 +1,590 @@
+mod common;
+
+use chrono::NaiveDate;
+use common::fit::{self, EVENT, LAP, RECORD, SESSION};
+use embassy_futures::block_on;
+use hijo::{
+    gps::{
+        fns::LatLonAlt, power::PowerProfile, reader::GpsReaderStats, route::Route,
+        waypoint::Waypoint,
+    },
+    shell::{
+        backend::{BackendError, GpsStatus, SettingInfo, ShellBackend},
+        command::{Command, DumpFormat, ParseError, parse_command},
+        runner::Shell,
+    },
+    track::{
+        log::{SessionEntry, SessionSummary},
+        record::TrackPoint,
+    },
+    utils::units::Units,
+};
+
+const WAYPOINT_SLOTS: usize = 2;
+
+struct FakeSetting {
+    id: u8,
+    label: &'static str,
+    options: &'static [&'static str],
+    selected: usize,
+}
+
+impl FakeSetting {
+    fn info(&self) -> SettingInfo {
+        SettingInfo {
+            id: self.id,
+            label: self.label,
+            value: self.options[self.selected],
+        }
+    }
+}
+
+/// Sessions, settings, waypoints and a route kept in plain vectors.
+#[derive(Default)]
+struct FakeBackend {
+    sessions: Vec<(u16, Vec<SessionEntry>)>,
+    active_session: Option<u16>,
+    settings: Vec<FakeSetting>,
+    waypoints: [Option<Waypoint>; WAYPOINT_SLOTS],
+    route: Vec<LatLonAlt>,
+    rebooted: bool,
+}
+
+impl FakeBackend {
+    fn session(&self, session: u16) -> Option<&[SessionEntry]> {
+        self.sessions
+            .iter()
+            .find(|(id, _)| *id == session)
+            .map(|(_, entries)| entries.as_slice())
+    }
+}
+
+impl ShellBackend for &mut FakeBackend {
+    async fn sessions(
+        &mut self,
+        mut f: impl AsyncFnMut(SessionSummary),
+    ) -> Result<(), BackendError> {
+        for (session, entries) in &self.sessions {
+            let points = entries
+                .iter()
+                .filter(|entry| matches!(entry, SessionEntry::Point(_)))
+                .count() as u32;
+            let power = entries
+                .iter()
+                .rev()
+                .find_map(|entry| match entry {
+                    SessionEntry::Power(profile) => Some(*profile),
+                    _ => None,
+                })
+                .unwrap_or_default();
+            f(SessionSummary {
+                session: *session,
+                points,
+                closed: self.active_session != Some(*session),
+                power,
+            })
+            .await;
+        }
+        Ok(())
+    }
+
+    async fn active_session(&mut self) -> Option<u16> {
+        self.active_session
+    }
+
+    async fn session_points(
+        &mut self,
+        session: u16,
+        mut f: impl AsyncFnMut(&TrackPoint),
+    ) -> Result<bool, BackendError> {
+        let Some(entries) = self.session(session) else {
+            return Ok(false);
+        };
+        for entry in entries {
+            if let SessionEntry::Point(point) = entry {
+                f(point).await;
+            }
+        }
+        Ok(true)
+    }
+
+    async fn session_entries(
+        &mut self,
+        session: u16,
+        mut f: impl AsyncFnMut(&SessionEntry),
+    ) -> Result<bool, BackendError> {
+        let Some(entries) = self.session(session) else {
+            return Ok(false);
+        };
+        for entry in entries {
+            f(entry).await;
+        }
+        Ok(true)
+    }
+
+    async fn delete_session(&mut self, session: u16) -> Result<(), BackendError> {
+        if self.active_session == Some(session) {
+            return Err(BackendError::SessionActive);
+        }
+        let len = self.sessions.len();
+        self.sessions.retain(|(id, _)| *id != session);
+        if self.sessions.len() == len {
+            return Err(BackendError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn setting(&mut self, id: u8) -> Result<SettingInfo, BackendError> {
+        self.settings
+            .iter()
+            .find(|setting| setting.id == id)
+            .map(FakeSetting::info)
+            .ok_or(BackendError::NotFound)
+    }
+
+    async fn set_setting(&mut self, id: u8, value: &str) -> Result<SettingInfo, BackendError> {
+        let setting = self
+            .settings
+            .iter_mut()
+            .find(|setting| setting.id == id)
+            .ok_or(BackendError::NotFound)?;
+        setting.selected = setting
+            .options
+            .iter()
+            .position(|option| *option == value)
+            .ok_or(BackendError::InvalidValue)?;
+        Ok(setting.info())
+    }
+
+    async fn waypoints(
+        &mut self,
+        mut f: impl AsyncFnMut(u8, &Waypoint),
+    ) -> Result<(), BackendError> {
+        for (slot, waypoint) in self.waypoints.iter().enumerate() {
+            if let Some(waypoint) = waypoint {
+                f(slot as u8, waypoint).await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn save_waypoint(&mut self, waypoint: Waypoint) -> Result<u8, BackendError> {
+        let slot = self
+            .waypoints
+            .iter()
+            .position(Option::is_none)
+            .ok_or(BackendError::Full)?;
+        self.waypoints[slot] = Some(waypoint);
+        Ok(slot as u8)
+    }
+
+    async fn delete_waypoint(&mut self, slot: u8) -> Result<(), BackendError> {
+        self.waypoints
+            .get_mut(usize::from(slot))
+            .and_then(Option::take)
+            .map(|_| ())
+            .ok_or(BackendError::NotFound)
+    }
+
+    async fn route_points(
+        &mut self,
+        mut f: impl AsyncFnMut(&LatLonAlt),
+    ) -> Result<(), BackendError> {
+        for point in &self.route {
+            f(point).await;
+        }
+        Ok(())
+    }
+
+    async fn save_route(&mut self, route: &Route) -> Result<(), BackendError> {
+        self.route = route.points().to_vec();
+        Ok(())
+    }
+
+    async fn delete_route(&mut self) -> Result<(), BackendError> {
+        self.route.clear();
+        Ok(())
+    }
+
+    async fn gps_status(&mut self) -> GpsStatus {
+        GpsStatus {
+            fix: None,
+            position: None,
+            speed_mps: 0.0,
+            units: Units::default(),
+            dop: None,
+            active_satellites: None,
+            satellites_in_view: Some(9),
+            gps_stats: GpsReaderStats::default(),
+            is_recording: self.active_session.is_some(),
+            is_paused: false,
+        }
+    }
+
+    async fn reboot(&mut self) {
+        self.rebooted = true;
+    }
+}
+
+fn point(secs: u32) -> TrackPoint {
+    TrackPoint {
+        lat: 51.5 + f64::from(secs) * 1e-5,
+        lon: -0.125,
+        alt: 30.0,
+        hdop: 0.9,
+        time_ms: 36_000_000 + secs * 1000,
+        date: NaiveDate::from_ymd_opt(2024, 5, 18),
+    }
+}
+
+fn backend() -> FakeBackend {
+    FakeBackend {
+        sessions: vec![
+            (
+                1,
+                vec![
+                    SessionEntry::Point(point(0)),
+                    SessionEntry::Point(point(1)),
+                    SessionEntry::Pause {
+                        time_ms: 36_001_500,
+                    },
+                    SessionEntry::Resume {
+                        time_ms: 36_009_000,
+                    },
+                    SessionEntry::Point(point(10)),
+                ],
+            ),
+            (
+                2,
+                vec![
+                    SessionEntry::Power(PowerProfile::Every30s),
+                    SessionEntry::Point(point(0)),
+                ],
+            ),
+        ],
+        active_session: Some(2),
+        settings: vec![FakeSetting {
+            id: 4,
+            label: "Units",
+            options: &["Metric", "Imperial"],
+            selected: 0,
+        }],
+        ..FakeBackend::default()
+    }
+}
+
+/// Feeds `input` to a shell over `backend` and returns everything it wrote.
+fn run(backend: &mut FakeBackend, input: &str) -> String {
+    let mut out = Vec::new();
+    let mut shell = Shell::new(input.as_bytes(), &mut out, backend);
+    block_on(shell.run());
+    drop(shell);
+    String::from_utf8(out).unwrap()
+}
+
+fn execute(backend: &mut FakeBackend, line: &str) -> String {
+    let mut out = Vec::new();
+    let mut shell = Shell::new(&b""[..], &mut out, backend);
+    block_on(shell.execute(line)).unwrap();
+    drop(shell);
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn parses_commands() {
+    assert_eq!(parse_command("  ls "), Ok(Command::List));
+    assert_eq!(
+        parse_command("dump 3 csv 2024-05-01"),
+        Ok(Command::Dump {
+            session: 3,
+            format: DumpFormat::Csv,
+            date: NaiveDate::from_ymd_opt(2024, 5, 1),
+        })
+    );
+    assert_eq!(
+        parse_command("dump 3 gpx"),
+        Ok(Command::Dump {
+            session: 3,
+            format: DumpFormat::Gpx,
+            date: None,
+        })
+    );
+    assert_eq!(
+        parse_command("dump 3 fit"),
+        Ok(Command::Dump {
+            session: 3,
+            format: DumpFormat::Fit,
+            date: None,
+        })
+    );
+    assert_eq!(parse_command("wpt"), Ok(Command::ListWaypoints));
+    assert_eq!(
+        parse_command("set 4 Imperial"),
+        Ok(Command::Set {
+            id: 4,
+            value: "Imperial".try_into().unwrap(),
+        })
+    );
+}
+
+#[test]
+fn rejects_bad_ids() {
+    for line in [
+        "dump x gpx",
+        "dump -1 gpx",
+        "dump 70000 gpx",
+        "rm 1.5",
+        "get 300",
+    ] {
+        assert_eq!(
+            parse_command(line),
+            Err(ParseError::InvalidArgument),
+            "{line}"
+        );
+    }
+    for line in ["dump", "rm", "get", "set", "wpt rm"] {
+        assert_eq!(
+            parse_command(line),
+            Err(ParseError::MissingArgument),
+            "{line}"
+        );
+    }
+}
+
+#[test]
+fn rejects_unknown_formats() {
+    assert_eq!(
+        parse_command("dump 3 kml"),
+        Err(ParseError::InvalidArgument)
+    );
+    assert_eq!(parse_command("dump 3"), Err(ParseError::MissingArgument));
+    assert_eq!(
+        parse_command("dump 3 gpx 2024-13-01"),
+        Err(ParseError::InvalidArgument)
+    );
+    assert_eq!(
+        parse_command("wpt export"),
+        Err(ParseError::InvalidArgument)
+    );
+    assert_eq!(
+        parse_command("route show"),
+        Err(ParseError::InvalidArgument)
+    );
+    assert_eq!(parse_command("frobnicate"), Err(ParseError::UnknownCommand));
+    assert_eq!(parse_command(" \t"), Err(ParseError::Empty));
+}
+
+#[test]
+fn rejects_extra_arguments() {
+    for line in [
+        "ls all",
+        "dump 3 gpx 2024-05-01 now",
+        "rm 3 4",
+        "set 4 Imperial please",
+        "wpt rm 1 2",
+        "route rm all",
+        "status now",
+        "reboot now",
+    ] {
+        assert_eq!(
+            parse_command(line),
+            Err(ParseError::InvalidArgument),
+            "{line}"
+        );
+    }
+    assert_eq!(
+        parse_command("set 4 a-value-longer-than-sixteen"),
+        Err(ParseError::InvalidArgument)
+    );
+}
+
+#[test]
+fn lists_sessions() {
+    let mut backend = backend();
+    assert_eq!(
+        execute(&mut backend, "ls"),
+        "1 3 points\r\n2 1 points open 1/30s\r\nOK\r\n"
+    );
+}
+
+#[test]
+fn parse_errors_are_reported() {
+    let mut backend = backend();
+    assert_eq!(
+        execute(&mut backend, "dump 1 kml"),
+        "ERR invalid argument\r\n"
+    );
+    assert_eq!(
+        execute(&mut backend, "frobnicate"),
+        "ERR unknown command\r\n"
+    );
+    assert_eq!(execute(&mut backend, ""), "");
+}
+
+#[test]
+fn dumps_a_session_as_gpx() {
+    let mut backend = backend();
+    let out = execute(&mut backend, "dump 1 gpx");
+    assert!(out.starts_with("<?xml"), "{out}");
+    assert!(out.contains("<name>HiJo session 1</name>"), "{out}");
+    assert_eq!(out.matches("<trkseg>").count(), 2, "{out}");
+    assert_eq!(out.matches("<trkpt ").count(), 3, "{out}");
+    assert!(
+        out.contains("<time>2024-05-18T10:00:10.000Z</time>"),
+        "{out}"
+    );
+    assert!(out.ends_with("</gpx>\nOK\r\n"), "{out}");
+}
+
+#[test]
+fn dumps_a_session_as_csv() {
+    let mut backend = backend();
+    let out = execute(&mut backend, "dump 1 csv");
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 5, "{out}");
+    assert_eq!(lines[0], "date,time_utc,lat,lon,alt_m,hdop");
+    assert!(lines[1].starts_with("2024-05-18,10:00:00"), "{out}");
+    assert_eq!(lines[4], "OK");
+}
+
+#[test]
+fn dumps_a_closed_session_as_fit() {
+    let mut backend = backend();
+    let mut out = Vec::new();
+    let mut shell = Shell::new(&b""[..], &mut out, &mut backend);
+    block_on(shell.execute("dump 1 fit")).unwrap();
+    drop(shell);
+    let file = out.strip_suffix(b"OK\r\n").expect("OK after the file");
+    let messages = fit::decode(file);
+    assert_eq!(fit::of(&messages, RECORD).len(), 3);
+    assert_eq!(fit::of(&messages, EVENT).len(), 4);
+    assert_eq!(fit::of(&messages, LAP).len(), 1);
+    let session = fit::of(&messages, SESSION)[0];
+    // 10 s from the first point to the last, 7.5 s of it paused.
+    assert_eq!(session.field(7), 10_000);
+    assert_eq!(session.field(8), 2_500);
+}
+
+#[test]
+fn the_recording_session_is_not_dumped_as_fit() {
+    let mut backend = backend();
+    assert_eq!(
+        execute(&mut backend, "dump 2 fit"),
+        "ERR session is recording\r\n"
+    );
+    assert!(execute(&mut backend, "dump 2 gpx").ends_with("OK\r\n"));
+}
+
+#[test]
+fn missing_sessions_are_not_found() {
+    let mut backend = backend();
+    assert_eq!(execute(&mut backend, "dump 9 gpx"), "ERR not found\r\n");
+    assert_eq!(execute(&mut backend, "dump 9 fit"), "ERR not found\r\n");
+    assert_eq!(execute(&mut backend, "rm 9"), "ERR not found\r\n");
+}
+
+#[test]
+fn the_recording_session_is_not_deleted() {
+    let mut backend = backend();
+    assert_eq!(
+        execute(&mut backend, "rm 2"),
+        "ERR session is recording\r\n"
+    );
+    assert_eq!(execute(&mut backend, "rm 1"), "OK\r\n");
+    assert_eq!(backend.sessions.len(), 1);
+}
+
+#[test]
+fn gets_and_sets_settings() {
+    let mut backend = backend();
+    assert_eq!(execute(&mut backend, "get 4"), "4 Units = Metric\r\nOK\r\n");
+    assert_eq!(
+        execute(&mut backend, "set 4 Imperial"),
+        "4 Units = Imperial\r\nOK\r\n"
+    );
+    assert_eq!(
+        execute(&mut backend, "set 4 Nautical"),
+        "ERR invalid value\r\n"
+    );
+    assert_eq!(execute(&mut backend, "get 5"), "ERR not found\r\n");
+    assert_eq!(backend.settings[0].selected, 1);
+}
+
+#[test]
+fn reads_lines_from_the_port() {
+    let mut backend = backend();
+    let out = run(&mut backend, "get 4\r\n\r\nrm 1\n");
+    assert_eq!(out, "4 Units = Metric\r\nOK\r\nOK\r\n");
+    let long = format!("get {}\r\nget 4\r\n", "4".repeat(80));
+    assert_eq!(
+        run(&mut backend, &long),
+        "ERR line too long\r\n4 Units = Metric\r\nOK\r\n"
+    );
+}
+
+#[test]
+fn loads_waypoints_until_the_slots_run_out() {
+    let mut backend = backend();
+    let out = run(
+        &mut backend,
+        concat!(
+            "wpt load\r\n",
+            "<gpx><wpt lat=\"1\" lon=\"2\"><name>A</name></wpt>",
+            "<wpt lat=\"3\" lon=\"4\"><name>B</name></wpt>",
+            "<wpt lat=\"5\" lon=\"6\"><name>C</name></wpt></gpx>",
+            "wpt ls\r\n",
+        ),
+    );
+    assert_eq!(
+        out,
+        concat!(
+            "send GPX, end with </gpx> or ctrl-c\r\n",
+            "saved 0\r\nsaved 1\r\n",
+            "2 saved 1 skipped\r\nERR full\r\n",
+            "0 A 1.0000000 2.0000000 0.0\r\n",
+            "1 B 3.0000000 4.0000000 0.0\r\nOK\r\n",
+        )
+    );
+}
+
+#[test]
+fn a_route_needs_two_points() {
+    let mut backend = backend();
+    let out = run(
+        &mut backend,
+        "route load\r\n<gpx><rtept lat=\"1\" lon=\"2\"/></gpx>route\r\n",
+    );
+    assert_eq!(
+        out,
+        concat!(
+            "send GPX, end with </gpx> or ctrl-c\r\n",
+            "1 saved 0 skipped\r\nERR invalid value\r\n",
+            "0 points 0 m\r\nOK\r\n",
+        )
+    );
+    assert!(backend.route.is_empty());
+}
+
+#[test]
+fn ctrl_c_abandons_an_upload() {
+    let mut backend = backend();
+    let out = run(
+        &mut backend,
+        "route load\r\n<gpx><rtept lat=\"1\"\x03ls\r\n",
+    );
+    assert_eq!(
+        out,
+        concat!(
+            "send GPX, end with </gpx> or ctrl-c\r\n",
+            "ERR aborted\r\n",
+            "1 3 points\r\n2 1 points open 1/30s\r\nOK\r\n",
+        )
+    );
+}
+
+#[test]
+fn reboot_answers_first() {
+    let mut backend = backend();
+    assert_eq!(execute(&mut backend, "reboot"), "OK\r\n");
+    assert!(backend.rebooted);
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use common::assert_close;
+use embassy_futures::block_on;
+use hijo::gps::{
+    reader::{Constellation, FixMode, GpsReader, SatelliteInfo},
+    sky::{GnssStatus, sky_position},
+};
+
+/// `$<body>*HH` with the checksum filled in.
+fn sentence(body: &str) -> String {
+    let checksum = body.bytes().fold(0u8, |acc, b| acc ^ b);
+    format!("${body}*{checksum:02X}\r\n")
+}
+
+fn status_after(bodies: &[&str]) -> GnssStatus {
+    let log: String = bodies.iter().map(|body| sentence(body)).collect();
+    let mut reader = GpsReader::new(log.as_bytes());
+    let mut status = GnssStatus::new();
+    block_on(async {
+        while let Some(out) = reader.next().await {
+            status.update(&out);
+        }
+    });
+    status
+}
+
+fn satellite(elevation: f32, azimuth: f32) -> SatelliteInfo {
+    SatelliteInfo {
+        constellation: Constellation::Gps,
+        prn: 1,
+        elevation: Some(elevation),
+        azimuth: Some(azimuth),
+        snr: Some(40.0),
+        in_use: false,
+    }
+}
+
+#[test]
+fn gsa_and_gsv_fill_the_status() {
+    let status = status_after(&[
+        "GPGSA,A,3,12,05,,,,,,,,,,,1.8,1.0,1.1",
+        "GPGSV,2,1,05,05,40,083,46,12,71,318,42,29,11,225,,31,22,110,33",
+        "GPGSV,2,2,05,02,60,010,38",
+        "GLGSV,1,1,02,70,35,050,30,65,10,300,25",
+    ]);
+    assert_eq!(status.fix_mode, Some(FixMode::Fix3D));
+    assert_eq!(status.in_use(), 2);
+    assert_eq!(status.in_view(), 7);
+
+    // Satellite 29 has no signal and gets no bar; GPS before GLONASS.
+    let bars: Vec<_> = status
+        .by_constellation()
+        .map(|sat| (sat.constellation, sat.prn, sat.in_use))
+        .collect();
+    assert_eq!(
+        bars,
+        [
+            (Constellation::Gps, 2, false),
+            (Constellation::Gps, 5, true),
+            (Constellation::Gps, 12, true),
+            (Constellation::Gps, 31, false),
+            (Constellation::Glonass, 65, false),
+            (Constellation::Glonass, 70, false),
+        ]
+    );
+}
+
+#[test]
+fn fix_mode_follows_gsa() {
+    let status = status_after(&["GPGSA,A,1,,,,,,,,,,,,,,,"]);
+    assert_eq!(status.fix_mode, Some(FixMode::NoFix));
+    assert_eq!(status.in_use(), 0);
+
+    let status = status_after(&["GPGSA,A,2,05,12,29,,,,,,,,,,2.5,2.3,1.0"]);
+    assert_eq!(status.fix_mode, Some(FixMode::Fix2D));
+    assert_eq!(status.in_use(), 3);
+}
+
+#[test]
+fn sky_plot_puts_zenith_in_the_middle_and_north_up() {
+    let (x, y) = sky_position(&satellite(90.0, 123.0), 20.0).unwrap();
+    assert_close(x, 0.0, 1e-9);
+    assert_close(y, 0.0, 1e-9);
+
+    let (x, y) = sky_position(&satellite(0.0, 0.0), 20.0).unwrap();
+    assert_close(x, 0.0, 1e-9);
+    assert_close(y, -20.0, 1e-9);
+
+    let (x, y) = sky_position(&satellite(45.0, 90.0), 20.0).unwrap();
+    assert_close(x, 10.0, 1e-9);
+    assert!(y.abs() < 1e-9);
+
+    let mut unplaced = satellite(45.0, 90.0);
+    unplaced.azimuth = None;
+    assert_eq!(sky_position(&unplaced, 20.0), None);
+}
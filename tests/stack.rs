@@ -0,0 +1,54 @@
+mod common;
+
+use chrono::NaiveTime;
+use common::fix;
+use hijo::gps::{reader::GpsReaderResults, receiver::UpdateRate, stack::GeoStack};
+
+#[test]
+fn fixes_keep_coming_past_utc_midnight() {
+    let mut stack = GeoStack::new();
+    // Walking north at 1.5 m/s from 23:59:50 to 00:00:10.
+    for i in 0..=20u32 {
+        let secs = (86_390 + i) % 86_400;
+        assert!(
+            stack.add_coords(fix(secs, f64::from(i) * 1.5), true),
+            "fix {i} rejected"
+        );
+    }
+    assert!(stack.total_distance > 20.0, "{}", stack.total_distance);
+    assert!(stack.current_speed_mps > 1.0, "{}", stack.current_speed_mps);
+}
+
+#[test]
+fn a_repeated_timestamp_is_still_rejected() {
+    let mut stack = GeoStack::new();
+    assert!(stack.add_coords(fix(86_399, 0.0), true));
+    assert!(stack.add_coords(fix(0, 1.5), true));
+    assert!(!stack.add_coords(fix(0, 1.5), true));
+}
+
+#[test]
+fn the_time_gate_follows_the_fix_rate() {
+    // Walking north at 1.5 m/s, fixes at 5 Hz.
+    let fix_at = |i: u32| GpsReaderResults {
+        timestamp: NaiveTime::from_num_seconds_from_midnight_opt(
+            36_000 + i / 5,
+            i % 5 * 200_000_000,
+        ),
+        ..fix(0, f64::from(i) * 0.3)
+    };
+    let accepted = |stack: &mut GeoStack| {
+        (0..50)
+            .filter(|&i| stack.add_coords(fix_at(i), true))
+            .count()
+    };
+
+    // The 1 Hz default keeps one fix a second.
+    assert_eq!(accepted(&mut GeoStack::new()), 10);
+
+    let mut stack = GeoStack::new();
+    stack.min_time_interval_ms = UpdateRate::Hz5.interval_ms().into();
+    assert_eq!(accepted(&mut stack), 50);
+    // A repeated epoch is still dropped.
+    assert!(!stack.add_coords(fix_at(49), true));
+}
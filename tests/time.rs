@@ -0,0 +1,111 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use hijo::{
+    gps::reader::GpsReaderResults,
+    utils::time::{DstRule, Hms, TimeZone, utc_datetime},
+};
+
+fn utc(year: i32, month: u32, day: u32, hour: u32, min: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(year, month, day)
+        .unwrap()
+        .and_hms_opt(hour, min, 0)
+        .unwrap()
+}
+
+#[test]
+fn us_eastern_switches_at_two_local() {
+    let eastern = TimeZone::new(-300, DstRule::Us);
+
+    // 2024: DST from March 10 to November 3.
+    assert_eq!(
+        eastern.to_local(utc(2024, 3, 10, 6, 59)),
+        utc(2024, 3, 10, 1, 59)
+    );
+    assert_eq!(
+        eastern.to_local(utc(2024, 3, 10, 7, 0)),
+        utc(2024, 3, 10, 3, 0)
+    );
+    assert_eq!(
+        eastern.to_local(utc(2024, 11, 3, 5, 59)),
+        utc(2024, 11, 3, 1, 59)
+    );
+    assert_eq!(
+        eastern.to_local(utc(2024, 11, 3, 6, 0)),
+        utc(2024, 11, 3, 1, 0)
+    );
+    assert_eq!(
+        eastern.to_local(utc(2024, 1, 15, 12, 0)),
+        utc(2024, 1, 15, 7, 0)
+    );
+}
+
+#[test]
+fn eu_switches_at_one_utc() {
+    let central = TimeZone::new(60, DstRule::Eu);
+
+    // 2024: DST from March 31 to October 27.
+    assert_eq!(
+        central.to_local(utc(2024, 3, 31, 0, 59)),
+        utc(2024, 3, 31, 1, 59)
+    );
+    assert_eq!(
+        central.to_local(utc(2024, 3, 31, 1, 0)),
+        utc(2024, 3, 31, 3, 0)
+    );
+    assert_eq!(
+        central.to_local(utc(2024, 10, 27, 0, 59)),
+        utc(2024, 10, 27, 2, 59)
+    );
+    assert_eq!(
+        central.to_local(utc(2024, 10, 27, 1, 0)),
+        utc(2024, 10, 27, 2, 0)
+    );
+}
+
+#[test]
+fn fractional_offsets_cross_midnight() {
+    let india = TimeZone::new(330, DstRule::None);
+    let nepal = TimeZone::new(345, DstRule::None);
+    let newfoundland = TimeZone::new(-210, DstRule::Us);
+
+    assert_eq!(
+        india.to_local(utc(2024, 12, 31, 20, 0)),
+        utc(2025, 1, 1, 1, 30)
+    );
+    assert_eq!(
+        nepal.to_local(utc(2024, 6, 1, 0, 0)),
+        utc(2024, 6, 1, 5, 45)
+    );
+    assert_eq!(
+        newfoundland.to_local(utc(2024, 7, 1, 2, 0)),
+        utc(2024, 6, 30, 23, 30)
+    );
+    assert_eq!(
+        TimeZone::UTC.to_local(utc(2024, 7, 1, 2, 0)),
+        utc(2024, 7, 1, 2, 0)
+    );
+}
+
+#[test]
+fn fix_needs_date_and_time() {
+    let mut results = GpsReaderResults {
+        lat: None,
+        lon: None,
+        alt: None,
+        hdop: None,
+        timestamp: Some(utc(2024, 5, 4, 13, 37).time()),
+        date: None,
+        speed_knots: None,
+        course: None,
+    };
+    assert_eq!(utc_datetime(&results), None);
+
+    results.date = NaiveDate::from_ymd_opt(2024, 5, 4);
+    assert_eq!(utc_datetime(&results), Some(utc(2024, 5, 4, 13, 37)));
+}
+
+#[test]
+fn durations_drop_the_hour_when_short() {
+    assert_eq!(format!("{}", Hms(59)), "0:59");
+    assert_eq!(format!("{}", Hms(3599)), "59:59");
+    assert_eq!(format!("{}", Hms(3600 + 62)), "1:01:02");
+}
@@ -0,0 +1,232 @@
+mod common;
+
+use chrono::NaiveDate;
+use common::flash::RamFlash;
+use embassy_futures::block_on;
+use hijo::track::{
+    log::{SessionEntry, SessionSummary, TrackEvent, TrackLog, TrackLogError},
+    record::{MAX_RECORD_LEN, RecordError, TrackPoint, TrackRecord},
+};
+
+type Log = TrackLog<RamFlash>;
+
+fn open(flash: &RamFlash) -> Log {
+    let range = 0..flash.len();
+    block_on(TrackLog::new(flash.clone(), range)).expect("open track log")
+}
+
+fn point(i: u32) -> TrackPoint {
+    TrackPoint {
+        lat: 51.5 + f64::from(i) * 1e-5,
+        lon: -0.12,
+        alt: 30.0 + i as f32,
+        hdop: 0.9,
+        time_ms: 36_000_000 + i * 1000,
+        date: NaiveDate::from_ymd_opt(2024, 5, 18),
+    }
+}
+
+fn sessions(log: &mut Log) -> Vec<SessionSummary> {
+    let mut out = Vec::new();
+    block_on(log.sessions(async |summary| out.push(summary))).unwrap();
+    out
+}
+
+fn points(log: &mut Log, session: u16) -> Option<Vec<TrackPoint>> {
+    let mut out = Vec::new();
+    let found = block_on(log.for_each_point(session, async |point| out.push(*point))).unwrap();
+    found.then_some(out)
+}
+
+fn record(log: &mut Log, count: u32) -> u16 {
+    block_on(async {
+        let session = log.start_session().await.unwrap();
+        for i in 0..count {
+            log.push_point(&point(i)).await.unwrap();
+        }
+        log.end_session().await.unwrap();
+        session
+    })
+}
+
+#[test]
+fn empty_flash_opens_with_no_sessions() {
+    let flash = RamFlash::new(4);
+    let mut log = open(&flash);
+    assert_eq!(log.last_session(), 0);
+    assert_eq!(log.active_session(), None);
+    assert_eq!(sessions(&mut log), []);
+}
+
+#[test]
+fn start_point_stop() {
+    let flash = RamFlash::new(4);
+    let mut log = open(&flash);
+
+    let session = block_on(log.start_session()).unwrap();
+    assert_eq!(session, 1);
+    assert_eq!(log.active_session(), Some(1));
+    // Starting again keeps the open session.
+    assert_eq!(block_on(log.start_session()).unwrap(), 1);
+    for i in 0..3 {
+        block_on(log.push_point(&point(i))).unwrap();
+    }
+    assert_eq!(block_on(log.end_session()).unwrap(), Some(1));
+    assert_eq!(log.active_session(), None);
+    assert_eq!(block_on(log.end_session()).unwrap(), None);
+
+    // Nothing is recorded outside a session.
+    block_on(log.push_point(&point(9))).unwrap();
+
+    assert_eq!(
+        points(&mut log, 1),
+        Some(vec![point(0), point(1), point(2)])
+    );
+    assert_eq!(points(&mut log, 2), None);
+    let summaries = sessions(&mut log);
+    assert_eq!(summaries.len(), 1);
+    assert_eq!(summaries[0].session, 1);
+    assert_eq!(summaries[0].points, 3);
+    assert!(summaries[0].closed);
+}
+
+#[test]
+fn reset_closes_the_open_session() {
+    let flash = RamFlash::new(4);
+    let mut log = open(&flash);
+    record(&mut log, 2);
+    block_on(async {
+        log.start_session().await.unwrap();
+        log.push_point(&point(0)).await.unwrap();
+        log.push_point(&point(1)).await.unwrap();
+    });
+    // Power lost mid-recording.
+    drop(log);
+
+    let mut log = open(&flash);
+    assert_eq!(log.active_session(), None);
+    assert_eq!(log.last_session(), 2);
+    let summaries = sessions(&mut log);
+    assert_eq!(summaries.len(), 2);
+    assert_eq!(summaries[1].session, 2);
+    assert_eq!(summaries[1].points, 2);
+    assert!(summaries[1].closed);
+
+    // Numbering carries on and the next session is complete on its own.
+    assert_eq!(record(&mut log, 1), 3);
+    drop(log);
+    let mut log = open(&flash);
+    assert_eq!(log.last_session(), 3);
+    assert_eq!(sessions(&mut log).len(), 3);
+    assert_eq!(points(&mut log, 2), Some(vec![point(0), point(1)]));
+}
+
+#[test]
+fn flush_writes_nothing() {
+    let flash = RamFlash::new(4);
+    let mut log = open(&flash);
+    block_on(async {
+        log.handle(TrackEvent::Flush).await.unwrap();
+        log.handle(TrackEvent::Start).await.unwrap();
+        log.handle(TrackEvent::Point(point(0))).await.unwrap();
+        log.handle(TrackEvent::Flush).await.unwrap();
+        log.handle(TrackEvent::Stop).await.unwrap();
+    });
+    assert_eq!(points(&mut log, 1), Some(vec![point(0)]));
+    assert_eq!(sessions(&mut log).len(), 1);
+}
+
+#[test]
+fn active_session_cannot_be_deleted() {
+    let flash = RamFlash::new(4);
+    let mut log = open(&flash);
+    record(&mut log, 2);
+    block_on(log.start_session()).unwrap();
+    assert!(matches!(
+        block_on(log.delete_session(2)),
+        Err(TrackLogError::SessionActive)
+    ));
+    assert!(block_on(log.delete_session(1)).unwrap());
+    assert_eq!(points(&mut log, 1), None);
+}
+
+#[test]
+fn full_queue_overwrites_the_oldest_sessions() {
+    let flash = RamFlash::new(2);
+    let mut log = open(&flash);
+    // Far more than two pages hold.
+    for session in 1..=20 {
+        assert_eq!(record(&mut log, 40), session);
+    }
+
+    let summaries = sessions(&mut log);
+    let oldest = summaries.first().unwrap().session;
+    assert!(oldest > 1, "oldest session {oldest} survived");
+    let newest = summaries.last().unwrap();
+    assert_eq!(newest.session, 20);
+    assert_eq!(newest.points, 40);
+    assert!(newest.closed);
+
+    // The log reopens on the wrapped queue and keeps counting.
+    drop(log);
+    let mut log = open(&flash);
+    assert_eq!(log.last_session(), 20);
+    assert_eq!(record(&mut log, 1), 21);
+    assert_eq!(points(&mut log, 21), Some(vec![point(0)]));
+}
+
+#[test]
+fn batches_read_back_everything_in_order() {
+    let flash = RamFlash::new(4);
+    let mut log = open(&flash);
+    for count in [3, 10, 7] {
+        record(&mut log, count);
+    }
+    let session = record(&mut log, 10);
+
+    let mut batch = heapless::Vec::<SessionEntry, 4>::new();
+    let mut entries = Vec::new();
+    let mut skip = 0;
+    loop {
+        assert!(block_on(log.entries_batch(session, skip, &mut batch)).unwrap());
+        entries.extend_from_slice(&batch);
+        if !batch.is_full() {
+            break;
+        }
+        skip += batch.len();
+    }
+    let expected: Vec<_> = (0..10).map(|i| SessionEntry::Point(point(i))).collect();
+    assert_eq!(entries, expected);
+    // The last batch comes back short.
+    assert!(block_on(log.entries_batch(session, 8, &mut batch)).unwrap());
+    assert_eq!(batch.len(), 2);
+    assert!(!block_on(log.entries_batch(99, 0, &mut batch)).unwrap());
+    assert!(batch.is_empty());
+
+    let mut summaries = heapless::Vec::<SessionSummary, 3>::new();
+    block_on(log.sessions_batch(0, &mut summaries)).unwrap();
+    assert_eq!(summaries.as_slice(), &sessions(&mut log)[..3]);
+    block_on(log.sessions_batch(3, &mut summaries)).unwrap();
+    assert_eq!(summaries.as_slice(), &sessions(&mut log)[3..]);
+}
+
+#[test]
+fn points_with_and_without_a_date_share_one_tag() {
+    let mut buf = [0u8; MAX_RECORD_LEN];
+    for point in [
+        point(1),
+        TrackPoint {
+            date: None,
+            ..point(2)
+        },
+    ] {
+        let record = TrackRecord::Point(point);
+        let bytes = record.encode(&mut buf).unwrap();
+        assert_eq!(bytes[0], 0x04);
+        assert_eq!(TrackRecord::decode(bytes), Ok(record));
+    }
+    assert_eq!(
+        TrackRecord::decode(&[0x02]),
+        Err(RecordError::UnknownTag(0x02))
+    );
+}
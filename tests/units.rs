@@ -0,0 +1,55 @@
+use hijo::utils::units::Units;
+
+fn shown(measure: impl core::fmt::Display) -> String {
+    format!("{measure}")
+}
+
+#[test]
+fn distance_switches_to_the_long_unit() {
+    assert_eq!(shown(Units::Imperial.distance(100.0)), "328 ft");
+    assert_eq!(shown(Units::Imperial.distance(3218.688)), "2.000 mi");
+    assert_eq!(shown(Units::Metric.distance(999.0)), "999 m");
+    assert_eq!(shown(Units::Metric.distance(1500.0)), "1.500 km");
+    assert_eq!(shown(Units::Nautical.distance(3704.0)), "2.00 nm");
+}
+
+#[test]
+fn speed_and_altitude() {
+    assert_eq!(shown(Units::Imperial.speed(4.4704)), "10.00 mph");
+    assert_eq!(shown(Units::Metric.speed(10.0)), "36.0 kph");
+    assert_eq!(
+        shown(Units::Nautical.speed(1852.0 / 3600.0 * 12.0)),
+        "12.0 kn"
+    );
+    assert_eq!(shown(Units::Imperial.altitude(1000.0)), "3281 ft");
+    assert_eq!(shown(Units::Nautical.altitude(1000.0)), "1000 m");
+}
+
+#[test]
+fn pace_needs_some_motion() {
+    assert_eq!(Units::Metric.pace(0.2), None);
+    assert_eq!(
+        shown(Units::Metric.pace(1000.0 / 300.0).unwrap()),
+        "5:00/km"
+    );
+    assert_eq!(
+        shown(Units::Imperial.pace(1609.344 / 485.0).unwrap()),
+        "8:05/mi"
+    );
+}
+
+#[test]
+fn unknown_setting_falls_back_to_imperial() {
+    assert_eq!(Units::from_setting(1), Units::Metric);
+    assert_eq!(Units::from_setting(2), Units::Nautical);
+    assert_eq!(Units::from_setting(7), Units::Imperial);
+}
+
+#[test]
+fn scale_bar_picks_the_longest_round_length_that_fits() {
+    assert_eq!(Units::Metric.scale_bar(140.0), (100.0, "100 m"));
+    assert_eq!(Units::Metric.scale_bar(1500.0), (1000.0, "1 km"));
+    assert_eq!(Units::Metric.scale_bar(1.0), (5.0, "5 m"));
+    assert_eq!(Units::Imperial.scale_bar(2000.0).1, "1 mi");
+    assert_eq!(Units::Nautical.scale_bar(4000.0).1, "2 nm");
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use common::at;
+use hijo::gps::{
+    fns::LatLonAlt,
+    waypoint::{ENCODED_LEN, MAX_WAYPOINTS, Waypoint, WaypointList},
+};
+
+#[test]
+fn encoding_round_trips() {
+    let waypoint = Waypoint::new(
+        "Trailhead",
+        LatLonAlt {
+            latitude: 45.5231234,
+            longitude: -122.6765432,
+            altitude: 15.5,
+        },
+    );
+    let mut buf = [0u8; ENCODED_LEN];
+    let bytes = waypoint.encode(&mut buf);
+    assert_eq!(bytes.len(), 21 + "Trailhead".len());
+    assert_eq!(Waypoint::decode(bytes), Some(waypoint));
+    assert_eq!(Waypoint::decode(&bytes[..25]), None);
+}
+
+#[test]
+fn long_names_are_cut_at_a_character() {
+    assert_eq!(
+        Waypoint::new("Mount Hood summit", at(0.0, 0.0)).name,
+        "Mount Hood s"
+    );
+    assert_eq!(
+        Waypoint::new("Café Müllerstraße", at(0.0, 0.0)).name,
+        "Café Mülle"
+    );
+}
+
+#[test]
+fn cursor_skips_empty_slots_and_wraps() {
+    let mut list = WaypointList::new();
+    assert_eq!(list.cursor(), None);
+    list.set(2, Waypoint::new("a", at(1.0, 1.0)));
+    list.set(5, Waypoint::new("b", at(2.0, 2.0)));
+    list.set(9, Waypoint::new("c", at(3.0, 3.0)));
+    assert_eq!(list.cursor(), Some(2));
+
+    list.next();
+    assert_eq!(list.cursor(), Some(5));
+    list.next();
+    list.next();
+    assert_eq!(list.cursor(), Some(2));
+    list.previous();
+    assert_eq!(list.cursor(), Some(9));
+
+    list.toggle_target();
+    assert_eq!(list.target().map(|w| w.name.as_str()), Some("c"));
+    list.toggle_target();
+    assert_eq!(list.target(), None);
+
+    list.toggle_target();
+    list.remove(9);
+    assert_eq!(list.target(), None);
+    assert_eq!(list.cursor(), Some(2));
+    assert_eq!(list.len(), 2);
+}
+
+#[test]
+fn insert_fills_gaps_and_names_count_up() {
+    let mut list = WaypointList::new();
+    assert_eq!(list.next_name(), "WP01");
+    for _ in 0..3 {
+        let name = list.next_name();
+        list.insert(Waypoint::new(&name, at(0.0, 0.0)));
+    }
+    assert_eq!(list.next_name(), "WP04");
+
+    list.remove(1);
+    assert_eq!(list.next_name(), "WP02");
+    assert_eq!(list.insert(Waypoint::new("x", at(0.0, 0.0))), Some(1));
+
+    while list.len() < MAX_WAYPOINTS {
+        list.insert(Waypoint::new("y", at(0.0, 0.0)));
+    }
+    assert_eq!(list.insert(Waypoint::new("z", at(0.0, 0.0))), None);
+}